@@ -9,4 +9,25 @@ pub struct AppSettings {
     pub public_url: String,
     pub request_origin: SecretString,
     pub cookie_key: SecretString,
+    #[serde(default)]
+    pub dialogue_storage: DialogueStorage,
+    /// wizards left untouched for this many minutes expire.
+    #[serde(default = "default_wizard_ttl_minutes")]
+    pub wizard_ttl_minutes: u32,
+}
+
+fn default_wizard_ttl_minutes() -> u32 {
+    30
+}
+
+/// Where the bot keeps the `/chat` mode and the wizard page of each chat.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DialogueStorage {
+    /// the chat mode is kept in the `dialogues` table and the wizard pages in
+    /// the `wizards` table, so that they survive restarts.
+    #[default]
+    Postgres,
+    /// lost whenever the bot restarts.
+    Memory,
 }
@@ -2,6 +2,8 @@ pub mod app;
 pub mod database;
 pub mod email;
 pub mod environment;
+pub mod llm;
+pub mod reminder;
 pub mod stickers;
 
 use app::AppSettings;
@@ -13,6 +15,8 @@ use figment::{
     providers::{Env, Format, Yaml},
     Figment,
 };
+use llm::LlmSettings;
+use reminder::ReminderSettings;
 use serde::Deserialize;
 use stickers::Stickers;
 use tracing::level_filters::LevelFilter;
@@ -25,6 +29,8 @@ pub struct Settings {
     pub email: EmailSettings,
     pub database: DatabaseSettings,
     pub stickers: Stickers,
+    pub reminder: ReminderSettings,
+    pub llm: LlmSettings,
 }
 
 pub fn get_settings(env: &Environment) -> Result<Settings, figment::Error> {
@@ -0,0 +1,69 @@
+use secrecy::SecretString;
+use serde::Deserialize;
+
+/// The defaults of the chat with the bot. chats may change some of them with
+/// `/llmconfig`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LlmSettings {
+    #[serde(default)]
+    pub provider: LlmProvider,
+    pub model: String,
+    /// base url of the openai-compatible server, eg. `http://localhost:11434/v1`
+    /// for ollama. only used by the `compatible` provider.
+    pub base_url: Option<String>,
+    /// api key of the openai-compatible server, if it needs one.
+    /// openai itself reads `OPENAI_API_KEY`.
+    pub api_key: Option<SecretString>,
+    pub temperature: f32,
+    /// number of tokens from the model's response
+    pub max_tokens: u16,
+    /// number of past chat messages sent along with each message.
+    pub history_count: u16,
+    /// past chat messages older than this many minutes are left out.
+    pub history_minutes: u32,
+    /// the models which chats may pick besides `model`.
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+    /// the most `max_tokens` a chat may pick.
+    #[serde(default = "default_max_tokens_limit")]
+    pub max_tokens_limit: u16,
+    /// the most `history_count` a chat may pick.
+    #[serde(default = "default_history_count_limit")]
+    pub history_count_limit: u16,
+    /// the most `history_minutes` a chat may pick.
+    #[serde(default = "default_history_minutes_limit")]
+    pub history_minutes_limit: u32,
+}
+
+impl LlmSettings {
+    /// whether chats may pick the model.
+    #[must_use]
+    pub fn is_allowed_model(&self, model: &str) -> bool {
+        model == self.model || self.allowed_models.iter().any(|x| x == model)
+    }
+}
+
+fn default_max_tokens_limit() -> u16 {
+    4096
+}
+
+fn default_history_count_limit() -> u16 {
+    100
+}
+
+/// one day
+fn default_history_minutes_limit() -> u32 {
+    1440
+}
+
+/// Who the bot chats through.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmProvider {
+    #[default]
+    OpenAI,
+    /// any server speaking the openai api, eg. llama.cpp, ollama or vllm.
+    Compatible,
+    /// replies without a model, for trying out the chat offline.
+    Mock,
+}
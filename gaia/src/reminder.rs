@@ -0,0 +1,8 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReminderSettings {
+    /// reminders which came due while the bot was down are still sent on startup
+    /// when they are at most this many minutes late. older ones are marked as missed.
+    pub grace_period_minutes: u32,
+}
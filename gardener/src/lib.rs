@@ -6,12 +6,13 @@ use axum_login::tower_sessions::ExpiredDeletion;
 use gaia::Settings;
 use sqlx::PgPool;
 use teloxide::Bot;
+use tokio_cron_scheduler::JobScheduler;
 use tower_sessions_sqlx_store::PostgresStore;
 
 use crate::routes::app_router;
 
 #[tracing::instrument(skip_all, name = "gardener")]
-pub async fn start_app(settings: Settings, pool: PgPool, bot: Bot) {
+pub async fn start_app(settings: Settings, pool: PgPool, bot: Bot, sched: JobScheduler) {
     let address = format!(
         "{}:{}",
         settings.application.host, settings.application.web_port
@@ -41,7 +42,7 @@ pub async fn start_app(settings: Settings, pool: PgPool, bot: Bot) {
             .continuously_delete_expired(tokio::time::Duration::from_secs(1800)),
     );
 
-    let app_router = app_router(settings, session_store, pool, bot);
+    let app_router = app_router(settings, session_store, pool, bot, sched);
 
     axum::serve(listener, app_router.into_make_service())
         .await
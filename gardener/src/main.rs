@@ -2,7 +2,7 @@ use gaia::{environment::get_environment, get_connection_pool, get_settings, init
 use gardener::start_app;
 use teloxide::Bot;
 use tokio::signal;
-use turtle_bot::start_bot;
+use turtle_bot::{init_scheduler, start_bot};
 
 #[tokio::main]
 async fn main() {
@@ -14,8 +14,18 @@ async fn main() {
 
     let bot = Bot::from_env();
 
-    let bot_app = tokio::spawn(start_bot(bot.clone(), env, settings.clone(), pool.clone()));
-    let web_app = tokio::spawn(start_app(settings, pool, bot));
+    let sched = init_scheduler(&bot, &settings.stickers, &pool, &settings.reminder)
+        .await
+        .expect("cannot initialize scheduler");
+
+    let bot_app = tokio::spawn(start_bot(
+        bot.clone(),
+        env,
+        settings.clone(),
+        pool.clone(),
+        sched.clone(),
+    ));
+    let web_app = tokio::spawn(start_app(settings, pool, bot, sched));
 
     tokio::select! {
         _ = signal::ctrl_c() => tracing::info!("ctrl-c received"),
@@ -15,6 +15,7 @@ use gaia::Settings;
 use secrecy::ExposeSecret;
 use sqlx::PgPool;
 use teloxide::Bot;
+use tokio_cron_scheduler::JobScheduler;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, trace::TraceLayer};
 use tower_sessions::{
@@ -65,14 +66,17 @@ pub struct AppState {
     pool: PgPool,
     bot: Bot,
     email_client: EmailClient,
+    /// the scheduler of the bot's reminders and greetings.
+    sched: JobScheduler,
 }
 
 impl AppState {
-    pub fn new(pool: PgPool, bot: Bot, email_client: EmailClient) -> Self {
+    pub fn new(pool: PgPool, bot: Bot, email_client: EmailClient, sched: JobScheduler) -> Self {
         Self {
             pool,
             bot,
             email_client,
+            sched,
         }
     }
 }
@@ -82,6 +86,7 @@ pub fn app_router(
     session_store: PostgresStore,
     pool: PgPool,
     bot: Bot,
+    sched: JobScheduler,
 ) -> Router {
     let cors_layer = CorsLayer::new()
         .allow_origin([settings
@@ -133,7 +138,7 @@ pub fn app_router(
 
     let email_client = EmailClient::new(settings.email);
 
-    let app_state = AppState::new(pool, bot, email_client);
+    let app_state = AppState::new(pool, bot, email_client, sched);
 
     Router::new()
         .merge(SwaggerUi::new("/docs").url("/docs.json", ApiDoc::openapi()))
@@ -1,9 +1,9 @@
 use anyhow::Context;
 use axum::{
     body::Bytes,
-    extract::{DefaultBodyLimit, Path, State},
+    extract::{DefaultBodyLimit, Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
@@ -23,6 +23,12 @@ use crate::auth::{AuthSession, Backend};
 
 use super::AppState;
 
+pub mod calendar;
+pub mod reminders;
+pub mod whispers;
+
+use whispers::{schedule_whisper, WhisperSchedule};
+
 #[derive(thiserror::Error, Debug)]
 pub enum TelegramError {
     #[error(transparent)]
@@ -48,6 +54,12 @@ pub enum TelegramError {
 
     #[error("doc is not image type")]
     NotImage,
+
+    #[error("invalid reminder: {0}")]
+    InvalidReminder(&'static str),
+
+    #[error("invalid schedule: {0}")]
+    InvalidSchedule(&'static str),
 }
 
 impl IntoResponse for TelegramError {
@@ -67,6 +79,9 @@ impl IntoResponse for TelegramError {
             Self::NotLoggedIn => (StatusCode::UNAUTHORIZED, "user is unauthorized".to_owned()),
             Self::NotFound => (StatusCode::NOT_FOUND, "resource(s) not found".to_owned()),
             Self::ExpiredToken => (StatusCode::GONE, "token has expired".to_owned()),
+            Self::InvalidReminder(e) | Self::InvalidSchedule(e) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, e.to_owned())
+            }
             Self::TeloxideError(e) => {
                 tracing::error!("{e:#?}");
                 (
@@ -87,17 +102,22 @@ impl IntoResponse for TelegramError {
 }
 
 /// sends message to telegram chat
+///
+/// with `sendAt`, the message is scheduled to be sent at that time instead.
 #[utoipa::path(
     post,
     tag = "telegram",
     path = "/telegram/message/{id}",
     params(
-        ("id", description="id of chat")
+        ("id", description="id of chat"),
+        WhisperSchedule
     ),
     request_body(content=String, description="message to send"),
     responses(
         (status = StatusCode::OK, description = "message sent"),
-        (status = StatusCode::NOT_FOUND, description = "room does not exist or bot is not in chat")
+        (status = StatusCode::ACCEPTED, body = ScheduledWhisperCreated, description = "message scheduled"),
+        (status = StatusCode::NOT_FOUND, description = "room does not exist or bot is not in chat"),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "sendAt is not in the future")
     )
 )]
 #[allow(deprecated)]
@@ -105,14 +125,20 @@ pub async fn send_tele_msg(
     auth_session: AuthSession,
     State(app): State<AppState>,
     Path(chat_id): Path<i64>,
+    Query(schedule): Query<WhisperSchedule>,
     msg: String,
-) -> Result<(), TelegramError> {
-    let pool = app.pool;
+) -> Result<Response, TelegramError> {
     let user_id = auth_session
         .user
         .context("user is using protected api")?
         .user_id;
 
+    if let Some(send_at) = schedule.send_at {
+        return schedule_whisper(&app, user_id, chat_id, Some(msg), Vec::new(), send_at).await;
+    }
+
+    let pool = app.pool;
+
     let exists = sqlx::query_scalar!(
         "select exists (
         select * from telegram_whisperers as a
@@ -154,7 +180,7 @@ pub async fn send_tele_msg(
     }
 
     msg_result?;
-    Ok(())
+    Ok(StatusCode::OK.into_response())
 }
 
 #[utoipa::path(
@@ -326,63 +352,82 @@ pub struct MediaUpload {
     #[form_data(limit = "unlimited")]
     media: Vec<FieldData<Bytes>>,
 }
+fn is_image(upload: &FieldData<Bytes>) -> bool {
+    upload
+        .metadata
+        .content_type
+        .as_deref()
+        .unwrap_or_default()
+        .starts_with("image/")
+}
+
+/// sends photos to telegram chat
+///
+/// with `sendAt`, the photos are scheduled to be sent at that time instead.
 #[utoipa::path(
     post,
     tag = "telegram",
     path = "/telegram/media/{id}",
     params(
-        ("id", description="id of chat")
+        ("id", description="id of chat"),
+        WhisperSchedule
     ),
     request_body(content_type = "multipart/form-data", content = MediaUpload),
     responses(
-        (status = 200, description = "media uploaded")
+        (status = 200, description = "media uploaded"),
+        (status = 202, body = ScheduledWhisperCreated, description = "media scheduled"),
+        (status = 403, description = "user is not a whisperer in chat"),
+        (status = 422, description = "sendAt is not in the future")
     )
 )]
 #[tracing::instrument(skip_all)]
 pub async fn send_tele_media(
+    auth_session: AuthSession,
     State(app): State<AppState>,
     Path(chat_id): Path<i64>,
+    Query(schedule): Query<WhisperSchedule>,
     TypedMultipart(data): TypedMultipart<MediaUpload>,
-) -> Result<(), TelegramError> {
-    let bot = app.bot;
-
-    let mut media_vec: Vec<InputMedia> = Vec::new();
+) -> Result<Response, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+    reminders::whisperer_username(&app.pool, user_id, chat_id).await?;
 
-    let mut wow = data.media.into_iter();
-    let first_media = wow.next().context("no upload available")?;
+    let mut uploads = data.media.into_iter();
+    let first_media = uploads.next().context("no upload available")?;
 
-    if first_media
-        .metadata
-        .content_type
-        .unwrap_or_default()
-        .starts_with("image/")
-    {
-        media_vec.push(InputMedia::Photo(
-            InputMediaPhoto::new(InputFile::memory(first_media.contents))
-                .caption(data.caption.unwrap_or_default()),
-        ));
-    } else {
+    if !is_image(&first_media) {
         return Err(TelegramError::NotImage);
     }
+    let photos: Vec<Bytes> = std::iter::once(first_media)
+        .chain(uploads.filter(is_image))
+        .map(|x| x.contents)
+        .collect();
+
+    if let Some(send_at) = schedule.send_at {
+        let photos = photos.into_iter().map(Vec::from).collect();
+        return schedule_whisper(&app, user_id, chat_id, data.caption, photos, send_at).await;
+    }
 
-    media_vec.append(
-        &mut wow
-            .filter(|x| {
-                x.metadata
-                    .clone()
-                    .content_type
-                    .unwrap_or_default()
-                    .starts_with("image/")
+    let mut caption = Some(data.caption.unwrap_or_default());
+    let media_vec: Vec<InputMedia> = photos
+        .into_iter()
+        .map(|x| {
+            let photo = InputMediaPhoto::new(InputFile::memory(x));
+            InputMedia::Photo(match caption.take() {
+                Some(caption) => photo.caption(caption),
+                None => photo,
             })
-            .map(|x| InputMedia::Photo(InputMediaPhoto::new(InputFile::memory(x.contents))))
-            .collect(),
-    );
+        })
+        .collect();
 
-    bot.send_media_group(ChatId(chat_id), media_vec)
+    app.bot
+        .send_media_group(ChatId(chat_id), media_vec)
         .await
         .context("error sending photos")?;
 
-    Ok(())
+    Ok(StatusCode::OK.into_response())
 }
 
 pub fn tele_router() -> Router<AppState> {
@@ -392,6 +437,9 @@ pub fn tele_router() -> Router<AppState> {
             "/media/:chat_id",
             post(send_tele_media).layer(DefaultBodyLimit::max(50 * 1024 * 1024)),
         )
+        .nest("/reminders", reminders::reminder_router())
+        .nest("/calendar", calendar::calendar_router())
+        .nest("/scheduled", whispers::scheduled_router())
         .route_layer(predicate_required!(
             is_telegram_user,
             (StatusCode::UNAUTHORIZED, "not verified").to_owned()
@@ -404,6 +452,7 @@ pub fn tele_router() -> Router<AppState> {
 
     Router::new()
         .route("/check-user", get(check_if_verified))
+        .route("/calendar/:file", get(calendar::calendar_feed))
         .merge(verified_user_routes)
         .merge(need_log_in_routes)
 }
@@ -0,0 +1,218 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use rand::{
+    distributions::{Alphanumeric, DistString},
+    thread_rng,
+};
+use serde::Serialize;
+use sqlx::types::time::OffsetDateTime;
+use turtle_bot::{calendar_events, to_ics};
+use utoipa::ToSchema;
+
+use crate::auth::AuthSession;
+
+use super::{AppState, TelegramError};
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    token: String,
+    /// path of the `.ics` feed, to be subscribed to by calendar apps
+    path: String,
+    #[serde(with = "time::serde::rfc3339")]
+    created_at: OffsetDateTime,
+}
+
+impl CalendarFeed {
+    fn new(token: String, created_at: OffsetDateTime) -> Self {
+        Self {
+            path: format!("/telegram/calendar/{token}.ics"),
+            token,
+            created_at,
+        }
+    }
+}
+
+fn generate_feed_token() -> String {
+    let mut rng = thread_rng();
+    Alphanumeric.sample_string(&mut rng, 32)
+}
+
+#[utoipa::path(
+    get,
+    tag = "telegram",
+    path = "/telegram/calendar",
+    responses(
+        (status = 200, body = Option<CalendarFeed>, description = "calendar feed of the user, if any"),
+        (status = 401, description = "user is not verified"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn get_calendar_feed(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+) -> Result<Json<Option<CalendarFeed>>, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let feed = sqlx::query!(
+        "select feed_token, created_at from telegram_calendar_feeds where user_id = $1",
+        user_id
+    )
+    .fetch_optional(&app.pool)
+    .await
+    .context("error getting calendar feed")?
+    .map(|x| CalendarFeed::new(x.feed_token, x.created_at));
+    Ok(Json(feed))
+}
+
+/// creates the calendar feed of the user, or replaces its token so that
+/// the old feed url stops working.
+#[utoipa::path(
+    post,
+    tag = "telegram",
+    path = "/telegram/calendar",
+    responses(
+        (status = 201, body = CalendarFeed, description = "calendar feed created with a new token"),
+        (status = 401, description = "user is not verified"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn rotate_calendar_feed(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+) -> Result<(StatusCode, Json<CalendarFeed>), TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let token = generate_feed_token();
+    let created_at = OffsetDateTime::now_utc();
+    sqlx::query!(
+        "insert into telegram_calendar_feeds (user_id, feed_token, created_at)
+        values ($1, $2, $3)
+        on conflict (user_id) do update
+        set feed_token = excluded.feed_token, created_at = excluded.created_at",
+        user_id,
+        token,
+        created_at
+    )
+    .execute(&app.pool)
+    .await
+    .context("error saving calendar feed token")?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CalendarFeed::new(token, created_at)),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    tag = "telegram",
+    path = "/telegram/calendar",
+    responses(
+        (status = 204, description = "calendar feed revoked"),
+        (status = 401, description = "user is not verified"),
+        (status = 404, description = "user has no calendar feed"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn revoke_calendar_feed(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+) -> Result<StatusCode, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let revoked = sqlx::query!(
+        "delete from telegram_calendar_feeds where user_id = $1",
+        user_id
+    )
+    .execute(&app.pool)
+    .await
+    .context("error revoking calendar feed")?
+    .rows_affected();
+
+    if revoked == 0 {
+        return Err(TelegramError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// reminders of every chat the owner of the token whispers in, as an iCalendar feed.
+///
+/// the token is the only credential, so calendar apps can subscribe without logging in.
+#[utoipa::path(
+    get,
+    tag = "telegram",
+    path = "/telegram/calendar/{token}.ics",
+    params(
+        ("token", description = "calendar feed token")
+    ),
+    responses(
+        (status = 200, content_type = "text/calendar", body = String, description = "iCalendar feed of reminders"),
+        (status = 404, description = "token does not exist or has been revoked"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+pub async fn calendar_feed(
+    State(app): State<AppState>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, TelegramError> {
+    let token = file.strip_suffix(".ics").ok_or(TelegramError::NotFound)?;
+
+    let user_id = sqlx::query_scalar!(
+        "select user_id from telegram_calendar_feeds where feed_token = $1",
+        token
+    )
+    .fetch_optional(&app.pool)
+    .await
+    .context("error getting calendar feed")?
+    .ok_or(TelegramError::NotFound)?;
+
+    let chat_ids = sqlx::query_scalar!(
+        "select distinct a.telegram_chat_id
+        from telegram_whisperers as a
+        inner join telegram_users as b on b.telegram_user_id = a.telegram_user_id
+        where b.user_id = $1",
+        user_id
+    )
+    .fetch_all(&app.pool)
+    .await
+    .context("error getting chats of calendar feed")?;
+
+    let now = OffsetDateTime::now_utc();
+    let export = calendar_events(&app.pool, &chat_ids, now)
+        .await
+        .context("error getting calendar events")?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        to_ics("🐢 Reminders", &export.events, now),
+    ))
+}
+
+pub fn calendar_router() -> Router<AppState> {
+    Router::new().route(
+        "/",
+        get(get_calendar_feed)
+            .post(rotate_calendar_feed)
+            .delete(revoke_calendar_feed),
+    )
+}
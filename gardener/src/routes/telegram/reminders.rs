@@ -0,0 +1,445 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, patch},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{types::time::OffsetDateTime, PgPool};
+use teloxide::types::ChatId;
+use turtle_bot::{
+    add_one_off_reminder, add_recurring_reminder, cancel_reminder, parse_cron, reschedule_reminder,
+    update_recurring_reminder, NewReminder, ReminderKind, ReminderMedia, ReminderStatus,
+};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::auth::AuthSession;
+
+use super::{AppState, TelegramError};
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReminderType {
+    OneOff,
+    Recurring,
+}
+
+impl From<ReminderType> for ReminderKind {
+    fn from(value: ReminderType) -> Self {
+        match value {
+            ReminderType::OneOff => ReminderKind::OneOff,
+            ReminderType::Recurring => ReminderKind::Recurring,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderInfo {
+    id: i32,
+    reminder_type: ReminderType,
+    chat_id: i64,
+    chat_title: Option<String>,
+    message: String,
+    username: Option<String>,
+    /// only for one-off reminders
+    #[serde(with = "time::serde::rfc3339::option")]
+    due: Option<OffsetDateTime>,
+    /// only for recurring reminders
+    cron_str: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderFilter {
+    /// only list the reminders of this chat
+    chat_id: Option<i64>,
+}
+
+/// either `due` or `cronStr` has to be given.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderCreate {
+    chat_id: i64,
+    message: String,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    due: Option<OffsetDateTime>,
+    /// cron expression with 5 to 7 fields, following the chat's time zone
+    cron_str: Option<String>,
+    /// only for one-off reminders, which are then sent during the chat's quiet hours
+    #[serde(default)]
+    urgent: bool,
+}
+
+/// fields that are left out stay the same.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderUpdate {
+    message: Option<String>,
+    /// only for one-off reminders
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    due: Option<OffsetDateTime>,
+    /// only for recurring reminders
+    cron_str: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderCreated {
+    id: i32,
+    reminder_type: ReminderType,
+}
+
+/// the telegram username of the user, if they are a whisperer in the chat.
+pub(super) async fn whisperer_username(
+    pool: &PgPool,
+    user_id: Uuid,
+    chat_id: i64,
+) -> Result<Option<String>, TelegramError> {
+    let whisperer = sqlx::query!(
+        "select b.telegram_username
+        from telegram_whisperers as a
+        inner join telegram_users as b on b.telegram_user_id = a.telegram_user_id
+        where b.user_id = $1 and a.telegram_chat_id = $2",
+        user_id,
+        chat_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("error checking if user is a whisperer in chat")?
+    .ok_or(TelegramError::UserNotInChat)?;
+
+    Ok(whisperer.telegram_username)
+}
+
+/// the chat of the reminder which can still be changed.
+async fn reminder_chat(
+    pool: &PgPool,
+    reminder_type: ReminderType,
+    id: i32,
+) -> Result<i64, TelegramError> {
+    let target = match reminder_type {
+        ReminderType::OneOff => {
+            sqlx::query_scalar!(
+                "select target from jobs_one_off
+            where id = $1 and status = $2 and due > now()",
+                id,
+                ReminderStatus::Pending.as_str()
+            )
+            .fetch_optional(pool)
+            .await
+        }
+        ReminderType::Recurring => {
+            sqlx::query_scalar!(
+                "select target from jobs_cron
+            where id = $1 and type = 'recurring-reminder' and cancelled = false",
+                id
+            )
+            .fetch_optional(pool)
+            .await
+        }
+    }
+    .context("error getting reminder")?;
+
+    target.ok_or(TelegramError::NotFound)
+}
+
+fn future_due(due: OffsetDateTime) -> Result<OffsetDateTime, TelegramError> {
+    if due <= OffsetDateTime::now_utc() {
+        return Err(TelegramError::InvalidReminder("due must be in the future"));
+    }
+    Ok(due)
+}
+
+fn valid_cron(cron_str: &str) -> Result<String, TelegramError> {
+    parse_cron(cron_str).ok_or(TelegramError::InvalidReminder("invalid cron expression"))
+}
+
+#[utoipa::path(
+    get,
+    tag = "telegram",
+    path = "/telegram/reminders",
+    params(ReminderFilter),
+    responses(
+        (status = 200, body = Vec<ReminderInfo>, description = "pending reminders of chats the user whispers in"),
+        (status = 401, description = "user is not verified"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn list_reminders(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+    Query(filter): Query<ReminderFilter>,
+) -> Result<Json<Vec<ReminderInfo>>, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let reminders = sqlx::query!(
+        r#"select
+        j.id as "id!",
+        j.reminder_type as "reminder_type!",
+        j.target as "target!",
+        c.title,
+        j.message as "message!",
+        j.username,
+        j.due,
+        j.cron_str
+        from (
+            select id, 'one-off' as reminder_type, target, message, username, due, null as cron_str
+            from jobs_one_off
+            where status = $3 and due > now()
+            union all
+            select id, 'recurring', target, message, username, null, cron_str
+            from jobs_cron
+            where type = 'recurring-reminder' and cancelled = false
+        ) as j
+        inner join chatrooms as c on c.id = j.target
+        inner join telegram_whisperers as w on w.telegram_chat_id = j.target
+        inner join telegram_users as u on u.telegram_user_id = w.telegram_user_id
+        where u.user_id = $1 and ($2::bigint is null or j.target = $2)
+        order by j.target, j.due nulls last, j.id
+        "#,
+        user_id,
+        filter.chat_id,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_all(&app.pool)
+    .await
+    .context("can't retrieve reminders")?
+    .into_iter()
+    .map(|x| ReminderInfo {
+        id: x.id,
+        reminder_type: if x.reminder_type == "one-off" {
+            ReminderType::OneOff
+        } else {
+            ReminderType::Recurring
+        },
+        chat_id: x.target,
+        chat_title: x.title,
+        message: x.message,
+        username: x.username,
+        due: x.due,
+        cron_str: x.cron_str,
+    })
+    .collect();
+
+    Ok(Json(reminders))
+}
+
+#[utoipa::path(
+    post,
+    tag = "telegram",
+    path = "/telegram/reminders",
+    request_body = ReminderCreate,
+    responses(
+        (status = 201, body = ReminderCreated, description = "reminder scheduled"),
+        (status = 401, description = "user is not verified"),
+        (status = 403, description = "user is not a whisperer in chat"),
+        (status = 422, description = "invalid due datetime or cron expression"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn create_reminder(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+    Json(body): Json<ReminderCreate>,
+) -> Result<(StatusCode, Json<ReminderCreated>), TelegramError> {
+    let user = auth_session.user.context("user is using protected api")?;
+    let username = whisperer_username(&app.pool, user.user_id, body.chat_id)
+        .await?
+        .unwrap_or(user.username);
+
+    let created = match (body.due, body.cron_str) {
+        (Some(due), None) => {
+            let reminder = NewReminder {
+                target: body.chat_id,
+                username,
+                message: body.message,
+                due: future_due(due)?,
+                media: None,
+                snoozed_from: None,
+                assignees: Vec::new(),
+                deliver_dm: false,
+                lead_times: Vec::new(),
+                urgent: body.urgent,
+            };
+            let id = add_one_off_reminder(&app.bot, &app.pool, &app.sched, reminder)
+                .await
+                .context("error adding one-off reminder")?;
+            ReminderCreated {
+                id,
+                reminder_type: ReminderType::OneOff,
+            }
+        }
+        (None, Some(cron_str)) => {
+            let id = add_recurring_reminder(
+                &app.bot,
+                &app.pool,
+                &app.sched,
+                body.chat_id,
+                Some(username),
+                body.message,
+                &valid_cron(&cron_str)?,
+            )
+            .await
+            .context("error adding recurring reminder")?;
+            ReminderCreated {
+                id,
+                reminder_type: ReminderType::Recurring,
+            }
+        }
+        _ => {
+            return Err(TelegramError::InvalidReminder(
+                "either due or cronStr must be given",
+            ))
+        }
+    };
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[utoipa::path(
+    patch,
+    tag = "telegram",
+    path = "/telegram/reminders/{reminder_type}/{id}",
+    params(
+        ("reminder_type" = ReminderType, description = "`one-off` or `recurring`"),
+        ("id", description = "id of reminder")
+    ),
+    request_body = ReminderUpdate,
+    responses(
+        (status = 200, description = "reminder rescheduled"),
+        (status = 401, description = "user is not verified"),
+        (status = 403, description = "user is not a whisperer in chat"),
+        (status = 404, description = "reminder does not exist or is no longer pending"),
+        (status = 422, description = "invalid due datetime or cron expression"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn update_reminder(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+    Path((reminder_type, id)): Path<(ReminderType, i32)>,
+    Json(body): Json<ReminderUpdate>,
+) -> Result<(), TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+    let chat_id = reminder_chat(&app.pool, reminder_type, id).await?;
+    whisperer_username(&app.pool, user_id, chat_id).await?;
+
+    match reminder_type {
+        ReminderType::OneOff => {
+            if body.cron_str.is_some() {
+                return Err(TelegramError::InvalidReminder(
+                    "one-off reminders have no cron expression",
+                ));
+            }
+            let current = sqlx::query!(
+                "select due, message, media_type, file_id from jobs_one_off where id = $1",
+                id
+            )
+            .fetch_one(&app.pool)
+            .await
+            .context("error getting one-off reminder")?;
+
+            let due = body.due.map_or(Ok(current.due), future_due)?;
+            let message = body.message.unwrap_or(current.message);
+            let media = ReminderMedia::from_columns(current.media_type.as_deref(), current.file_id);
+            reschedule_reminder(
+                &app.bot,
+                &app.pool,
+                &app.sched,
+                ChatId(chat_id),
+                id,
+                due,
+                &message,
+                media.as_ref(),
+            )
+            .await?;
+        }
+        ReminderType::Recurring => {
+            if body.due.is_some() {
+                return Err(TelegramError::InvalidReminder(
+                    "recurring reminders have no due datetime",
+                ));
+            }
+            let current = sqlx::query!("select cron_str, message from jobs_cron where id = $1", id)
+                .fetch_one(&app.pool)
+                .await
+                .context("error getting recurring reminder")?;
+
+            let cron_str = match body.cron_str {
+                Some(x) => valid_cron(&x)?,
+                None => current.cron_str,
+            };
+            let message = body.message.unwrap_or(current.message);
+            let updated = update_recurring_reminder(
+                &app.bot, &app.pool, &app.sched, chat_id, id, &cron_str, &message,
+            )
+            .await
+            .context("error updating recurring reminder")?;
+            if !updated {
+                return Err(TelegramError::NotFound);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    delete,
+    tag = "telegram",
+    path = "/telegram/reminders/{reminder_type}/{id}",
+    params(
+        ("reminder_type" = ReminderType, description = "`one-off` or `recurring`"),
+        ("id", description = "id of reminder")
+    ),
+    responses(
+        (status = 204, description = "reminder cancelled"),
+        (status = 401, description = "user is not verified"),
+        (status = 403, description = "user is not a whisperer in chat"),
+        (status = 404, description = "reminder does not exist or is no longer pending"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn delete_reminder(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+    Path((reminder_type, id)): Path<(ReminderType, i32)>,
+) -> Result<StatusCode, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+    let chat_id = reminder_chat(&app.pool, reminder_type, id).await?;
+    whisperer_username(&app.pool, user_id, chat_id).await?;
+
+    cancel_reminder(
+        &app.pool,
+        &app.sched,
+        ChatId(chat_id),
+        reminder_type.into(),
+        id,
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn reminder_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_reminders).post(create_reminder))
+        .route(
+            "/:reminder_type/:id",
+            patch(update_reminder).delete(delete_reminder),
+        )
+}
@@ -0,0 +1,151 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::time::OffsetDateTime;
+use turtle_bot::{add_scheduled_whisper, cancel_scheduled_whisper, NewWhisper, ReminderStatus};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::auth::AuthSession;
+
+use super::{reminders::whisperer_username, AppState, TelegramError};
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+pub struct WhisperSchedule {
+    /// send the whisper at this time, instead of right away
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub send_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledWhisperCreated {
+    id: i32,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledWhisperInfo {
+    id: i32,
+    chat_id: i64,
+    chat_title: Option<String>,
+    /// the text of the message, or the caption of the photos
+    message: Option<String>,
+    photo_count: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    send_at: OffsetDateTime,
+}
+
+/// Saves the whisper to be sent at `send_at` through the bot's scheduler.
+pub(super) async fn schedule_whisper(
+    app: &AppState,
+    user_id: Uuid,
+    chat_id: i64,
+    message: Option<String>,
+    photos: Vec<Vec<u8>>,
+    send_at: OffsetDateTime,
+) -> Result<Response, TelegramError> {
+    if send_at <= OffsetDateTime::now_utc() {
+        return Err(TelegramError::InvalidSchedule(
+            "sendAt must be in the future",
+        ));
+    }
+    whisperer_username(&app.pool, user_id, chat_id).await?;
+
+    let whisper = NewWhisper {
+        user_id,
+        chat_id,
+        message,
+        photos,
+        send_at,
+    };
+    let id = add_scheduled_whisper(&app.bot, &app.pool, &app.sched, whisper)
+        .await
+        .context("error scheduling whisper")?;
+    Ok((StatusCode::ACCEPTED, Json(ScheduledWhisperCreated { id })).into_response())
+}
+
+#[utoipa::path(
+    get,
+    tag = "telegram",
+    path = "/telegram/scheduled",
+    responses(
+        (status = 200, body = Vec<ScheduledWhisperInfo>, description = "pending scheduled whispers of the user"),
+        (status = 401, description = "user is not verified"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn list_scheduled_whispers(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+) -> Result<Json<Vec<ScheduledWhisperInfo>>, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let whispers = sqlx::query_as!(
+        ScheduledWhisperInfo,
+        r#"select a.id, a.telegram_chat_id as chat_id, b.title as "chat_title?", a.message,
+        cardinality(a.photos) as "photo_count!", a.send_at
+        from telegram_scheduled_whispers as a
+        left join chatrooms as b on b.id = a.telegram_chat_id
+        where a.user_id = $1 and a.status = $2
+        order by a.send_at"#,
+        user_id,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_all(&app.pool)
+    .await
+    .context("error getting scheduled whispers")?;
+
+    Ok(Json(whispers))
+}
+
+#[utoipa::path(
+    delete,
+    tag = "telegram",
+    path = "/telegram/scheduled/{id}",
+    params(
+        ("id", description = "id of scheduled whisper")
+    ),
+    responses(
+        (status = 204, description = "scheduled whisper cancelled"),
+        (status = 401, description = "user is not verified"),
+        (status = 404, description = "whisper does not exist or is no longer pending"),
+        (status = 505, description = "internal server error")
+    )
+)]
+#[tracing::instrument(skip_all)]
+async fn cancel_whisper(
+    auth_session: AuthSession,
+    State(app): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, TelegramError> {
+    let user_id = auth_session
+        .user
+        .context("user is using protected api")?
+        .user_id;
+
+    let cancelled = cancel_scheduled_whisper(&app.pool, &app.sched, user_id, id)
+        .await
+        .context("error cancelling scheduled whisper")?;
+    if !cancelled {
+        return Err(TelegramError::NotFound);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub fn scheduled_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_scheduled_whispers))
+        .route("/:id", delete(cancel_whisper))
+}
@@ -0,0 +1,2 @@
+alter table jobs_cron
+add column username text;
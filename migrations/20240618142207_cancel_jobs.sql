@@ -0,0 +1,5 @@
+alter table jobs_one_off
+add column cancelled boolean not null default false;
+
+alter table jobs_cron
+add column cancelled boolean not null default false;
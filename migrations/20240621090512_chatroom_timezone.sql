@@ -0,0 +1,2 @@
+ALTER TABLE chatrooms
+ADD COLUMN timezone TEXT NOT NULL DEFAULT 'Asia/Singapore';
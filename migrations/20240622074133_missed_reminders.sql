@@ -0,0 +1,2 @@
+ALTER TABLE jobs_one_off
+ADD COLUMN missed BOOLEAN NOT NULL DEFAULT false;
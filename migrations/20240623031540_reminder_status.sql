@@ -0,0 +1,27 @@
+BEGIN;
+
+ALTER TABLE jobs_one_off
+ADD COLUMN status TEXT NOT NULL DEFAULT 'pending',
+ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN last_error TEXT;
+
+UPDATE jobs_one_off
+SET
+  status = CASE
+    WHEN cancelled THEN 'cancelled'
+    WHEN completed THEN 'sent'
+    WHEN missed THEN 'missed'
+    ELSE 'pending'
+  END;
+
+ALTER TABLE jobs_one_off
+ADD CONSTRAINT jobs_one_off_status_check CHECK (
+  status IN ('pending', 'sent', 'failed', 'cancelled', 'missed')
+);
+
+ALTER TABLE jobs_one_off
+DROP COLUMN completed,
+DROP COLUMN cancelled,
+DROP COLUMN missed;
+
+COMMIT;
@@ -0,0 +1,2 @@
+ALTER TABLE jobs_one_off
+ADD COLUMN snoozed_from INTEGER REFERENCES jobs_one_off (id);
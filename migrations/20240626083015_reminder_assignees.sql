@@ -0,0 +1,17 @@
+CREATE TABLE chat_members (
+  chat_id BIGINT NOT NULL REFERENCES chatrooms (id),
+  user_id BIGINT NOT NULL,
+  PRIMARY KEY (chat_id, user_id),
+  username TEXT,
+  first_name TEXT NOT NULL,
+  updated_at timestamptz NOT NULL
+);
+
+CREATE TABLE jobs_one_off_assignees (
+  reminder_id INTEGER NOT NULL REFERENCES jobs_one_off (id),
+  user_id BIGINT NOT NULL,
+  PRIMARY KEY (reminder_id, user_id)
+);
+
+ALTER TABLE jobs_one_off
+ADD COLUMN deliver_dm BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,15 @@
+ALTER TABLE jobs_one_off
+ADD COLUMN media_type TEXT CHECK (
+  media_type IN (
+    'photo',
+    'video',
+    'animation',
+    'audio',
+    'document',
+    'voice',
+    'video_note',
+    'sticker'
+  )
+),
+ADD COLUMN file_id TEXT,
+ADD CONSTRAINT jobs_one_off_media_check CHECK ((media_type IS NULL) = (file_id IS NULL));
@@ -0,0 +1,2 @@
+ALTER TABLE jobs_cron
+ADD COLUMN paused BOOLEAN NOT NULL DEFAULT false;
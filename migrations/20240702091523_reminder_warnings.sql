@@ -0,0 +1,6 @@
+CREATE TABLE jobs_one_off_warnings (
+  reminder_id INTEGER NOT NULL REFERENCES jobs_one_off (id),
+  lead_minutes INTEGER NOT NULL CHECK (lead_minutes > 0),
+  PRIMARY KEY (reminder_id, lead_minutes),
+  job_id UUID UNIQUE
+);
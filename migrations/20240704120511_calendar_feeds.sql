@@ -0,0 +1,5 @@
+create table telegram_calendar_feeds (
+  user_id uuid primary key references users (user_id) on delete cascade,
+  feed_token text unique not null,
+  created_at timestamptz not null
+);
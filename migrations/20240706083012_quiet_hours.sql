@@ -0,0 +1,6 @@
+ALTER TABLE chatrooms
+ADD COLUMN quiet_start TIME,
+ADD COLUMN quiet_end TIME,
+ADD CONSTRAINT quiet_hours_both_or_neither CHECK ((quiet_start IS NULL) = (quiet_end IS NULL));
+ALTER TABLE jobs_one_off
+ADD COLUMN urgent BOOLEAN NOT NULL DEFAULT false;
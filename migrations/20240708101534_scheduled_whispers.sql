@@ -0,0 +1,15 @@
+create table telegram_scheduled_whispers (
+  id serial primary key,
+  user_id uuid not null references users (user_id) on delete cascade,
+  telegram_chat_id bigint not null,
+  message text,
+  photos bytea[] not null default '{}',
+  send_at timestamptz not null,
+  status text not null default 'pending',
+  job_id uuid,
+  last_error text,
+  created_at timestamptz not null
+);
+
+create index telegram_scheduled_whispers_pending on telegram_scheduled_whispers (user_id, send_at)
+where status = 'pending';
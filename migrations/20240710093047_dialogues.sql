@@ -0,0 +1,7 @@
+CREATE TABLE dialogues (
+    chat_id BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    dialogue JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (chat_id, kind)
+);
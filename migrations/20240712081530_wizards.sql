@@ -0,0 +1,10 @@
+CREATE TABLE wizards (
+    chat_id BIGINT NOT NULL,
+    message_id INT NOT NULL,
+    user_id BIGINT NOT NULL,
+    page JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (chat_id, message_id)
+);
+CREATE INDEX wizards_chat_user_idx ON wizards (chat_id, user_id, updated_at DESC);
+DELETE FROM dialogues WHERE kind = 'callback-page';
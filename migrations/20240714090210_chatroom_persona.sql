@@ -0,0 +1,2 @@
+ALTER TABLE chatrooms
+ADD COLUMN persona TEXT;
@@ -0,0 +1,6 @@
+ALTER TABLE chatrooms
+ADD COLUMN llm_model TEXT,
+ADD COLUMN llm_temperature REAL,
+ADD COLUMN llm_max_tokens INT,
+ADD COLUMN llm_history_count INT,
+ADD COLUMN llm_history_minutes INT;
@@ -0,0 +1,7 @@
+ALTER TABLE jobs_one_off
+DROP CONSTRAINT jobs_one_off_status_check;
+
+ALTER TABLE jobs_one_off
+ADD CONSTRAINT jobs_one_off_status_check CHECK (
+  status IN ('pending', 'sent', 'failed', 'cancelled', 'missed', 'done')
+);
@@ -1,28 +1,35 @@
 use std::sync::OnceLock;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use teloxide::{
     dispatching::{
-        dialogue::{Dialogue, InMemStorage},
-        DpHandlerDescription, HandlerExt, MessageFilterExt, UpdateFilterExt,
+        dialogue::Dialogue, DpHandlerDescription, HandlerExt, MessageFilterExt, UpdateFilterExt,
     },
     dptree::{self, di::DependencyMap, Handler},
     requests::Requester,
-    types::{CallbackQuery, Me, Message, Update},
+    types::{Me, Message, Update},
     utils::command::BotCommands,
     Bot,
 };
 
 use crate::{
     callbacks::{
-        change_time_callback, confirm_reminder_text, date_callback, expired_callback,
-        occurence_callback, remind_text_callback, time_callback, CallbackPage,
+        assign_callback, change_schedule_callback, change_time_callback, confirm_cron_text,
+        confirm_recurring_text, confirm_reminder_text, cron_callback, date_callback,
+        delivered_reminder_callback, expired_callback, foreign_callback, import_calendar_callback,
+        import_calendar_page, is_calendar_file, is_delivered_reminder, is_keep_text,
+        keep_text_callback, lead_time_callback, month_day_callback, occurence_callback,
+        recurrence_callback, recurring_job_callback, recurring_time_callback, remind_text_callback,
+        reminder_list_callback, time_callback, timezone_callback, weekday_callback, CallbackPage,
+        CallbackState,
     },
     chat::user_chat,
     chatroom::update_title,
     commands,
     handlers::{group_title_change, is_not_group_chat},
     member::{self, handle_me_leave, i_got_added, i_got_removed},
+    storage::BotStorage,
 };
 
 /// feel free to `.unwrap()` once it has been initialized.
@@ -30,14 +37,14 @@ pub static BOT_ME: OnceLock<Me> = OnceLock::new();
 /// feel free to `.unwrap()` once it has been initialized.
 pub static BOT_NAME: OnceLock<String> = OnceLock::new();
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub enum ChatState {
     #[default]
     Shutup,
     Talk,
 }
 
-pub type BotDialogue = Dialogue<ChatState, InMemStorage<ChatState>>;
+pub type BotDialogue = Dialogue<ChatState, BotStorage<ChatState>>;
 
 pub async fn init_bot_details(bot: &Bot) {
     bot.set_my_commands(commands::Command::bot_commands())
@@ -61,17 +68,31 @@ pub fn bot_handler() -> Handler<'static, DependencyMap, Result<()>, DpHandlerDes
         .inspect(|u: Update| tracing::debug!("{:#?}", u))
         .branch(
             Update::filter_message()
-                .enter_dialogue::<Message, InMemStorage<ChatState>, ChatState>()
-                .enter_dialogue::<Message, InMemStorage<CallbackPage>, CallbackPage>()
-                .branch(
-                    dptree::case![CallbackPage::ConfirmDateTime { date_time }]
-                        .endpoint(confirm_reminder_text),
-                )
+                .inspect_async(member::track_member)
+                .enter_dialogue::<Message, BotStorage<ChatState>, ChatState>()
+                .map_async(CallbackState::from_message)
+                .filter_map_async(wizard_page)
+                // commands go first, so that `/cancel` isn't taken as the text of a wizard
                 .branch(
                     dptree::entry()
                         .filter_command::<commands::Command>()
                         .endpoint(commands::Command::answer),
                 )
+                .branch(
+                    dptree::case![CallbackPage::ConfirmDateTime { date_time, editing }]
+                        .endpoint(confirm_reminder_text),
+                )
+                .branch(dptree::case![CallbackPage::RecurringCron].endpoint(confirm_cron_text))
+                .branch(
+                    dptree::case![CallbackPage::ConfirmRecurrence { schedule }]
+                        .endpoint(confirm_recurring_text),
+                )
+                // a calendar file sent in the middle of a wizard is not taken as an import
+                .branch(
+                    dptree::case![CallbackPage::Expired]
+                        .filter(is_calendar_file)
+                        .endpoint(import_calendar_page),
+                )
                 .branch(Message::filter_group_chat_created().endpoint(member::handle_me_join))
                 .branch(
                     Message::filter_new_chat_members()
@@ -89,24 +110,98 @@ pub fn bot_handler() -> Handler<'static, DependencyMap, Result<()>, DpHandlerDes
         )
         .branch(
             Update::filter_callback_query()
-                .enter_dialogue::<CallbackQuery, InMemStorage<CallbackPage>, CallbackPage>()
-                .branch(dptree::case![CallbackPage::Occcurence].endpoint(occurence_callback))
-                .branch(dptree::case![CallbackPage::RemindDate].endpoint(date_callback))
-                .branch(
-                    dptree::case![CallbackPage::RemindDateTime { date, time }]
-                        .endpoint(time_callback),
-                )
-                .branch(
-                    dptree::case![CallbackPage::ConfirmDateTime { date_time }]
-                        .endpoint(change_time_callback),
-                )
-                .branch(
-                    dptree::case![CallbackPage::ConfirmOneOffJob {
-                        date_time,
-                        msg_text
-                    }]
-                    .endpoint(remind_text_callback),
-                )
-                .branch(dptree::endpoint(expired_callback)),
+                .filter(is_delivered_reminder)
+                .endpoint(delivered_reminder_callback),
+        )
+        .branch(callback_handler())
+}
+
+/// the page of the wizard, which is logged and skipped when it can't be read.
+async fn wizard_page(p: CallbackState) -> Option<CallbackPage> {
+    p.get_or_default()
+        .await
+        .map_err(|e| tracing::error!("{e:#?}"))
+        .ok()
+}
+
+/// the callbacks of the wizards, routed by the page each wizard is on.
+fn callback_handler() -> Handler<'static, DependencyMap, Result<()>, DpHandlerDescription> {
+    Update::filter_callback_query()
+        .filter_map(CallbackState::from_query)
+        .branch(dptree::filter_async(CallbackState::is_foreign).endpoint(foreign_callback))
+        .chain(dptree::filter_map_async(wizard_page))
+        .branch(dptree::case![CallbackPage::Occcurence].endpoint(occurence_callback))
+        .branch(dptree::case![CallbackPage::RemindDate { editing }].endpoint(date_callback))
+        .branch(
+            dptree::case![CallbackPage::RemindDateTime {
+                date,
+                time,
+                editing
+            }]
+            .endpoint(time_callback),
+        )
+        .branch(
+            dptree::case![CallbackPage::ConfirmDateTime { date_time, editing }]
+                .branch(dptree::filter(is_keep_text).endpoint(keep_text_callback))
+                .branch(dptree::endpoint(change_time_callback)),
+        )
+        .branch(
+            dptree::case![CallbackPage::ConfirmOneOffJob {
+                date_time,
+                msg_text,
+                media,
+                editing,
+                assignment,
+                lead_times,
+                urgent
+            }]
+            .endpoint(remind_text_callback),
+        )
+        .branch(
+            dptree::case![CallbackPage::AssignReminder {
+                date_time,
+                msg_text,
+                media,
+                assignment,
+                lead_times,
+                urgent
+            }]
+            .endpoint(assign_callback),
+        )
+        .branch(
+            dptree::case![CallbackPage::ReminderLeadTimes {
+                date_time,
+                msg_text,
+                media,
+                assignment,
+                lead_times,
+                urgent
+            }]
+            .endpoint(lead_time_callback),
+        )
+        .branch(dptree::case![CallbackPage::Recurrence].endpoint(recurrence_callback))
+        .branch(
+            dptree::case![CallbackPage::RecurringWeekdays { weekdays }].endpoint(weekday_callback),
+        )
+        .branch(dptree::case![CallbackPage::RecurringMonthDay].endpoint(month_day_callback))
+        .branch(dptree::case![CallbackPage::RecurringCron].endpoint(cron_callback))
+        .branch(
+            dptree::case![CallbackPage::RecurringTime { recurrence, time }]
+                .endpoint(recurring_time_callback),
+        )
+        .branch(
+            dptree::case![CallbackPage::ConfirmRecurrence { schedule }]
+                .endpoint(change_schedule_callback),
+        )
+        .branch(
+            dptree::case![CallbackPage::ConfirmRecurringJob { schedule, msg_text }]
+                .endpoint(recurring_job_callback),
+        )
+        .branch(dptree::case![CallbackPage::ReminderList { page }].endpoint(reminder_list_callback))
+        .branch(dptree::case![CallbackPage::Timezone].endpoint(timezone_callback))
+        .branch(
+            dptree::case![CallbackPage::ImportCalendar { reminders }]
+                .endpoint(import_calendar_callback),
         )
+        .branch(dptree::endpoint(expired_callback))
 }
@@ -10,38 +10,115 @@
 //!
 //! these **callback** functions decides how the callback data is processed.
 
+mod assign;
+mod calendar;
 mod date;
 mod expired;
+mod lead_time;
 mod occurrence;
+mod quick_remind;
+mod recurring;
 mod remind_text;
+mod reminder_list;
+mod snooze;
 mod time;
+mod timezone;
 
-use ::time::{Date, OffsetDateTime};
+use ::time::{Date, OffsetDateTime, Weekday};
+pub use assign::*;
+pub use calendar::*;
 pub use date::*;
 pub use expired::*;
+pub use lead_time::*;
 pub use occurrence::*;
+pub use quick_remind::*;
+pub use recurring::*;
 pub use remind_text::*;
-use teloxide::dispatching::dialogue::{Dialogue, InMemStorage};
-pub use time::*;
+pub use reminder_list::*;
+use serde::{Deserialize, Serialize};
+pub use snooze::*;
 
-#[derive(Clone, Default)]
+pub use crate::wizard::CallbackState;
+use crate::{ical::ImportedReminder, media::ReminderMedia};
+pub use time::*;
+pub use timezone::*;
 
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub enum CallbackPage {
     #[default]
     Expired,
     Occcurence,
-    RemindDate,
+    RemindDate {
+        editing: Option<EditingReminder>,
+    },
     RemindDateTime {
         date: Date,
         time: RemindTime,
+        editing: Option<EditingReminder>,
     },
     ConfirmDateTime {
         date_time: OffsetDateTime,
+        editing: Option<EditingReminder>,
     },
     ConfirmOneOffJob {
         date_time: OffsetDateTime,
         msg_text: String,
+        media: Option<ReminderMedia>,
+        editing: Option<EditingReminder>,
+        assignment: Assignment,
+        lead_times: Vec<i32>,
+        urgent: bool,
+    },
+    AssignReminder {
+        date_time: OffsetDateTime,
+        msg_text: String,
+        media: Option<ReminderMedia>,
+        assignment: Assignment,
+        lead_times: Vec<i32>,
+        urgent: bool,
+    },
+    ReminderLeadTimes {
+        date_time: OffsetDateTime,
+        msg_text: String,
+        media: Option<ReminderMedia>,
+        assignment: Assignment,
+        lead_times: Vec<i32>,
+        urgent: bool,
     },
+    Recurrence,
+    RecurringWeekdays {
+        weekdays: Vec<Weekday>,
+    },
+    RecurringMonthDay,
+    RecurringCron,
+    RecurringTime {
+        recurrence: Recurrence,
+        time: RemindTime,
+    },
+    ConfirmRecurrence {
+        schedule: RecurringSchedule,
+    },
+    ConfirmRecurringJob {
+        schedule: RecurringSchedule,
+        msg_text: String,
+    },
+    ReminderList {
+        page: usize,
+    },
+    ImportCalendar {
+        reminders: Vec<ImportedReminder>,
+    },
+    Timezone,
 }
 
-pub type CallbackState = Dialogue<CallbackPage, InMemStorage<CallbackPage>>;
+/// The pending one-off reminder which the one-off wizard is editing.
+///
+/// The wizard carries it as `Option<EditingReminder>`, which is `None` when
+/// creating a new reminder.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EditingReminder {
+    pub id: i32,
+    pub due: OffsetDateTime,
+    pub message: String,
+    pub media: Option<ReminderMedia>,
+}
@@ -0,0 +1,314 @@
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use teloxide::{
+    payloads::EditMessageTextSetters,
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+    },
+    Bot,
+};
+use time::OffsetDateTime;
+
+use crate::media::ReminderMedia;
+
+use super::{
+    expired_callback_msg, job_text, job_text_keyboard, warned_job_text, CallbackPage, CallbackState,
+};
+
+/// number of recently seen members listed in the picker.
+const MEMBER_LIMIT: i64 = 30;
+const MEMBERS_PER_ROW: usize = 2;
+
+const ASSIGN_MEMBER: &str = "assign";
+const ASSIGN_DM: &str = "assign-dm";
+const ASSIGN_DONE: &str = "assign-done";
+
+/// The members picked for a one-off reminder in the wizard.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Assignment {
+    pub assignees: Vec<Assignee>,
+    /// deliver the reminder privately to assignees who started the bot.
+    pub dm: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Assignee {
+    pub user_id: i64,
+    pub name: String,
+}
+
+impl Assignment {
+    fn toggle(&mut self, member: Assignee) {
+        if let Some(i) = self
+            .assignees
+            .iter()
+            .position(|x| x.user_id == member.user_id)
+        {
+            self.assignees.remove(i);
+        } else {
+            self.assignees.push(member);
+        }
+    }
+
+    pub fn user_ids(&self) -> Vec<i64> {
+        self.assignees.iter().map(|x| x.user_id).collect()
+    }
+}
+
+/// What each button in the assignee picker does.
+///
+/// The callback data is in the format of `assign:user_id`, `assign-dm` or `assign-done`.
+#[derive(Debug, PartialEq)]
+enum AssignAction {
+    Toggle(i64),
+    Dm,
+    Done,
+}
+
+impl AssignAction {
+    fn to_data(&self) -> String {
+        match self {
+            Self::Toggle(user_id) => format!("{ASSIGN_MEMBER}:{user_id}"),
+            Self::Dm => ASSIGN_DM.to_string(),
+            Self::Done => ASSIGN_DONE.to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for AssignAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || format!("{value} is not a supported assign action.");
+        match value.split_once(':') {
+            Some((ASSIGN_MEMBER, user_id)) => {
+                Ok(Self::Toggle(user_id.parse().map_err(|_| invalid())?))
+            }
+            None if value == ASSIGN_DM => Ok(Self::Dm),
+            None if value == ASSIGN_DONE => Ok(Self::Done),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// how the member is called in the picker and in the delivered reminder.
+pub fn member_name(username: Option<&str>, first_name: &str) -> String {
+    username.map_or_else(|| first_name.to_string(), |x| format!("@{x}"))
+}
+
+/// the members of the chat, most recently seen first.
+async fn chat_members(pool: &PgPool, chat_id: i64) -> Result<Vec<Assignee>, sqlx::Error> {
+    let members = sqlx::query!(
+        "SELECT user_id, username, first_name FROM chat_members
+        WHERE chat_id = $1
+        ORDER BY updated_at DESC
+        LIMIT $2",
+        chat_id,
+        MEMBER_LIMIT
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|x| Assignee {
+        user_id: x.user_id,
+        name: member_name(x.username.as_deref(), &x.first_name),
+    })
+    .collect();
+    Ok(members)
+}
+
+fn assign_keyboard(members: &[Assignee], assignment: &Assignment) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = members
+        .chunks(MEMBERS_PER_ROW)
+        .map(|row| {
+            row.iter()
+                .map(|x| {
+                    let label = if assignment.assignees.contains(x) {
+                        format!("✅ {}", x.name)
+                    } else {
+                        x.name.clone()
+                    };
+                    InlineKeyboardButton::callback(label, AssignAction::Toggle(x.user_id).to_data())
+                })
+                .collect()
+        })
+        .collect();
+
+    let dm = if assignment.dm {
+        "DM assignees: on"
+    } else {
+        "DM assignees: off"
+    };
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        dm,
+        AssignAction::Dm.to_data(),
+    )]);
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Done",
+        AssignAction::Done.to_data(),
+    )]);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// the confirmation text of the one-off reminder, with its assignees.
+pub fn assigned_job_text(
+    date_time: OffsetDateTime,
+    text: &str,
+    media: Option<&ReminderMedia>,
+    assignment: &Assignment,
+) -> String {
+    let text = job_text(date_time, text, media);
+    if assignment.assignees.is_empty() {
+        return text;
+    }
+    let names: Vec<&str> = assignment
+        .assignees
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect();
+    let dm = if assignment.dm {
+        "\n(sent by DM to those who started me)"
+    } else {
+        ""
+    };
+    format!("{text}\n\nfor: {}{dm}", names.join(", "))
+}
+
+pub async fn assign_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    assignment: &Assignment,
+    pool: &PgPool,
+) -> anyhow::Result<()> {
+    let members = chat_members(pool, chat_id.0).await?;
+    let text = if members.is_empty() {
+        "I don't know anyone in this chat yet 😅\nMembers show up here once they say something."
+    } else {
+        "Who is this reminder for? 🐢\n\nTap the members to assign them."
+    };
+    bot.edit_message_text(chat_id, msg_id, text)
+        .reply_markup(assign_keyboard(&members, assignment))
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn assign_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    (date_time, msg_text, media, mut assignment, lead_times, urgent): (
+        OffsetDateTime,
+        String,
+        Option<ReminderMedia>,
+        Assignment,
+        Vec<i32>,
+        bool,
+    ),
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    let action = match AssignAction::try_from(data.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            expired_callback_msg(bot, chat.id, *id).await?;
+            bail!("{e}");
+        }
+    };
+
+    match action {
+        AssignAction::Toggle(user_id) => {
+            let members = chat_members(&pool, chat.id.0).await?;
+            let Some(member) = members.into_iter().find(|x| x.user_id == user_id) else {
+                bail!("user {user_id} is not a member of this chat");
+            };
+            assignment.toggle(member);
+        }
+        AssignAction::Dm => assignment.dm = !assignment.dm,
+        AssignAction::Done => {
+            bot.edit_message_text(
+                chat.id,
+                *id,
+                warned_job_text(
+                    date_time,
+                    &msg_text,
+                    media.as_ref(),
+                    &assignment,
+                    &lead_times,
+                    urgent,
+                ),
+            )
+            .reply_markup(job_text_keyboard(true, urgent))
+            .await?;
+            p.update(CallbackPage::ConfirmOneOffJob {
+                date_time,
+                msg_text,
+                media,
+                editing: None,
+                assignment,
+                lead_times,
+                urgent,
+            })
+            .await?;
+            return Ok(());
+        }
+    }
+
+    assign_page(bot, chat.id, *id, &assignment, &pool).await?;
+    p.update(CallbackPage::AssignReminder {
+        date_time,
+        msg_text,
+        media,
+        assignment,
+        lead_times,
+        urgent,
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{member_name, AssignAction, Assignee, Assignment};
+
+    #[test]
+    fn assign_action_data() {
+        let actions = [
+            AssignAction::Toggle(220_272_763),
+            AssignAction::Dm,
+            AssignAction::Done,
+        ];
+        for action in actions {
+            let data = action.to_data();
+            assert_eq!(AssignAction::try_from(data.as_str()).unwrap(), action);
+        }
+        assert!(AssignAction::try_from("assign:bob").is_err());
+    }
+
+    #[test]
+    fn toggle_assignee() {
+        let bob = Assignee {
+            user_id: 1,
+            name: member_name(Some("bob"), "Bob"),
+        };
+        let mut assignment = Assignment::default();
+        assignment.toggle(bob.clone());
+        assert_eq!(assignment.user_ids(), vec![1]);
+        assert_eq!(assignment.assignees[0].name, "@bob");
+        assignment.toggle(bob);
+        assert!(assignment.assignees.is_empty());
+    }
+}
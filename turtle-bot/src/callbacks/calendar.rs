@@ -0,0 +1,267 @@
+use anyhow::bail;
+use sqlx::PgPool;
+use teloxide::{
+    net::Download,
+    payloads::{SendDocumentSetters, SendMessageSetters},
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message,
+    },
+    Bot,
+};
+use time::{macros::format_description, OffsetDateTime};
+use tokio_cron_scheduler::JobScheduler;
+
+use crate::{
+    ical::{calendar_events, import_reminders, to_ics, ImportedReminder},
+    jobs::{add_one_off_reminder, add_recurring_reminder, NewReminder},
+    timezone::{chat_timezone, to_local},
+};
+
+use super::{expired_callback_msg, CallbackPage, CallbackState};
+
+/// calendar files bigger than this are not read.
+const MAX_FILE_BYTES: u32 = 1024 * 1024;
+/// number of reminders listed before asking to import them.
+const PREVIEW_SIZE: usize = 10;
+/// most reminders imported from one calendar, the rest of the file is left out.
+const MAX_IMPORT: usize = 100;
+
+const IMPORT: &str = "Import";
+const CANCEL: &str = "Cancel";
+
+/// Sends the pending reminders and cron jobs of the chat as an `.ics` file.
+pub async fn export_command(bot: Bot, chat_id: ChatId, pool: &PgPool) -> anyhow::Result<()> {
+    let now = OffsetDateTime::now_utc();
+    let export = calendar_events(pool, &[chat_id.0], now).await?;
+    if export.events.is_empty() {
+        bot.send_message(chat_id, "There's nothing scheduled to export 😅")
+            .await?;
+        return Ok(());
+    }
+
+    let skipped = if export.skipped > 0 {
+        format!(
+            "\n\n{} recurring reminders repeat in ways calendars can't follow, so they're left out.",
+            export.skipped
+        )
+    } else {
+        String::new()
+    };
+    let caption = format!(
+        "{} reminders, ready for your calendar 🐢{skipped}",
+        export.events.len()
+    );
+
+    let ics = to_ics("🐢 Reminders", &export.events, now);
+    bot.send_document(
+        chat_id,
+        InputFile::memory(ics.into_bytes()).file_name("reminders.ics"),
+    )
+    .caption(caption)
+    .await?;
+    Ok(())
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn is_calendar_file(msg: Message) -> bool {
+    msg.document().is_some_and(|x| {
+        x.file_name
+            .as_deref()
+            .is_some_and(|name| name.to_lowercase().ends_with(".ics"))
+            || x.mime_type
+                .as_ref()
+                .is_some_and(|mime| mime.essence_str() == "text/calendar")
+    })
+}
+
+fn import_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(CANCEL, CANCEL),
+        InlineKeyboardButton::callback(IMPORT, IMPORT),
+    ]])
+}
+
+/// Reads the events of the `.ics` file sent to the chat, and asks before importing them.
+#[tracing::instrument(skip_all)]
+pub async fn import_calendar_page(
+    bot: Bot,
+    msg: Message,
+    callback: CallbackState,
+    pool: PgPool,
+) -> anyhow::Result<()> {
+    let Some(document) = msg.document() else {
+        bail!("no calendar file in message");
+    };
+    if document.file.size > MAX_FILE_BYTES {
+        bot.send_message(msg.chat.id, "This calendar is too big for me 😅")
+            .await?;
+        return Ok(());
+    }
+
+    let file = bot.get_file(&document.file.id).await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content).await?;
+
+    let tz = chat_timezone(&pool, msg.chat.id.0).await?;
+    let mut import = import_reminders(
+        &String::from_utf8_lossy(&content),
+        tz,
+        OffsetDateTime::now_utc(),
+    );
+    if import.reminders.is_empty() {
+        bot.send_message(
+            msg.chat.id,
+            "I couldn't find any upcoming events in this calendar 😅",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let found = import.reminders.len();
+    import.reminders.truncate(MAX_IMPORT);
+
+    let due_format = format_description!("[day] [month repr:short] [year] [hour]:[minute]");
+    let mut lines: Vec<String> = import
+        .reminders
+        .iter()
+        .take(PREVIEW_SIZE)
+        .map(|x| {
+            let when = match x {
+                ImportedReminder::OneOff { due, .. } => {
+                    to_local(tz, *due).format(&due_format).unwrap_or_default()
+                }
+                ImportedReminder::Recurring { cron_str, .. } => format!("cron {cron_str}"),
+            };
+            format!("• {when} - {}", x.message())
+        })
+        .collect();
+    if import.reminders.len() > PREVIEW_SIZE {
+        lines.push(format!(
+            "...and {} more",
+            import.reminders.len() - PREVIEW_SIZE
+        ));
+    }
+    let trimmed = if found > MAX_IMPORT {
+        format!("\n\nThat's a lot 😅 only the first {MAX_IMPORT} are imported.")
+    } else {
+        String::new()
+    };
+    let skipped = if import.skipped > 0 {
+        format!(
+            "\n\n{} events are skipped, as they're over, cancelled or repeat in ways I can't follow.",
+            import.skipped
+        )
+    } else {
+        String::new()
+    };
+    let unknown_timezones = if import.unknown_timezones.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n\nI don't know the time zones {}, so their times are read in this chat's time zone ⚠️",
+            import.unknown_timezones.join(", ")
+        )
+    };
+    let text = format!(
+        "I found {found} reminders in this calendar:\n\n{}{trimmed}{skipped}{unknown_timezones}\n\nShould I import them? 🐢",
+        lines.join("\n")
+    );
+
+    let sent = bot
+        .send_message(msg.chat.id, text)
+        .reply_markup(import_keyboard())
+        .await?;
+    callback
+        .start(
+            sent.id,
+            CallbackPage::ImportCalendar {
+                reminders: import.reminders,
+            },
+        )
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn import_calendar_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    reminders: Vec<ImportedReminder>,
+    pool: PgPool,
+    sched: JobScheduler,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    match data.as_str() {
+        CANCEL => {
+            p.reset().await?;
+            bot.edit_message_text(chat.id, *id, "Okay, nothing is imported. 🐢")
+                .await?;
+        }
+        IMPORT => {
+            let username = q
+                .from
+                .username
+                .clone()
+                .unwrap_or_else(|| q.from.first_name.clone());
+            let total = reminders.len();
+            let mut imported = 0;
+            for reminder in reminders {
+                let result = match reminder {
+                    ImportedReminder::OneOff { due, message } => {
+                        let reminder = NewReminder {
+                            target: chat.id.0,
+                            username: username.clone(),
+                            message,
+                            due,
+                            media: None,
+                            snoozed_from: None,
+                            assignees: Vec::new(),
+                            deliver_dm: false,
+                            lead_times: Vec::new(),
+                            urgent: false,
+                        };
+                        add_one_off_reminder(&bot, &pool, &sched, reminder).await
+                    }
+                    ImportedReminder::Recurring { cron_str, message } => {
+                        add_recurring_reminder(
+                            &bot,
+                            &pool,
+                            &sched,
+                            chat.id.0,
+                            Some(username.clone()),
+                            message,
+                            &cron_str,
+                        )
+                        .await
+                    }
+                };
+                match result {
+                    Ok(_) => imported += 1,
+                    Err(e) => tracing::error!("error importing reminder: {e:#?}"),
+                }
+            }
+
+            p.reset().await?;
+            let text = if imported == total {
+                format!("imported {imported} reminders 🐢")
+            } else {
+                format!("imported {imported} of {total} reminders 😅")
+            };
+            bot.edit_message_text(chat.id, *id, text).await?;
+        }
+        _ => expired_callback_msg(bot, chat.id, *id).await?,
+    }
+    Ok(())
+}
@@ -1,4 +1,6 @@
 use anyhow::bail;
+use chrono_tz::Tz;
+use sqlx::PgPool;
 use teloxide::{
     payloads::EditMessageTextSetters,
     requests::Requester,
@@ -8,18 +10,91 @@ use teloxide::{
     Bot,
 };
 use time::{
-    error::ComponentRange,
-    macros::{format_description, offset},
-    Date, Month, OffsetDateTime, Weekday,
+    error::ComponentRange, macros::format_description, Date, Month, OffsetDateTime, Weekday,
 };
 
-use crate::callbacks::expired_callback_msg;
+use crate::{
+    callbacks::expired_callback_msg,
+    timezone::{chat_timezone, now_in, to_local},
+};
 
-use super::{occurence_page, time::RemindTime, time_page, CallbackPage, CallbackState};
+use super::{
+    occurence_page, reminder_list_page, time::RemindTime, time_page, CallbackPage, CallbackState,
+    EditingReminder,
+};
 
 const CURRENT_MONTH: &str = "Current";
 const OCCURENCE: &str = "Occurence";
 const DATE_PICK_MSG: &str = "Pick your date 🐢";
+const MONTH_PICK_MSG: &str = "Pick your month 🐢";
+
+const MONTHS: &str = "months";
+const MONTH: &str = "month";
+const TODAY: &str = "today";
+const TOMORROW: &str = "tomorrow";
+const NEXT_MONDAY: &str = "next-monday";
+
+const MONTHS_PER_ROW: usize = 3;
+
+/// What the buttons of the date picker besides the days do.
+///
+/// The callback data is in the format of `months:year`, `month:month:year`
+/// or the name of a shortcut, eg. `today`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateAction {
+    /// the months of the year, to jump to.
+    Months(i32),
+    /// the days of the month.
+    Month(Month, i32),
+    Today,
+    Tomorrow,
+    NextMonday,
+}
+
+impl DateAction {
+    fn to_data(self) -> String {
+        match self {
+            Self::Months(year) => format!("{MONTHS}:{year}"),
+            Self::Month(month, year) => format!("{MONTH}:{}:{year}", u8::from(month)),
+            Self::Today => TODAY.to_string(),
+            Self::Tomorrow => TOMORROW.to_string(),
+            Self::NextMonday => NEXT_MONDAY.to_string(),
+        }
+    }
+
+    /// the date picked by a shortcut, relative to `today` in the chat.
+    fn shortcut_date(self, today: Date) -> Option<Date> {
+        match self {
+            Self::Today => Some(today),
+            Self::Tomorrow => today.next_day(),
+            Self::NextMonday => Some(today.next_occurrence(Weekday::Monday)),
+            Self::Months(_) | Self::Month(..) => None,
+        }
+    }
+}
+
+impl TryFrom<&str> for DateAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let invalid = || format!("{value} is not a supported date picker action.");
+        match parts.as_slice() {
+            [MONTHS, year] => Ok(Self::Months(year.parse().map_err(|_| invalid())?)),
+            [MONTH, month, year] => {
+                let month: u8 = month.parse().map_err(|_| invalid())?;
+                Ok(Self::Month(
+                    Month::try_from(month).map_err(|_| invalid())?,
+                    year.parse().map_err(|_| invalid())?,
+                ))
+            }
+            [TODAY] => Ok(Self::Today),
+            [TOMORROW] => Ok(Self::Tomorrow),
+            [NEXT_MONDAY] => Ok(Self::NextMonday),
+            _ => Err(invalid()),
+        }
+    }
+}
 
 #[derive(thiserror::Error, Debug)]
 pub enum DateError {
@@ -46,28 +121,30 @@ pub async fn date_page(
     day: u8,
     month: u8,
     year: i32,
+    tz: Tz,
 ) -> anyhow::Result<()> {
-    let keyboard = date_keyboard(day, month, year)?;
+    let keyboard = date_keyboard(day, month, year, now_in(tz))?;
     bot.edit_message_text(chat_id, msg_id, DATE_PICK_MSG)
         .reply_markup(keyboard)
         .await?;
     Ok(())
 }
 
-fn date_keyboard(day: u8, month: u8, year: i32) -> Result<InlineKeyboardMarkup, DateError> {
-    let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-
+/// the days of the month from `day` onwards, where `now` is the local time of the chat.
+fn date_keyboard(
+    day: u8,
+    month: u8,
+    year: i32,
+    now: OffsetDateTime,
+) -> Result<InlineKeyboardMarkup, DateError> {
     let month: Month = month.try_into()?;
-    let then = now.replace_year(year)?.replace_month(month)?;
-
-    // the earliest possible date is today, hence if `then` > `now`,
-    // change to specified date
-    if then > now {
-        then.replace_day(day)?;
-    }
+    // from the first of the month, as the day of `now` may not exist in it, eg. the 31st
+    let then = Date::from_calendar_date(year, month, 1)?;
+    let last_day_of_month = time::util::days_in_year_month(year, month);
+    let day = day.clamp(1, last_day_of_month);
 
     let mut calendar_vec: Vec<InlineKeyboardButton> = Vec::new();
-    let weekday_of_first_day = then.replace_day(1)?.weekday();
+    let weekday_of_first_day = then.weekday();
 
     match weekday_of_first_day {
         Weekday::Monday => calendar_vec.append(&mut vec![]),
@@ -100,19 +177,6 @@ fn date_keyboard(day: u8, month: u8, year: i32) -> Result<InlineKeyboardMarkup,
 
     let past_future_month_year = get_past_future_month_year(month, year);
 
-    let PastFutureMonthYear {
-        next_month,
-        year_of_next_month,
-        ..
-    } = past_future_month_year;
-
-    let naive_last_day_of_month =
-        Date::from_calendar_date(year_of_next_month, next_month.try_into()?, 1)?
-            .previous_day()
-            .ok_or(DateError::None)?
-            .day();
-    let last_day_of_month = then.replace_day(naive_last_day_of_month)?.day();
-
     let mut wow = (day..=last_day_of_month)
         .map(|i| InlineKeyboardButton::callback(i.to_string(), format!("{i}-{month}-{year}")))
         .collect();
@@ -149,8 +213,17 @@ fn date_keyboard(day: u8, month: u8, year: i32) -> Result<InlineKeyboardMarkup,
     for week in calendar_vec.chunks(7) {
         calendar.push(week.to_owned());
     }
+    calendar.push(
+        [
+            ("Today", DateAction::Today),
+            ("Tomorrow", DateAction::Tomorrow),
+            ("Next Mon", DateAction::NextMonday),
+        ]
+        .map(|(label, x)| InlineKeyboardButton::callback(label, x.to_data()))
+        .to_vec(),
+    );
     let mut occurence_row = vec![InlineKeyboardButton::callback("Back", OCCURENCE)];
-    if then.month() != now.month() {
+    if (then.year(), then.month()) != (now.year(), now.month()) {
         occurence_row.push(InlineKeyboardButton::callback(CURRENT_MONTH, CURRENT_MONTH));
     }
     calendar.push(occurence_row);
@@ -164,8 +237,11 @@ fn date_keyboard_pagination_row(
     now: OffsetDateTime,
 ) -> Result<Vec<Vec<InlineKeyboardButton>>, DateError> {
     let month_name = parse_month_to_str(month)?;
-    let calendar_title =
-        InlineKeyboardButton::callback(format!("{month_name} {year}"), " ".to_owned());
+    // tapping the title jumps to any month of the year
+    let calendar_title = InlineKeyboardButton::callback(
+        format!("{month_name} {year}"),
+        DateAction::Months(year).to_data(),
+    );
 
     let PastFutureMonthYear {
         prev_month,
@@ -174,27 +250,26 @@ fn date_keyboard_pagination_row(
         year_of_next_month,
     } = data;
 
-    let prev_month_first_day = now
-        .replace_year(year_of_prev_month)?
-        .replace_month(prev_month.try_into()?)?
-        .replace_day(1)?;
+    let prev_month_first_day =
+        Date::from_calendar_date(year_of_prev_month, prev_month.try_into()?, 1)?;
+    let prev_month_name = prev_month_first_day.month();
 
-    let prev_month_calendar = if prev_month_first_day > now {
-        let prev_month_date = format!("01-{prev_month}-{year_of_prev_month} <<");
+    let prev_month_calendar = if prev_month_first_day > now.date() {
+        let prev_month_date = format!("1-{prev_month_name}-{year_of_prev_month} <<");
         InlineKeyboardButton::callback("<<", prev_month_date.clone())
     } else {
         let curr_day = now.day();
         let curr_month: u8 = now.month().into();
         let curr_year = now.year();
         if prev_month == curr_month && year_of_prev_month == curr_year {
-            let prev_month_date = format!("{curr_day}-{prev_month}-{year_of_prev_month} <<");
+            let prev_month_date = format!("{curr_day}-{prev_month_name}-{year_of_prev_month} <<");
             InlineKeyboardButton::callback("<<", prev_month_date.clone())
         } else {
             InlineKeyboardButton::callback(" ", " ")
         }
     };
 
-    let next_month_date = format!(">> 01-{next_month}-{year_of_next_month}");
+    let next_month_date = format!(">> 1-{}-{year_of_next_month}", Month::try_from(next_month)?);
 
     let next_month_calendar = InlineKeyboardButton::callback(">>", next_month_date.clone());
 
@@ -216,6 +291,43 @@ fn date_keyboard_pagination_row(
     Ok(keyboard_markup)
 }
 
+/// The months of `year`, where the months before the current one can't be picked.
+fn month_keyboard(year: i32, now: OffsetDateTime) -> Result<InlineKeyboardMarkup, DateError> {
+    let year = year.max(now.year());
+    let current_month: u8 = now.month().into();
+
+    let prev_year = if year > now.year() {
+        InlineKeyboardButton::callback("<<", DateAction::Months(year - 1).to_data())
+    } else {
+        InlineKeyboardButton::callback(" ", " ")
+    };
+    let next_year = InlineKeyboardButton::callback(">>", DateAction::Months(year + 1).to_data());
+    let mut keyboard = vec![vec![
+        prev_year,
+        InlineKeyboardButton::callback(year.to_string(), " "),
+        next_year,
+    ]];
+
+    let months = (1..=12u8)
+        .map(|month| {
+            if year == now.year() && month < current_month {
+                return Ok(InlineKeyboardButton::callback(" ", " "));
+            }
+            let data = DateAction::Month(month.try_into()?, year).to_data();
+            Ok(InlineKeyboardButton::callback(
+                parse_month_to_str(month)?,
+                data,
+            ))
+        })
+        .collect::<Result<Vec<_>, DateError>>()?;
+    for row in months.chunks(MONTHS_PER_ROW) {
+        keyboard.push(row.to_owned());
+    }
+
+    keyboard.push(vec![InlineKeyboardButton::callback("Back", CURRENT_MONTH)]);
+    Ok(InlineKeyboardMarkup::new(keyboard))
+}
+
 #[allow(clippy::struct_field_names)]
 #[derive(Copy, Clone)]
 struct PastFutureMonthYear {
@@ -265,20 +377,72 @@ async fn send_prev_or_next_month(
     chat_id: ChatId,
     msg_id: MessageId,
     bot: Bot,
+    tz: Tz,
 ) -> anyhow::Result<()> {
     let day = d.day();
     let month: u8 = d.month().into();
     let year = d.year();
-    let calendar = date_keyboard(day, month, year)?;
+    let calendar = date_keyboard(day, month, year, now_in(tz))?;
     bot.edit_message_text(chat_id, msg_id, DATE_PICK_MSG)
         .reply_markup(calendar)
         .await?;
     Ok(())
 }
 
+/// moves on to the time page, unless the date is already over, eg. on a
+/// calendar left open overnight.
+async fn pick_date(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    p: CallbackState,
+    date: Date,
+    editing: Option<EditingReminder>,
+    tz: Tz,
+) -> anyhow::Result<()> {
+    let now = now_in(tz);
+    if date < now.date() {
+        date_page(
+            bot,
+            chat_id,
+            msg_id,
+            now.day(),
+            now.month().into(),
+            now.year(),
+            tz,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // keep the original time when editing a reminder
+    let remind_time = match editing {
+        Some(ref x) => {
+            let due = to_local(tz, x.due);
+            RemindTime::new(due.hour(), due.minute()).unwrap_or_default()
+        }
+        None => RemindTime::default(),
+    };
+    p.update(CallbackPage::RemindDateTime {
+        date,
+        time: remind_time.clone(),
+        editing,
+    })
+    .await?;
+
+    time_page(bot, chat_id, msg_id, date, remind_time).await?;
+    Ok(())
+}
+
 #[allow(deprecated)]
 #[tracing::instrument(skip_all)]
-pub async fn date_callback(bot: Bot, q: CallbackQuery, p: CallbackState) -> anyhow::Result<()> {
+pub async fn date_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    editing: Option<EditingReminder>,
+    pool: PgPool,
+) -> anyhow::Result<()> {
     bot.answer_callback_query(q.id.clone()).await?;
 
     let Some(ref data) = q.data else {
@@ -289,36 +453,72 @@ pub async fn date_callback(bot: Bot, q: CallbackQuery, p: CallbackState) -> anyh
         tracing::error!("no message data from telegram");
         return Err(DateError::NoMessageData.into());
     };
+    let tz = chat_timezone(&pool, chat.id.0).await?;
 
     if data.trim().is_empty() {
         return Ok(());
+    } else if let Ok(action) = DateAction::try_from(data.as_str()) {
+        let now = now_in(tz);
+        match action {
+            DateAction::Months(year) => {
+                bot.edit_message_text(chat.id, *id, MONTH_PICK_MSG)
+                    .reply_markup(month_keyboard(year, now)?)
+                    .await?;
+            }
+            DateAction::Month(month, year) => {
+                // the current month starts from today, and past months aren't shown
+                let (day, month, year) =
+                    if (year, u8::from(month)) > (now.year(), u8::from(now.month())) {
+                        (1, month, year)
+                    } else {
+                        (now.day(), now.month(), now.year())
+                    };
+                date_page(bot, chat.id, *id, day, month.into(), year, tz).await?;
+            }
+            shortcut => {
+                let picked = shortcut.shortcut_date(now.date()).ok_or(DateError::None)?;
+                pick_date(bot, chat.id, *id, p, picked, editing, tz).await?;
+            }
+        }
     } else if data.strip_suffix(" <<").is_some() {
-        let prev_month_format = format_description!("[day]-[month repr:long]-[year] <<");
+        let prev_month_format =
+            format_description!("[day padding:none]-[month repr:long]-[year] <<");
         let prev_month = Date::parse(data, prev_month_format)?;
-        send_prev_or_next_month(prev_month, chat.id, *id, bot).await?;
+        send_prev_or_next_month(prev_month, chat.id, *id, bot, tz).await?;
     } else if data.strip_prefix(">> ").is_some() {
-        let next_month_format = format_description!(">> [day]-[month repr:long]-[year]");
+        let next_month_format =
+            format_description!(">> [day padding:none]-[month repr:long]-[year]");
         let next_month = Date::parse(data, next_month_format)?;
-        send_prev_or_next_month(next_month, chat.id, *id, bot).await?;
-    } else if Date::parse(data, format_description!("[day]-[month repr:long]-[year]")).is_ok() {
-        let date = Date::parse(data, format_description!("[day]-[month repr:long]-[year]"))?;
-        let remind_time = RemindTime::default();
-        p.update(CallbackPage::RemindDateTime {
-            date,
-            time: remind_time.clone(),
-        })
-        .await?;
-
-        time_page(bot, chat.id, *id, date, remind_time).await?;
+        send_prev_or_next_month(next_month, chat.id, *id, bot, tz).await?;
+    } else if let Ok(date) = Date::parse(
+        data,
+        // days before the 10th are sent without a leading zero
+        format_description!("[day padding:none]-[month repr:long]-[year]"),
+    ) {
+        pick_date(bot, chat.id, *id, p, date, editing, tz).await?;
     } else {
         match data.as_ref() {
             OCCURENCE => {
-                p.update(CallbackPage::Occcurence).await?;
-                occurence_page(bot, chat.id, *id).await?;
+                if editing.is_some() {
+                    p.update(CallbackPage::ReminderList { page: 0 }).await?;
+                    reminder_list_page(bot, chat.id, *id, &pool, 0, None).await?;
+                } else {
+                    p.update(CallbackPage::Occcurence).await?;
+                    occurence_page(bot, chat.id, *id).await?;
+                }
             }
             CURRENT_MONTH => {
-                let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-                date_page(bot, chat.id, *id, now.day(), now.month().into(), now.year()).await?;
+                let now = now_in(tz);
+                date_page(
+                    bot,
+                    chat.id,
+                    *id,
+                    now.day(),
+                    now.month().into(),
+                    now.year(),
+                    tz,
+                )
+                .await?;
             }
             unknown => {
                 tracing::error!(unknown, "unrecognizable value");
@@ -333,7 +533,13 @@ pub async fn date_callback(bot: Bot, q: CallbackQuery, p: CallbackState) -> anyh
 
 #[cfg(test)]
 mod tests {
-    use time::{macros::format_description, Date};
+    use teloxide::types::InlineKeyboardButtonKind;
+    use time::{
+        macros::{date, datetime, format_description},
+        Date, Month,
+    };
+
+    use super::{date_keyboard, DateAction};
 
     #[test]
     fn date_parse() {
@@ -341,4 +547,85 @@ mod tests {
         let date = Date::parse(zzz, format_description!("[day]-[month repr:long]-[year]"));
         println!("{date:#?}");
     }
+
+    #[test]
+    fn date_action_data() {
+        let actions = [
+            DateAction::Months(2025),
+            DateAction::Month(Month::December, 2024),
+            DateAction::Today,
+            DateAction::NextMonday,
+        ];
+        for action in actions {
+            let data = action.to_data();
+            assert_eq!(DateAction::try_from(data.as_str()).unwrap(), action);
+        }
+        assert!(DateAction::try_from("month:13:2024").is_err());
+        // the days of the calendar keep their own format
+        assert!(DateAction::try_from("17-August-2024").is_err());
+    }
+
+    #[test]
+    fn shortcut_dates() {
+        // a tuesday at the end of the year
+        let today = date!(2024 - 12 - 31);
+        assert_eq!(DateAction::Today.shortcut_date(today), Some(today));
+        assert_eq!(
+            DateAction::Tomorrow.shortcut_date(today),
+            Some(date!(2025 - 01 - 01))
+        );
+        assert_eq!(
+            DateAction::NextMonday.shortcut_date(today),
+            Some(date!(2025 - 01 - 06))
+        );
+        // on a monday, it's the one a week later
+        assert_eq!(
+            DateAction::NextMonday.shortcut_date(date!(2025 - 01 - 06)),
+            Some(date!(2025 - 01 - 13))
+        );
+    }
+
+    #[test]
+    fn shorter_month_from_month_end() {
+        let now = datetime!(2025-01-31 10:00 +8);
+        let days = |day, month, year| {
+            let keyboard = date_keyboard(day, month, year, now).unwrap();
+            keyboard
+                .inline_keyboard
+                .iter()
+                .flatten()
+                .filter_map(|x| match &x.kind {
+                    InlineKeyboardButtonKind::CallbackData(data) => Date::parse(
+                        data,
+                        format_description!("[day padding:none]-[month repr:long]-[year]"),
+                    )
+                    .ok(),
+                    _ => None,
+                })
+                .collect::<Vec<Date>>()
+        };
+        let february = days(1, 2, 2025);
+        assert_eq!(february.first(), Some(&date!(2025 - 02 - 01)));
+        assert_eq!(february.last(), Some(&date!(2025 - 02 - 28)));
+        // today's day is past the end of the month
+        assert_eq!(days(31, 4, 2025), vec![date!(2025 - 04 - 30)]);
+        assert_eq!(days(31, 1, 2025), vec![date!(2025 - 01 - 31)]);
+    }
+
+    #[test]
+    fn month_pagination() {
+        let now = datetime!(2025-01-31 10:00 +8);
+        let keyboard = date_keyboard(1, 3, 2025, now).unwrap();
+        let data: Vec<&str> = keyboard.inline_keyboard[0]
+            .iter()
+            .filter_map(|x| match &x.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => Some(data.as_str()),
+                _ => None,
+            })
+            .collect();
+        let prev = format_description!("[day padding:none]-[month repr:long]-[year] <<");
+        let next = format_description!(">> [day padding:none]-[month repr:long]-[year]");
+        assert_eq!(Date::parse(data[0], prev), Ok(date!(2025 - 02 - 01)));
+        assert_eq!(Date::parse(data[2], next), Ok(date!(2025 - 04 - 01)));
+    }
 }
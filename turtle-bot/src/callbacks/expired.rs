@@ -1,10 +1,13 @@
 use anyhow::bail;
 use teloxide::{
+    payloads::AnswerCallbackQuerySetters,
     requests::Requester,
     types::{CallbackQuery, ChatId, Message, MessageId},
     Bot,
 };
 
+use super::CallbackState;
+
 #[tracing::instrument(skip_all)]
 pub async fn expired_callback(bot: Bot, q: CallbackQuery) -> anyhow::Result<()> {
     let Some(Message { id, chat, .. }) = q.regular_message() else {
@@ -15,6 +18,36 @@ pub async fn expired_callback(bot: Bot, q: CallbackQuery) -> anyhow::Result<()>
     Ok(())
 }
 
+/// answers presses on the wizard of someone else, leaving the wizard as it is.
+#[tracing::instrument(skip_all)]
+pub async fn foreign_callback(bot: Bot, q: CallbackQuery) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id)
+        .text("This isn't your reminder 🐢")
+        .show_alert(true)
+        .await?;
+    Ok(())
+}
+
+/// Cancels the latest wizard of the user in the chat.
+pub async fn cancel_command(
+    bot: Bot,
+    chat_id: ChatId,
+    callback: CallbackState,
+) -> anyhow::Result<()> {
+    let Some(msg_id) = callback.msg_id() else {
+        bot.send_message(chat_id, "There's nothing to cancel 🐢")
+            .await?;
+        return Ok(());
+    };
+    callback.reset().await?;
+    // the message may have been deleted in the meantime
+    if let Err(e) = bot.edit_message_text(chat_id, msg_id, "Cancelled ❌").await {
+        tracing::warn!("error editing cancelled wizard: {e:#?}");
+        bot.send_message(chat_id, "Cancelled 🐢").await?;
+    }
+    Ok(())
+}
+
 pub async fn expired_callback_msg(
     bot: Bot,
     chat_id: ChatId,
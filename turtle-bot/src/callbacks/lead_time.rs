@@ -0,0 +1,260 @@
+use anyhow::bail;
+use teloxide::{
+    payloads::EditMessageTextSetters,
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+    },
+    Bot,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::{jobs::lead_time_label, media::ReminderMedia};
+
+use super::{
+    assigned_job_text, expired_callback_msg, job_text_keyboard, Assignment, CallbackPage,
+    CallbackState,
+};
+
+/// lead times offered in the picker, in minutes.
+const LEAD_TIMES: [i32; 6] = [15, 30, 60, 180, 1440, 10080];
+const LEAD_TIMES_PER_ROW: usize = 3;
+
+const LEAD_TIME: &str = "lead";
+const LEAD_DONE: &str = "lead-done";
+
+/// What each button in the lead time picker does.
+///
+/// The callback data is in the format of `lead:minutes` or `lead-done`.
+#[derive(Debug, PartialEq)]
+enum LeadAction {
+    Toggle(i32),
+    Done,
+}
+
+impl LeadAction {
+    fn to_data(&self) -> String {
+        match self {
+            Self::Toggle(minutes) => format!("{LEAD_TIME}:{minutes}"),
+            Self::Done => LEAD_DONE.to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for LeadAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let invalid = || format!("{value} is not a supported lead time action.");
+        match value.split_once(':') {
+            Some((LEAD_TIME, minutes)) => {
+                let minutes = minutes.parse().map_err(|_| invalid())?;
+                if !LEAD_TIMES.contains(&minutes) {
+                    return Err(invalid());
+                }
+                Ok(Self::Toggle(minutes))
+            }
+            None if value == LEAD_DONE => Ok(Self::Done),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// adds or removes the lead time, keeping the earliest warning first.
+fn toggle_lead_time(lead_times: &mut Vec<i32>, minutes: i32) {
+    if let Some(i) = lead_times.iter().position(|x| *x == minutes) {
+        lead_times.remove(i);
+    } else {
+        lead_times.push(minutes);
+        lead_times.sort_unstable_by(|a, b| b.cmp(a));
+    }
+}
+
+/// the lead times which are still ahead of `now`.
+fn available_lead_times(date_time: OffsetDateTime, now: OffsetDateTime) -> Vec<i32> {
+    LEAD_TIMES
+        .into_iter()
+        .filter(|x| date_time - Duration::minutes((*x).into()) > now)
+        .collect()
+}
+
+/// the confirmation text of the one-off reminder, with its assignees, advance warnings
+/// and whether it is urgent.
+pub fn warned_job_text(
+    date_time: OffsetDateTime,
+    text: &str,
+    media: Option<&ReminderMedia>,
+    assignment: &Assignment,
+    lead_times: &[i32],
+    urgent: bool,
+) -> String {
+    let text = assigned_job_text(date_time, text, media, assignment);
+    let heads_up = if lead_times.is_empty() {
+        String::new()
+    } else {
+        let labels: Vec<String> = lead_times.iter().map(|x| lead_time_label(*x)).collect();
+        format!("\n\nheads-up: {} before", labels.join(", "))
+    };
+    let urgent = if urgent {
+        "\n\nurgent: sent even during quiet hours"
+    } else {
+        ""
+    };
+    format!("{text}{heads_up}{urgent}")
+}
+
+fn lead_time_keyboard(available: &[i32], lead_times: &[i32]) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = available
+        .chunks(LEAD_TIMES_PER_ROW)
+        .map(|row| {
+            row.iter()
+                .map(|x| {
+                    let label = if lead_times.contains(x) {
+                        format!("✅ {}", lead_time_label(*x))
+                    } else {
+                        lead_time_label(*x)
+                    };
+                    InlineKeyboardButton::callback(label, LeadAction::Toggle(*x).to_data())
+                })
+                .collect()
+        })
+        .collect();
+
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Done",
+        LeadAction::Done.to_data(),
+    )]);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+pub async fn lead_time_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    date_time: OffsetDateTime,
+    lead_times: &[i32],
+) -> anyhow::Result<()> {
+    let available = available_lead_times(date_time, OffsetDateTime::now_utc());
+    let text = if available.is_empty() {
+        "This reminder is too soon for a heads-up 😅"
+    } else {
+        "How long before should I give a heads-up? 🐢\n\nTap as many as you like."
+    };
+    bot.edit_message_text(chat_id, msg_id, text)
+        .reply_markup(lead_time_keyboard(&available, lead_times))
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn lead_time_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    (date_time, msg_text, media, assignment, mut lead_times, urgent): (
+        OffsetDateTime,
+        String,
+        Option<ReminderMedia>,
+        Assignment,
+        Vec<i32>,
+        bool,
+    ),
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    let action = match LeadAction::try_from(data.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            expired_callback_msg(bot, chat.id, *id).await?;
+            bail!("{e}");
+        }
+    };
+
+    match action {
+        LeadAction::Toggle(minutes) => toggle_lead_time(&mut lead_times, minutes),
+        LeadAction::Done => {
+            // the picker may have been left open until some lead times passed
+            let available = available_lead_times(date_time, OffsetDateTime::now_utc());
+            lead_times.retain(|x| available.contains(x));
+
+            bot.edit_message_text(
+                chat.id,
+                *id,
+                warned_job_text(
+                    date_time,
+                    &msg_text,
+                    media.as_ref(),
+                    &assignment,
+                    &lead_times,
+                    urgent,
+                ),
+            )
+            .reply_markup(job_text_keyboard(true, urgent))
+            .await?;
+            p.update(CallbackPage::ConfirmOneOffJob {
+                date_time,
+                msg_text,
+                media,
+                editing: None,
+                assignment,
+                lead_times,
+                urgent,
+            })
+            .await?;
+            return Ok(());
+        }
+    }
+
+    lead_time_page(bot, chat.id, *id, date_time, &lead_times).await?;
+    p.update(CallbackPage::ReminderLeadTimes {
+        date_time,
+        msg_text,
+        media,
+        assignment,
+        lead_times,
+        urgent,
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::{available_lead_times, toggle_lead_time, LeadAction};
+
+    #[test]
+    fn lead_action_data() {
+        for action in [LeadAction::Toggle(1440), LeadAction::Done] {
+            let data = action.to_data();
+            assert_eq!(LeadAction::try_from(data.as_str()).unwrap(), action);
+        }
+        assert!(LeadAction::try_from("lead:7").is_err());
+    }
+
+    #[test]
+    fn lead_times() {
+        let mut lead_times = Vec::new();
+        toggle_lead_time(&mut lead_times, 15);
+        toggle_lead_time(&mut lead_times, 1440);
+        assert_eq!(lead_times, vec![1440, 15]);
+        toggle_lead_time(&mut lead_times, 15);
+        assert_eq!(lead_times, vec![1440]);
+
+        let due = datetime!(2024-07-02 10:00 +8);
+        assert_eq!(
+            available_lead_times(due, datetime!(2024-07-02 08:30 +8)),
+            vec![15, 30, 60]
+        );
+    }
+}
@@ -1,5 +1,9 @@
+use crate::{
+    callbacks::{date_page, expired_callback_msg, recurrence_page, CallbackPage},
+    timezone::{chat_timezone, now_in},
+};
 use anyhow::bail;
-use gaia::stickers::Stickers;
+use sqlx::PgPool;
 use teloxide::{
     payloads::{EditMessageTextSetters, SendMessageSetters},
     requests::Requester,
@@ -9,12 +13,6 @@ use teloxide::{
     },
     Bot,
 };
-use time::{macros::offset, OffsetDateTime};
-
-use crate::{
-    callbacks::{date_page, expired_callback_msg, CallbackPage},
-    sticker::send_sticker,
-};
 
 use super::CallbackState;
 
@@ -67,13 +65,14 @@ fn occurence_keyboard() -> InlineKeyboardMarkup {
 
 #[allow(deprecated)]
 #[tracing::instrument(skip_all)]
-pub async fn new_occurence_page(bot: Bot, chat_id: ChatId) -> anyhow::Result<()> {
+pub async fn new_occurence_page(bot: Bot, chat_id: ChatId) -> anyhow::Result<MessageId> {
     let keyboard = occurence_keyboard();
-    bot.send_message(chat_id, OCCURENCE_DESCRIPTION)
+    let sent = bot
+        .send_message(chat_id, OCCURENCE_DESCRIPTION)
         .parse_mode(ParseMode::Markdown)
         .reply_markup(keyboard)
         .await?;
-    Ok(())
+    Ok(sent.id)
 }
 
 #[allow(deprecated)]
@@ -90,7 +89,7 @@ pub async fn occurence_callback(
     bot: Bot,
     q: CallbackQuery,
     p: CallbackState,
-    stickers: Stickers,
+    pool: PgPool,
 ) -> anyhow::Result<()> {
     bot.answer_callback_query(q.id.clone()).await?;
     let Some(ref data) = q.data else {
@@ -110,14 +109,25 @@ pub async fn occurence_callback(
     };
     match occurence {
         OccurenceState::OneOff => {
-            let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
-            p.update(CallbackPage::RemindDate).await?;
+            let tz = chat_timezone(&pool, chat.id.0).await?;
+            let now = now_in(tz);
+            p.update(CallbackPage::RemindDate { editing: None }).await?;
             tracing::debug!("changed callback state to date");
-            date_page(bot, chat.id, *id, now.day(), now.month().into(), now.year()).await?;
+            date_page(
+                bot,
+                chat.id,
+                *id,
+                now.day(),
+                now.month().into(),
+                now.year(),
+                tz,
+            )
+            .await?;
         }
         OccurenceState::Recurring => {
-            bot.delete_message(chat.id, *id).await?;
-            send_sticker(&bot, &chat.id, stickers.coming_soon).await?;
+            p.update(CallbackPage::Recurrence).await?;
+            tracing::debug!("changed callback state to recurrence");
+            recurrence_page(bot, chat.id, *id).await?;
         }
     }
     Ok(())
@@ -0,0 +1,324 @@
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use teloxide::{payloads::SendMessageSetters, requests::Requester, types::ChatId, Bot};
+use time::{
+    macros::format_description, Date, Duration, OffsetDateTime, PrimitiveDateTime, Time, Weekday,
+};
+
+use crate::timezone::{chat_timezone, from_local, now_in, to_local};
+
+use super::{job_text, job_text_keyboard, Assignment, CallbackPage, CallbackState};
+
+/// how far ahead a relative reminder may be due, eg. `in 520 weeks`.
+const MAX_AHEAD: Duration = Duration::weeks(520);
+
+const USAGE: &str = r"I can't remind you that far ahead 😅 try something like:
+
+/remind in 20 minutes stretch
+/remind tomorrow 9am submit report
+/remind fri 17:30 drinks
+/remind 2024-12-01 08:00 pay rent";
+
+#[derive(Debug, PartialEq)]
+pub enum ReminderParseError {
+    /// the arguments don't match any of the supported forms.
+    Unknown,
+    /// the reminder would be due too far ahead.
+    OutOfRange,
+}
+
+/// parses the arguments of `/remind` into a due datetime and reminder text.
+///
+/// supported forms, always followed by the reminder text:
+///
+/// - `in 20 minutes`, `in 2h`, `in 3 days`
+/// - `today 18:00`, `tonight 9pm`, `tomorrow 9am`
+/// - `fri 17:30`, `monday 8.15am`
+/// - `2024-12-01 08:00`
+///
+/// `now` and the returned datetime are in the local time of `tz`.
+pub fn parse_reminder(
+    input: &str,
+    now: OffsetDateTime,
+    tz: Tz,
+) -> Result<(OffsetDateTime, String), ReminderParseError> {
+    use ReminderParseError::Unknown;
+
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    let first = tokens.first().ok_or(Unknown)?.to_lowercase();
+
+    let (due, used) = match first.as_str() {
+        "in" => {
+            let (duration, used) = parse_duration(&tokens[1..])?;
+            let due = (now + duration).replace_second(0).map_err(|_| Unknown)?;
+            (to_local(tz, due), used + 1)
+        }
+        "today" | "tonight" => at_time(now.date(), &tokens[1..], tz).ok_or(Unknown)?,
+        "tomorrow" | "tmr" => {
+            let date = now.date().next_day().ok_or(Unknown)?;
+            at_time(date, &tokens[1..], tz).ok_or(Unknown)?
+        }
+        word => {
+            if let Some(weekday) = parse_weekday(word) {
+                let (time, used) = parse_time(&tokens[1..]).ok_or(Unknown)?;
+                let mut date = now.date();
+                while date.weekday() != weekday || combine(date, time, tz) <= now {
+                    date = date.next_day().ok_or(Unknown)?;
+                }
+                (combine(date, time, tz), used + 1)
+            } else {
+                let date = Date::parse(word, format_description!("[year]-[month]-[day]"))
+                    .map_err(|_| Unknown)?;
+                at_time(date, &tokens[1..], tz).ok_or(Unknown)?
+            }
+        }
+    };
+
+    let text = tokens[used..].join(" ");
+    if text.is_empty() {
+        return Err(Unknown);
+    }
+    Ok((due, text))
+}
+
+fn combine(date: Date, time: Time, tz: Tz) -> OffsetDateTime {
+    from_local(tz, PrimitiveDateTime::new(date, time))
+}
+
+fn at_time(date: Date, tokens: &[&str], tz: Tz) -> Option<(OffsetDateTime, usize)> {
+    let (time, used) = parse_time(tokens)?;
+    Some((combine(date, time, tz), used + 1))
+}
+
+/// parses `20 minutes`, `20min` or `2h` from the start of the tokens.
+///
+/// durations longer than [`MAX_AHEAD`] are out of range.
+fn parse_duration(tokens: &[&str]) -> Result<(Duration, usize), ReminderParseError> {
+    use ReminderParseError::{OutOfRange, Unknown};
+
+    let first = tokens.first().ok_or(Unknown)?.to_lowercase();
+    let split = first
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(first.len());
+    let (amount, unit) = first.split_at(split);
+    if amount.is_empty() {
+        return Err(Unknown);
+    }
+    // only digits are left, so it can only fail by being too large
+    let amount: i64 = amount.parse().map_err(|_| OutOfRange)?;
+
+    let (unit, used) = if unit.is_empty() {
+        (tokens.get(1).ok_or(Unknown)?.to_lowercase(), 2)
+    } else {
+        (unit.to_string(), 1)
+    };
+
+    let unit = match unit.as_str() {
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::MINUTE,
+        "h" | "hr" | "hrs" | "hour" | "hours" => Duration::HOUR,
+        "d" | "day" | "days" => Duration::DAY,
+        "w" | "week" | "weeks" => Duration::WEEK,
+        _ => return Err(Unknown),
+    };
+    let duration = unit
+        .whole_seconds()
+        .checked_mul(amount)
+        .map(Duration::seconds)
+        .ok_or(OutOfRange)?;
+    if duration <= Duration::ZERO {
+        return Err(Unknown);
+    }
+    if duration > MAX_AHEAD {
+        return Err(OutOfRange);
+    }
+    Ok((duration, used))
+}
+
+/// parses `17:30`, `9am`, `9.30pm` or `9 pm` from the start of the tokens.
+///
+/// returns the time and the number of tokens used.
+pub fn parse_time(tokens: &[&str]) -> Option<(Time, usize)> {
+    let first = tokens.first()?.to_lowercase();
+
+    let (clock, meridiem, used) = if let Some(clock) = first.strip_suffix("am") {
+        (clock.to_string(), Some(false), 1)
+    } else if let Some(clock) = first.strip_suffix("pm") {
+        (clock.to_string(), Some(true), 1)
+    } else {
+        match tokens.get(1).map(|x| x.to_lowercase()).as_deref() {
+            Some("am") => (first, Some(false), 2),
+            Some("pm") => (first, Some(true), 2),
+            _ => (first, None, 1),
+        }
+    };
+
+    let (hour, minute) = match clock.split_once([':', '.']) {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        // a bare number is only a time when it has am/pm, eg. `9am`
+        None if meridiem.is_some() => (clock.parse().ok()?, 0),
+        _ => return None,
+    };
+
+    let hour: u8 = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    Some((Time::from_hms(hour, minute, 0).ok()?, used))
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        "mon" | "monday" => Weekday::Monday,
+        "tue" | "tues" | "tuesday" => Weekday::Tuesday,
+        "wed" | "wednesday" => Weekday::Wednesday,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thursday,
+        "fri" | "friday" => Weekday::Friday,
+        "sat" | "saturday" => Weekday::Saturday,
+        "sun" | "sunday" => Weekday::Sunday,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+/// tries to skip the wizard using the arguments of `/remind`.
+///
+/// returns `false` when the arguments can't be used, so that the caller falls
+/// back to the wizard. arguments which are understood but can't be reminded of,
+/// eg. in the past, are answered here instead.
+pub async fn quick_remind_page(
+    bot: Bot,
+    chat_id: ChatId,
+    args: &str,
+    callback: CallbackState,
+    pool: &PgPool,
+) -> anyhow::Result<bool> {
+    let tz = chat_timezone(pool, chat_id.0).await?;
+    let now = now_in(tz);
+    let (date_time, msg_text) = match parse_reminder(args, now, tz) {
+        Ok(x) => x,
+        Err(ReminderParseError::Unknown) => return Ok(false),
+        Err(ReminderParseError::OutOfRange) => {
+            bot.send_message(chat_id, USAGE).await?;
+            return Ok(true);
+        }
+    };
+    if date_time <= now {
+        bot.send_message(chat_id, "You can't send a message into the past. ❌")
+            .await?;
+        return Ok(true);
+    }
+
+    let sent = bot
+        .send_message(chat_id, job_text(date_time, &msg_text, None))
+        .reply_markup(job_text_keyboard(true, false))
+        .await?;
+    callback
+        .start(
+            sent.id,
+            CallbackPage::ConfirmOneOffJob {
+                date_time,
+                msg_text,
+                media: None,
+                editing: None,
+                assignment: Assignment::default(),
+                lead_times: Vec::new(),
+                urgent: false,
+            },
+        )
+        .await?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    // a wednesday
+    const NOW: OffsetDateTime = datetime!(2024-06-19 10:15:42 +8);
+
+    fn parse(input: &str) -> Option<(OffsetDateTime, String)> {
+        parse_reminder(input, NOW, Tz::Asia__Singapore).ok()
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(
+            parse("in 20 minutes stretch"),
+            Some((datetime!(2024-06-19 10:35 +8), "stretch".to_string()))
+        );
+        assert_eq!(
+            parse("in 2h call mum").map(|x| x.0),
+            Some(datetime!(2024-06-19 12:15 +8))
+        );
+        assert_eq!(parse("in 0 minutes stretch"), None);
+        assert_eq!(parse("in 20 stretch"), None);
+    }
+
+    #[test]
+    fn too_far_ahead() {
+        for input in [
+            "in 99999999999999w x",
+            "in 99999999999999999999 minutes x",
+            "in 600 weeks x",
+        ] {
+            assert_eq!(
+                parse_reminder(input, NOW, Tz::Asia__Singapore),
+                Err(ReminderParseError::OutOfRange),
+                "{input}"
+            );
+        }
+        assert!(parse("in 520 weeks x").is_some());
+    }
+
+    #[test]
+    fn day_and_time() {
+        assert_eq!(
+            parse("tomorrow 9am submit report"),
+            Some((datetime!(2024-06-20 9:00 +8), "submit report".to_string()))
+        );
+        assert_eq!(
+            parse("today 12 pm lunch").map(|x| x.0),
+            Some(datetime!(2024-06-19 12:00 +8))
+        );
+        assert_eq!(
+            parse("tonight 9.30pm sleep").map(|x| x.0),
+            Some(datetime!(2024-06-19 21:30 +8))
+        );
+        assert_eq!(
+            parse("fri 17:30 drinks").map(|x| x.0),
+            Some(datetime!(2024-06-21 17:30 +8))
+        );
+        assert_eq!(
+            parse("wed 9:00 standup").map(|x| x.0),
+            Some(datetime!(2024-06-26 9:00 +8))
+        );
+        assert_eq!(
+            parse("2024-12-01 08:00 pay rent").map(|x| x.0),
+            Some(datetime!(2024-12-01 8:00 +8))
+        );
+    }
+
+    #[test]
+    fn daylight_saving() {
+        let now = datetime!(2024-03-30 10:00 +0);
+        assert_eq!(
+            parse_reminder("tomorrow 9am run", now, Tz::Europe__London)
+                .ok()
+                .map(|x| x.0),
+            Some(datetime!(2024-03-31 9:00 +1))
+        );
+    }
+
+    #[test]
+    fn unparseable() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("submit report"), None);
+        assert_eq!(parse("tomorrow submit report"), None);
+        assert_eq!(parse("tomorrow 25:00 submit report"), None);
+        assert_eq!(parse("tomorrow 9am"), None);
+    }
+}
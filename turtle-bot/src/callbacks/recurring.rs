@@ -0,0 +1,651 @@
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use teloxide::{
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+        ParseMode,
+    },
+    Bot,
+};
+use time::Weekday;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+use crate::{jobs::add_recurring_reminder, timezone::DEFAULT_TIMEZONE};
+
+use super::{
+    expired_callback_msg, occurence_page, recurring_time_page, CallbackPage, CallbackState,
+    RemindTime,
+};
+
+const DAILY: &str = "Daily";
+const WEEKLY: &str = "Weekly";
+const MONTHLY: &str = "Monthly";
+const CUSTOM: &str = "Custom";
+
+const BACK: &str = "Back";
+const NEXT: &str = "Next";
+const CONFIRM: &str = "Confirm";
+const CHANGE_SCHEDULE: &str = "Change Schedule";
+
+const RECURRENCE_DESCRIPTION: &str = r"How often should I remind you? 🐢
+
+**Custom** lets you type your own cron expression.";
+
+const CRON_DESCRIPTION: &str = r"Send me the cron expression in your next message. 🐢
+
+Format: `minute hour day-of-month month day-of-week`
+e.g. `30 9 * * Mon-Fri` is 09:30 on every weekday.
+
+Use day names (`Mon`, `Tue`...) or numbers from `0` (Sunday) to `6` for the day of week.";
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    /// day of the month
+    Monthly(u8),
+}
+
+impl Recurrence {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Daily => "every day".to_string(),
+            Self::Weekly(weekdays) => format!("every {}", weekday_list(weekdays, ", ")),
+            Self::Monthly(day) => format!("day {day} of every month"),
+        }
+    }
+
+    /// The cron expression follows `tokio_cron_scheduler`'s format, which has
+    /// an extra `seconds` field in front.
+    pub fn schedule(&self, hour: u8, minute: u8) -> RecurringSchedule {
+        let cron_str = match self {
+            Self::Daily => format!("0 {minute} {hour} * * *"),
+            Self::Weekly(weekdays) => {
+                format!("0 {minute} {hour} * * {}", weekday_list(weekdays, ","))
+            }
+            Self::Monthly(day) => format!("0 {minute} {hour} {day} * *"),
+        };
+        RecurringSchedule {
+            cron_str,
+            description: format!("{} at {hour:02}:{minute:02}", self.describe()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecurringSchedule {
+    pub cron_str: String,
+    pub description: String,
+}
+
+fn weekday_short(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Monday => "Mon",
+        Weekday::Tuesday => "Tue",
+        Weekday::Wednesday => "Wed",
+        Weekday::Thursday => "Thu",
+        Weekday::Friday => "Fri",
+        Weekday::Saturday => "Sat",
+        Weekday::Sunday => "Sun",
+    }
+}
+
+fn weekday_list(weekdays: &[Weekday], separator: &str) -> String {
+    weekdays
+        .iter()
+        .map(|x| weekday_short(*x))
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+/// the day of week of the usual cron, where Sunday is `0` or `7`, as a day name.
+fn standard_weekday(value: &str) -> Option<String> {
+    match value.parse::<u8>() {
+        Ok(x @ 0..=7) => Some(weekday_short(Weekday::Sunday.nth_next(x % 7)).to_string()),
+        Ok(_) => None,
+        Err(_) => Some(value.to_string()),
+    }
+}
+
+/// The day of week field of the usual cron, with day names in place of numbers.
+///
+/// `tokio_cron_scheduler` counts the days from Sunday as `1`, so `1-5` would
+/// otherwise be Sunday to Thursday.
+fn standard_weekdays(field: &str) -> Option<String> {
+    let items = field.split(',').map(|item| {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+        let range = match range.split_once('-') {
+            // Sunday as `7` ends the range, which day names can't wrap around to
+            Some(("0", "7")) => "Sun-Sat".to_string(),
+            Some((start, "7")) if step.is_none() => format!("{}-Sat,Sun", standard_weekday(start)?),
+            Some((_, "7")) => return None,
+            Some((start, end)) => {
+                format!("{}-{}", standard_weekday(start)?, standard_weekday(end)?)
+            }
+            None => standard_weekday(range)?,
+        };
+        Some(match step {
+            Some(step) => format!("{range}/{step}"),
+            None => range,
+        })
+    });
+    Some(items.collect::<Option<Vec<String>>>()?.join(","))
+}
+
+/// Turns the user's cron expression into one that `tokio_cron_scheduler` accepts.
+///
+/// The usual 5 fields cron expression gets a `0` seconds field in front, and
+/// day names for its numbered days of week. expressions with 6 or 7 fields are
+/// already in `tokio_cron_scheduler`'s format.
+#[must_use]
+pub fn parse_cron(text: &str) -> Option<String> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    let cron_str = match fields.as_slice() {
+        [minute, hour, month_day, month, weekday] => format!(
+            "0 {minute} {hour} {month_day} {month} {}",
+            standard_weekdays(weekday)?
+        ),
+        [_, _, _, _, _, _] | [_, _, _, _, _, _, _] => fields.join(" "),
+        _ => return None,
+    };
+
+    Job::new_async_tz(cron_str.as_str(), DEFAULT_TIMEZONE, |_, _| {
+        Box::pin(async {})
+    })
+    .ok()
+    .map(|_| cron_str)
+}
+
+fn recurrence_keyboard() -> InlineKeyboardMarkup {
+    let keyboard: Vec<Vec<InlineKeyboardButton>> = vec![
+        vec![
+            InlineKeyboardButton::callback(DAILY, DAILY),
+            InlineKeyboardButton::callback(WEEKLY, WEEKLY),
+        ],
+        vec![
+            InlineKeyboardButton::callback(MONTHLY, MONTHLY),
+            InlineKeyboardButton::callback(CUSTOM, CUSTOM),
+        ],
+        vec![InlineKeyboardButton::callback(BACK, BACK)],
+    ];
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+#[allow(deprecated)]
+#[tracing::instrument(skip_all)]
+pub async fn recurrence_page(bot: Bot, chat_id: ChatId, msg_id: MessageId) -> anyhow::Result<()> {
+    bot.edit_message_text(chat_id, msg_id, RECURRENCE_DESCRIPTION)
+        .parse_mode(ParseMode::Markdown)
+        .reply_markup(recurrence_keyboard())
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn recurrence_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no message data")
+    };
+
+    match data.as_ref() {
+        DAILY => {
+            let remind_time = RemindTime::default();
+            p.update(CallbackPage::RecurringTime {
+                recurrence: Recurrence::Daily,
+                time: remind_time.clone(),
+            })
+            .await?;
+            recurring_time_page(bot, chat.id, *id, &Recurrence::Daily, remind_time).await?;
+        }
+        WEEKLY => {
+            p.update(CallbackPage::RecurringWeekdays {
+                weekdays: Vec::new(),
+            })
+            .await?;
+            weekday_page(bot, chat.id, *id, &[]).await?;
+        }
+        MONTHLY => {
+            p.update(CallbackPage::RecurringMonthDay).await?;
+            month_day_page(bot, chat.id, *id).await?;
+        }
+        CUSTOM => {
+            p.update(CallbackPage::RecurringCron).await?;
+            cron_page(bot, chat.id, *id).await?;
+        }
+        BACK => {
+            p.update(CallbackPage::Occcurence).await?;
+            occurence_page(bot, chat.id, *id).await?;
+        }
+        unknown => {
+            tracing::error!(unknown, "unrecognizable value");
+            expired_callback_msg(bot, chat.id, *id).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn weekday_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    weekdays: &[Weekday],
+) -> anyhow::Result<()> {
+    let weekday_buttons: Vec<InlineKeyboardButton> = WEEKDAYS
+        .iter()
+        .map(|x| {
+            let name = weekday_short(*x);
+            let text = if weekdays.contains(x) {
+                format!("✅ {name}")
+            } else {
+                name.to_string()
+            };
+            InlineKeyboardButton::callback(text, name)
+        })
+        .collect();
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = weekday_buttons
+        .chunks(4)
+        .map(<[InlineKeyboardButton]>::to_vec)
+        .collect();
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(BACK, BACK),
+        InlineKeyboardButton::callback(NEXT, NEXT),
+    ]);
+
+    bot.edit_message_text(chat_id, msg_id, "Pick the day(s) of the week 🐢")
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn weekday_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    weekdays: Vec<Weekday>,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no message data")
+    };
+
+    match data.as_ref() {
+        BACK => {
+            p.update(CallbackPage::Recurrence).await?;
+            recurrence_page(bot, chat.id, *id).await?;
+        }
+        NEXT => {
+            if weekdays.is_empty() {
+                return Ok(());
+            }
+            let recurrence = Recurrence::Weekly(weekdays);
+            let remind_time = RemindTime::default();
+            p.update(CallbackPage::RecurringTime {
+                recurrence: recurrence.clone(),
+                time: remind_time.clone(),
+            })
+            .await?;
+            recurring_time_page(bot, chat.id, *id, &recurrence, remind_time).await?;
+        }
+        name => {
+            let Some(chosen) = WEEKDAYS.into_iter().find(|x| weekday_short(*x) == name) else {
+                tracing::error!(name, "unrecognizable value");
+                expired_callback_msg(bot, chat.id, *id).await?;
+                bail!("invalid weekday");
+            };
+
+            // rebuilt from `WEEKDAYS` so that the days stay in order
+            let weekdays: Vec<Weekday> = WEEKDAYS
+                .into_iter()
+                .filter(|x| (*x == chosen) != weekdays.contains(x))
+                .collect();
+
+            p.update(CallbackPage::RecurringWeekdays {
+                weekdays: weekdays.clone(),
+            })
+            .await?;
+            weekday_page(bot, chat.id, *id, &weekdays).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn month_day_page(bot: Bot, chat_id: ChatId, msg_id: MessageId) -> anyhow::Result<()> {
+    let day_buttons: Vec<InlineKeyboardButton> = (1..=31)
+        .map(|i: u8| InlineKeyboardButton::callback(i.to_string(), i.to_string()))
+        .collect();
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = day_buttons
+        .chunks(7)
+        .map(<[InlineKeyboardButton]>::to_vec)
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(BACK, BACK)]);
+
+    let text = r"Pick the day of the month 🐢
+
+Months without that day will be skipped.";
+
+    bot.edit_message_text(chat_id, msg_id, text)
+        .reply_markup(InlineKeyboardMarkup::new(keyboard))
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn month_day_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no message data")
+    };
+
+    if data.as_str() == BACK {
+        p.update(CallbackPage::Recurrence).await?;
+        recurrence_page(bot, chat.id, *id).await?;
+        return Ok(());
+    }
+
+    let Some(day) = data.parse::<u8>().ok().filter(|x| (1..=31).contains(x)) else {
+        tracing::error!(data, "unrecognizable value");
+        expired_callback_msg(bot, chat.id, *id).await?;
+        bail!("invalid day of month");
+    };
+
+    let recurrence = Recurrence::Monthly(day);
+    let remind_time = RemindTime::default();
+    p.update(CallbackPage::RecurringTime {
+        recurrence: recurrence.clone(),
+        time: remind_time.clone(),
+    })
+    .await?;
+    recurring_time_page(bot, chat.id, *id, &recurrence, remind_time).await?;
+    Ok(())
+}
+
+#[allow(deprecated)]
+async fn cron_page(bot: Bot, chat_id: ChatId, msg_id: MessageId) -> anyhow::Result<()> {
+    bot.edit_message_text(chat_id, msg_id, CRON_DESCRIPTION)
+        .parse_mode(ParseMode::Markdown)
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::callback(BACK, BACK),
+        ]]))
+        .await?;
+    Ok(())
+}
+
+/// only the `Back` button is available while waiting for the cron expression.
+#[tracing::instrument(skip_all)]
+pub async fn cron_callback(bot: Bot, q: CallbackQuery, p: CallbackState) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no message data")
+    };
+
+    p.update(CallbackPage::Recurrence).await?;
+    recurrence_page(bot, chat.id, *id).await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn confirm_cron_text(
+    bot: Bot,
+    msg: Message,
+    callback: CallbackState,
+) -> anyhow::Result<()> {
+    let Some(text) = msg.text() else {
+        bail!("no text")
+    };
+
+    let Some(cron_str) = parse_cron(text) else {
+        bot.send_message(
+            msg.chat.id,
+            "That doesn't look like a valid cron expression ❌ Please try again.",
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let schedule = RecurringSchedule {
+        description: format!("cron `{cron_str}`"),
+        cron_str,
+    };
+
+    let sent = bot
+        .send_message(msg.chat.id, recurring_text(&schedule))
+        .reply_markup(change_schedule_keyboard())
+        .await?;
+
+    callback
+        .move_to(sent.id, CallbackPage::ConfirmRecurrence { schedule })
+        .await?;
+    Ok(())
+}
+
+fn recurring_text(schedule: &RecurringSchedule) -> String {
+    format!(
+        r"You have chosen: {}
+
+What is it that you want me to remind you of?
+Say it in your next message. 🐢",
+        schedule.description
+    )
+}
+
+fn change_schedule_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        BACK,
+        CHANGE_SCHEDULE,
+    )]])
+}
+
+pub async fn recurring_text_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    schedule: &RecurringSchedule,
+) -> anyhow::Result<()> {
+    bot.edit_message_text(chat_id, msg_id, recurring_text(schedule))
+        .reply_markup(change_schedule_keyboard())
+        .await?;
+    Ok(())
+}
+
+pub async fn change_schedule_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no message data")
+    };
+
+    p.update(CallbackPage::Recurrence).await?;
+    recurrence_page(bot, chat.id, *id).await?;
+    Ok(())
+}
+
+pub async fn confirm_recurring_text(
+    bot: Bot,
+    msg: Message,
+    schedule: RecurringSchedule,
+    callback: CallbackState,
+) -> anyhow::Result<()> {
+    let Some(text) = msg.text() else {
+        bail!("no text")
+    };
+
+    if text.is_empty() {
+        bail!("empty text")
+    }
+
+    let job_msg = format!(
+        r"You have chosen: {}
+
+text:
+{text}",
+        schedule.description
+    );
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(BACK, BACK),
+        InlineKeyboardButton::callback(CONFIRM, CONFIRM),
+    ]]);
+
+    let sent = bot
+        .send_message(msg.chat.id, job_msg)
+        .reply_markup(keyboard)
+        .await?;
+
+    callback
+        .move_to(
+            sent.id,
+            CallbackPage::ConfirmRecurringJob {
+                schedule,
+                msg_text: text.to_string(),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn recurring_job_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    (schedule, msg_text): (RecurringSchedule, String),
+    pool: PgPool,
+    sched: JobScheduler,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(ref username) = q.from.username else {
+        tracing::warn!("no username given for this reminder text");
+        bail!("wtf");
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    match data.as_ref() {
+        BACK => {
+            p.update(CallbackPage::ConfirmRecurrence {
+                schedule: schedule.clone(),
+            })
+            .await?;
+
+            recurring_text_page(bot, chat.id, *id, &schedule).await?;
+        }
+        CONFIRM => {
+            add_recurring_reminder(
+                &bot,
+                &pool,
+                &sched,
+                chat.id.0,
+                Some(username.clone()),
+                msg_text.clone(),
+                &schedule.cron_str,
+            )
+            .await?;
+
+            p.reset().await?;
+
+            let text = format!("confirmed 🐢 - I will remind you {}.", schedule.description);
+            bot.edit_message_text(chat.id, *id, text).await?;
+        }
+        _ => expired_callback_msg(bot, chat.id, *id).await?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{macros::datetime, Weekday};
+
+    use crate::{ical::cron_event, timezone::DEFAULT_TIMEZONE};
+
+    use super::{parse_cron, Recurrence};
+
+    #[test]
+    fn weekly_schedule() {
+        let recurrence = Recurrence::Weekly(vec![Weekday::Monday, Weekday::Friday]);
+        let schedule = recurrence.schedule(9, 5);
+        assert_eq!(schedule.cron_str, "0 5 9 * * Mon,Fri");
+        assert_eq!(schedule.description, "every Mon, Fri at 09:05");
+    }
+
+    #[test]
+    fn cron_parse() {
+        assert_eq!(
+            parse_cron("30 9 * * Mon-Fri").unwrap(),
+            "0 30 9 * * Mon-Fri"
+        );
+        assert_eq!(parse_cron("0 30 9 1 * *").unwrap(), "0 30 9 1 * *");
+        assert!(parse_cron("every day").is_none());
+        assert!(parse_cron("99 9 * * *").is_none());
+    }
+
+    #[test]
+    fn cron_numbered_weekdays() {
+        let cron_str = parse_cron("30 9 * * 1-5").unwrap();
+        assert_eq!(cron_str, "0 30 9 * * Mon-Fri");
+        let (_, rrule) =
+            cron_event(&cron_str, datetime!(2024-07-02 00:00 UTC), DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(rrule, "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR");
+
+        assert_eq!(parse_cron("0 8 * * 0,6").unwrap(), "0 0 8 * * Sun,Sat");
+        assert_eq!(parse_cron("0 8 * * 5-7").unwrap(), "0 0 8 * * Fri-Sat,Sun");
+        assert_eq!(parse_cron("0 8 * * 0-7").unwrap(), "0 0 8 * * Sun-Sat");
+        assert_eq!(parse_cron("0 8 * * */2").unwrap(), "0 0 8 * * */2");
+        assert!(parse_cron("0 8 * * 8").is_none());
+    }
+}
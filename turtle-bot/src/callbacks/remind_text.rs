@@ -1,29 +1,41 @@
-use std::time::Duration;
-
 use anyhow::bail;
 use sqlx::PgPool;
 use teloxide::{
-    payloads::{EditMessageTextSetters, SendMessageSetters},
+    payloads::{EditMessageReplyMarkupSetters, EditMessageTextSetters, SendMessageSetters},
     requests::Requester,
     types::{
         CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
     },
     Bot,
 };
-use time::{macros::offset, OffsetDateTime};
-use tokio_cron_scheduler::{Job, JobScheduler};
+use time::OffsetDateTime;
+use tokio_cron_scheduler::JobScheduler;
 
-use super::{expired_callback_msg, time_check, CallbackPage, CallbackState};
+use crate::{
+    jobs::{add_one_off_reminder, NewReminder},
+    media::{preview_text, ReminderMedia},
+    timezone::{chat_timezone, now_in},
+};
+
+use super::{
+    assign_page, expired_callback_msg, lead_time_page, reschedule_reminder, time_check, Assignment,
+    CallbackPage, CallbackState, EditingReminder,
+};
 
 const JOB_TEXT_BACK: &str = "Back";
 const JOB_TEXT_CONFIRM: &str = "Confirm";
 const CHANGE_TIME: &str = "Change Time";
+const KEEP_TEXT: &str = "Keep Text";
+const ASSIGN: &str = "Assign";
+const HEADS_UP: &str = "Heads-up";
+const URGENT: &str = "Urgent";
 
 pub async fn remind_text_page(
     bot: Bot,
     chat_id: ChatId,
     msg_id: MessageId,
     chosen_datetime: OffsetDateTime,
+    editing: Option<&EditingReminder>,
 ) -> anyhow::Result<()> {
     let chosen_year = chosen_datetime.year();
     let chosen_month = chosen_datetime.month();
@@ -41,37 +53,44 @@ hour: {chosen_hour}
 minute: {chosen_minute}
 
 What is it that you want me to remind you of?
-Say it in your next message. 🐢"
+Say it in your next message. 🐢
+
+You can also send a photo, document, voice note or sticker, and I'll send it back."
     );
+    let mut buttons = vec![InlineKeyboardButton::callback("Back", CHANGE_TIME)];
+
+    let text = match editing {
+        Some(editing) => {
+            buttons.push(InlineKeyboardButton::callback(KEEP_TEXT, KEEP_TEXT));
+            format!(
+                r"{text}
+
+Current text:
+{}",
+                preview_text(editing.media.as_ref(), &editing.message)
+            )
+        }
+        None => text,
+    };
 
     bot.edit_message_text(chat_id, msg_id, text)
-        .reply_markup(InlineKeyboardMarkup::new(vec![vec![
-            InlineKeyboardButton::callback("Back", CHANGE_TIME),
-        ]]))
+        .reply_markup(InlineKeyboardMarkup::new(vec![buttons]))
         .await?;
     Ok(())
 }
 
-pub async fn confirm_reminder_text(
-    bot: Bot,
-    msg: Message,
+pub fn job_text(
     chosen_datetime: OffsetDateTime,
-    callback: CallbackState,
-) -> anyhow::Result<()> {
-    let Some(text) = msg.text() else {
-        bail!("no text")
-    };
-
-    if text.is_empty() {
-        bail!("empty text")
-    }
+    text: &str,
+    media: Option<&ReminderMedia>,
+) -> String {
     let chosen_year = chosen_datetime.year();
     let chosen_month = chosen_datetime.month();
     let chosen_day = chosen_datetime.day();
     let chosen_hour = chosen_datetime.hour();
     let chosen_minute = chosen_datetime.minute();
 
-    let job_msg = format!(
+    format!(
         r"You have chosen:
 
 year: {chosen_year}
@@ -81,38 +100,128 @@ hour: {chosen_hour}
 minute: {chosen_minute}
 
 text:
-{text}"
-    );
+{}",
+        preview_text(media, text)
+    )
+}
 
-    callback
-        .update(CallbackPage::ConfirmOneOffJob {
-            date_time: chosen_datetime,
-            msg_text: text.to_string(),
-        })
-        .await?;
+pub async fn confirm_reminder_text(
+    bot: Bot,
+    msg: Message,
+    (chosen_datetime, editing): (OffsetDateTime, Option<EditingReminder>),
+    callback: CallbackState,
+) -> anyhow::Result<()> {
+    let media = ReminderMedia::from_message(&msg);
+    // the caption becomes the text of media reminders
+    let text = msg.text().or(msg.caption()).unwrap_or_default();
 
-    let keyboard = job_text_keyboard();
+    if text.is_empty() && media.is_none() {
+        bail!("no text or media")
+    }
+
+    let keyboard = job_text_keyboard(editing.is_none(), false);
+    let confirm_text = job_text(chosen_datetime, text, media.as_ref());
 
-    bot.send_message(msg.chat.id, job_msg)
+    let sent = bot
+        .send_message(msg.chat.id, confirm_text)
         .reply_markup(keyboard)
         .await?;
 
+    callback
+        .move_to(
+            sent.id,
+            CallbackPage::ConfirmOneOffJob {
+                date_time: chosen_datetime,
+                msg_text: text.to_string(),
+                media,
+                editing,
+                assignment: Assignment::default(),
+                lead_times: Vec::new(),
+                urgent: false,
+            },
+        )
+        .await?;
+
     Ok(())
 }
-fn job_text_keyboard() -> InlineKeyboardMarkup {
-    let keyboard: Vec<Vec<InlineKeyboardButton>> = vec![vec![
+/// `new_reminder` shows the buttons for assigning members, advance warnings and
+/// urgency, which are only available to new reminders.
+pub fn job_text_keyboard(new_reminder: bool, urgent: bool) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![vec![
         InlineKeyboardButton::callback(JOB_TEXT_BACK, JOB_TEXT_BACK),
         InlineKeyboardButton::callback(JOB_TEXT_CONFIRM, JOB_TEXT_CONFIRM),
     ]];
+    if new_reminder {
+        keyboard.push(vec![
+            InlineKeyboardButton::callback(ASSIGN, ASSIGN),
+            InlineKeyboardButton::callback(HEADS_UP, HEADS_UP),
+        ]);
+        let label = if urgent {
+            format!("✅ {URGENT}")
+        } else {
+            URGENT.to_string()
+        };
+        keyboard.push(vec![InlineKeyboardButton::callback(label, URGENT)]);
+    }
 
     InlineKeyboardMarkup::new(keyboard)
 }
 
+#[allow(clippy::needless_pass_by_value)]
+pub fn is_keep_text(q: CallbackQuery) -> bool {
+    q.data.as_deref() == Some(KEEP_TEXT)
+}
+
+/// keeps the current text of the reminder being edited.
+pub async fn keep_text_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    (date_time, editing): (OffsetDateTime, Option<EditingReminder>),
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+    let Some(editing) = editing else {
+        expired_callback_msg(bot, chat.id, *id).await?;
+        bail!("not editing any reminder");
+    };
+
+    let text = job_text(date_time, &editing.message, editing.media.as_ref());
+    p.update(CallbackPage::ConfirmOneOffJob {
+        date_time,
+        msg_text: editing.message.clone(),
+        media: editing.media.clone(),
+        editing: Some(editing),
+        assignment: Assignment::default(),
+        lead_times: Vec::new(),
+        urgent: false,
+    })
+    .await?;
+
+    bot.edit_message_text(chat.id, *id, text)
+        .reply_markup(job_text_keyboard(false, false))
+        .await?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_lines)]
 pub async fn remind_text_callback(
     bot: Bot,
     q: CallbackQuery,
     p: CallbackState,
-    (date_time, msg_text): (OffsetDateTime, String),
+    (date_time, msg_text, media, editing, assignment, lead_times, urgent): (
+        OffsetDateTime,
+        String,
+        Option<ReminderMedia>,
+        Option<EditingReminder>,
+        Assignment,
+        Vec<i32>,
+        bool,
+    ),
     pool: PgPool,
     sched: JobScheduler,
 ) -> anyhow::Result<()> {
@@ -132,80 +241,99 @@ pub async fn remind_text_callback(
     };
     let msg = msg.clone();
 
-    let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
+    let tz = chat_timezone(&pool, msg.chat.id.0).await?;
+    let now = now_in(tz);
 
     time_check(&bot, msg.chat.id, date_time, now).await?;
 
     match data.as_ref() {
         JOB_TEXT_BACK => {
-            p.update(CallbackPage::ConfirmDateTime { date_time })
-                .await?;
+            p.update(CallbackPage::ConfirmDateTime {
+                date_time,
+                editing: editing.clone(),
+            })
+            .await?;
 
-            remind_text_page(bot, msg.chat.id, msg.id, date_time).await?;
+            remind_text_page(bot, msg.chat.id, msg.id, date_time, editing.as_ref()).await?;
         }
-        JOB_TEXT_CONFIRM => {
-            let time_delta = date_time - now;
-            let time_delta_secs = time_delta.whole_seconds();
-            let seconds = u64::from_le_bytes(time_delta_secs.to_le_bytes());
-
-            let bot_clone = bot.clone();
-            let text_clone = msg_text.clone();
-            let pool_clone = pool.clone();
-            let username_clone = username.clone();
-
-            let job = Job::new_one_shot_async(Duration::from_secs(seconds), move |_, _| {
-                let bot = bot_clone.clone();
-                let text = text_clone.clone();
-                let pool = pool_clone.clone();
-                let username = username_clone.clone();
-                Box::pin(async move {
-                    let text = format!(
-                        r"From: @{username}
-
-{text}"
-                    );
-                    if let Err(e) = bot.send_message(msg.chat.id, text).await {
-                        tracing::error!("error sending one-off-job {e:#?}");
-                    }
-                    if let Err(e) = sqlx::query!(
-                        r#"UPDATE jobs_one_off 
-                         SET 
-                         completed = $1
-                         WHERE 
-                         target = $2
-                         and due = $3
-                         and username = $4
-                         "#,
-                        true,
-                        msg.chat.id.0,
-                        date_time,
-                        username
-                    )
-                    .execute(&pool)
-                    .await
-                    {
-                        tracing::error!("error updating completed one_off_job in database: {e:#?}");
-                    }
-                })
-            })?;
-
-            let job_id = job.guid();
-            sqlx::query!(
-                r#"INSERT INTO jobs_one_off
-            (target, job_id, type, due, completed, message, username)
-            VALUES
-            ($1, $2, $3, $4, $5, $6, $7)"#,
-                msg.chat.id.0,
-                job_id,
-                "normal",
+        ASSIGN if editing.is_none() => {
+            assign_page(bot, msg.chat.id, msg.id, &assignment, &pool).await?;
+            p.update(CallbackPage::AssignReminder {
                 date_time,
-                false,
                 msg_text,
-                username
-            )
-            .execute(&pool)
+                media,
+                assignment,
+                lead_times,
+                urgent,
+            })
             .await?;
-            sched.add(job).await?;
+        }
+        HEADS_UP if editing.is_none() => {
+            lead_time_page(bot, msg.chat.id, msg.id, date_time, &lead_times).await?;
+            p.update(CallbackPage::ReminderLeadTimes {
+                date_time,
+                msg_text,
+                media,
+                assignment,
+                lead_times,
+                urgent,
+            })
+            .await?;
+        }
+        URGENT if editing.is_none() => {
+            let urgent = !urgent;
+            bot.edit_message_reply_markup(msg.chat.id, msg.id)
+                .reply_markup(job_text_keyboard(true, urgent))
+                .await?;
+            p.update(CallbackPage::ConfirmOneOffJob {
+                date_time,
+                msg_text,
+                media,
+                editing: None,
+                assignment,
+                lead_times,
+                urgent,
+            })
+            .await?;
+        }
+        JOB_TEXT_CONFIRM => {
+            if let Some(editing) = editing {
+                let text = match reschedule_reminder(
+                    &bot,
+                    &pool,
+                    &sched,
+                    msg.chat.id,
+                    editing.id,
+                    date_time,
+                    &msg_text,
+                    media.as_ref(),
+                )
+                .await
+                {
+                    Ok(()) => "updated 🐢 - your message will be sent at the new time.",
+                    Err(e) => {
+                        tracing::error!("{e:#?}");
+                        "This reminder is no longer pending 😅"
+                    }
+                };
+                p.reset().await?;
+                bot.edit_message_text(msg.chat.id, msg.id, text).await?;
+                return Ok(());
+            }
+
+            let reminder = NewReminder {
+                target: msg.chat.id.0,
+                username: username.clone(),
+                message: msg_text,
+                due: date_time,
+                media,
+                snoozed_from: None,
+                assignees: assignment.user_ids(),
+                deliver_dm: assignment.dm,
+                lead_times,
+                urgent,
+            };
+            add_one_off_reminder(&bot, &pool, &sched, reminder).await?;
 
             p.reset().await?;
 
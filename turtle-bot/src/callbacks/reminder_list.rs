@@ -0,0 +1,610 @@
+use anyhow::{bail, Context};
+use sqlx::PgPool;
+use teloxide::{
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+    },
+    Bot,
+};
+use time::{macros::format_description, OffsetDateTime};
+use tokio_cron_scheduler::JobScheduler;
+use uuid::Uuid;
+
+use crate::{
+    jobs::{cancel_warnings, one_off_reminder_job, schedule_warnings, CronJobType, ReminderStatus},
+    media::{preview_text, ReminderMedia},
+    timezone::{chat_timezone, now_in, to_local},
+};
+
+use super::{date_page, expired_callback_msg, CallbackPage, CallbackState, EditingReminder};
+
+/// number of reminders shown in each page
+const PAGE_SIZE: usize = 5;
+
+const ONE_OFF: &str = "one-off";
+const RECURRING: &str = "recurring";
+
+const PAGE: &str = "page";
+const INSPECT: &str = "inspect";
+const CANCEL: &str = "cancel";
+const EDIT: &str = "edit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReminderKind {
+    OneOff,
+    Recurring,
+}
+
+impl ReminderKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::OneOff => ONE_OFF,
+            Self::Recurring => RECURRING,
+        }
+    }
+}
+
+impl TryFrom<&str> for ReminderKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            ONE_OFF => Ok(Self::OneOff),
+            RECURRING => Ok(Self::Recurring),
+            unknown => Err(format!("{unknown} is not a supported reminder kind.")),
+        }
+    }
+}
+
+/// What each button in the reminder list does.
+///
+/// The callback data is in the format of `action:page`, `action:id` or `action:kind:id`.
+#[derive(Debug, PartialEq)]
+enum ReminderAction {
+    Page(usize),
+    Inspect(ReminderKind, i32),
+    Cancel(ReminderKind, i32),
+    /// only one-off reminders can be edited.
+    Edit(i32),
+}
+
+impl ReminderAction {
+    fn to_data(&self) -> String {
+        match self {
+            Self::Page(page) => format!("{PAGE}:{page}"),
+            Self::Inspect(kind, id) => format!("{INSPECT}:{}:{id}", kind.as_str()),
+            Self::Cancel(kind, id) => format!("{CANCEL}:{}:{id}", kind.as_str()),
+            Self::Edit(id) => format!("{EDIT}:{id}"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ReminderAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let invalid = || format!("{value} is not a supported reminder action.");
+        match parts.as_slice() {
+            [PAGE, page] => Ok(Self::Page(page.parse().map_err(|_| invalid())?)),
+            [EDIT, id] => Ok(Self::Edit(id.parse().map_err(|_| invalid())?)),
+            [action @ (INSPECT | CANCEL), kind, id] => {
+                let kind = ReminderKind::try_from(*kind)?;
+                let id = id.parse().map_err(|_| invalid())?;
+                if *action == INSPECT {
+                    Ok(Self::Inspect(kind, id))
+                } else {
+                    Ok(Self::Cancel(kind, id))
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+struct ReminderEntry {
+    kind: ReminderKind,
+    id: i32,
+    /// due datetime for one-off reminders, cron expression for recurring ones.
+    schedule: String,
+    message: String,
+    username: Option<String>,
+}
+
+impl ReminderEntry {
+    fn label(&self) -> String {
+        let icon = match self.kind {
+            ReminderKind::OneOff => "🕑",
+            ReminderKind::Recurring => "🔁",
+        };
+        let preview: String = self.message.chars().take(24).collect();
+        let ellipsis = if self.message.chars().count() > 24 {
+            "…"
+        } else {
+            ""
+        };
+        format!("{icon} {} - {preview}{ellipsis}", self.schedule)
+    }
+
+    fn details(&self) -> String {
+        let (kind, schedule) = match self.kind {
+            ReminderKind::OneOff => ("One-off", "Due"),
+            ReminderKind::Recurring => ("Recurring", "Cron"),
+        };
+        let from = self
+            .username
+            .as_ref()
+            .map(|x| format!("\nFrom: @{x}"))
+            .unwrap_or_default();
+        format!(
+            r"{kind} reminder 🐢
+
+{schedule}: {}{from}
+
+{}",
+            self.schedule, self.message
+        )
+    }
+}
+
+/// pending one-off reminders come first, followed by recurring reminders.
+async fn get_pending_reminders(
+    pool: &PgPool,
+    chat_id: ChatId,
+) -> anyhow::Result<Vec<ReminderEntry>> {
+    let due_format = format_description!("[day] [month repr:short] [year] [hour]:[minute]");
+    let tz = chat_timezone(pool, chat_id.0).await?;
+
+    let one_offs = sqlx::query!(
+        "SELECT id, due, message, username, media_type, file_id FROM jobs_one_off
+        WHERE target = $1 AND status = $2
+        AND due >= CURRENT_TIMESTAMP
+        ORDER BY due",
+        chat_id.0,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let recurrings = sqlx::query!(
+        "SELECT id, cron_str, message, username FROM jobs_cron
+        WHERE target = $1 AND type = $2 AND cancelled = false
+        ORDER BY id",
+        chat_id.0,
+        CronJobType::RecurringReminder.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut reminders = Vec::with_capacity(one_offs.len() + recurrings.len());
+    for x in one_offs {
+        reminders.push(ReminderEntry {
+            kind: ReminderKind::OneOff,
+            id: x.id,
+            schedule: to_local(tz, x.due).format(due_format)?,
+            message: preview_text(
+                ReminderMedia::from_columns(x.media_type.as_deref(), x.file_id).as_ref(),
+                &x.message,
+            ),
+            username: Some(x.username),
+        });
+    }
+    reminders.extend(recurrings.into_iter().map(|x| ReminderEntry {
+        kind: ReminderKind::Recurring,
+        id: x.id,
+        schedule: x.cron_str,
+        message: x.message,
+        username: x.username,
+    }));
+    Ok(reminders)
+}
+
+fn reminder_list_keyboard(reminders: &[ReminderEntry], page: usize) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = reminders
+        .iter()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|x| {
+            vec![
+                InlineKeyboardButton::callback(
+                    x.label(),
+                    ReminderAction::Inspect(x.kind, x.id).to_data(),
+                ),
+                InlineKeyboardButton::callback(
+                    "❌",
+                    ReminderAction::Cancel(x.kind, x.id).to_data(),
+                ),
+            ]
+        })
+        .collect();
+
+    let last_page = reminders.len().saturating_sub(1) / PAGE_SIZE;
+    if last_page > 0 {
+        let prev = if page > 0 {
+            InlineKeyboardButton::callback("<<", ReminderAction::Page(page - 1).to_data())
+        } else {
+            InlineKeyboardButton::callback(" ", " ")
+        };
+        let next = if page < last_page {
+            InlineKeyboardButton::callback(">>", ReminderAction::Page(page + 1).to_data())
+        } else {
+            InlineKeyboardButton::callback(" ", " ")
+        };
+        let title = InlineKeyboardButton::callback(format!("{}/{}", page + 1, last_page + 1), " ");
+        keyboard.push(vec![prev, title, next]);
+    }
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+fn reminder_list_text(notice: Option<&str>, is_empty: bool) -> String {
+    let text = if is_empty {
+        "There are no pending reminders in this chat 🐢"
+    } else {
+        "Pending reminders in this chat 🐢\n\nTap on a reminder to see it, or ❌ to cancel it."
+    };
+    match notice {
+        Some(notice) => format!("{notice}\n\n{text}"),
+        None => text.to_string(),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn new_reminder_list_page(
+    bot: Bot,
+    chat_id: ChatId,
+    pool: &PgPool,
+    callback: CallbackState,
+) -> anyhow::Result<()> {
+    let reminders = get_pending_reminders(pool, chat_id).await?;
+    let text = reminder_list_text(None, reminders.is_empty());
+
+    if reminders.is_empty() {
+        bot.send_message(chat_id, text).await?;
+        return Ok(());
+    }
+
+    let sent = bot
+        .send_message(chat_id, text)
+        .reply_markup(reminder_list_keyboard(&reminders, 0))
+        .await?;
+    callback
+        .start(sent.id, CallbackPage::ReminderList { page: 0 })
+        .await?;
+    Ok(())
+}
+
+pub async fn reminder_list_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    pool: &PgPool,
+    page: usize,
+    notice: Option<&str>,
+) -> anyhow::Result<usize> {
+    let reminders = get_pending_reminders(pool, chat_id).await?;
+    let page = page.min(reminders.len().saturating_sub(1) / PAGE_SIZE);
+    let text = reminder_list_text(notice, reminders.is_empty());
+
+    bot.edit_message_text(chat_id, msg_id, text)
+        .reply_markup(reminder_list_keyboard(&reminders, page))
+        .await?;
+    Ok(page)
+}
+
+async fn reminder_details_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    pool: &PgPool,
+    (kind, id): (ReminderKind, i32),
+    page: usize,
+) -> anyhow::Result<()> {
+    let reminders = get_pending_reminders(pool, chat_id).await?;
+    let Some(reminder) = reminders.iter().find(|x| x.kind == kind && x.id == id) else {
+        reminder_list_page(
+            bot,
+            chat_id,
+            msg_id,
+            pool,
+            page,
+            Some("That reminder is no longer pending."),
+        )
+        .await?;
+        return Ok(());
+    };
+
+    let mut buttons = vec![
+        InlineKeyboardButton::callback("Back", ReminderAction::Page(page).to_data()),
+        InlineKeyboardButton::callback(
+            "Cancel Reminder",
+            ReminderAction::Cancel(kind, id).to_data(),
+        ),
+    ];
+    if kind == ReminderKind::OneOff {
+        buttons.push(InlineKeyboardButton::callback(
+            "Edit",
+            ReminderAction::Edit(id).to_data(),
+        ));
+    }
+    let keyboard = InlineKeyboardMarkup::new(vec![buttons]);
+
+    bot.edit_message_text(chat_id, msg_id, reminder.details())
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Removes the reminder from the scheduler and marks its row as cancelled.
+///
+/// Only reminders belonging to `chat_id` can be cancelled, and the advance
+/// warnings of one-off reminders are removed along with them.
+#[tracing::instrument(skip(pool, sched))]
+pub async fn cancel_reminder(
+    pool: &PgPool,
+    sched: &JobScheduler,
+    chat_id: ChatId,
+    kind: ReminderKind,
+    id: i32,
+) -> anyhow::Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .context("failed to acquire a postgres connection from pool.")?;
+
+    let job_id: Option<Uuid> = match kind {
+        ReminderKind::OneOff => {
+            sqlx::query_scalar!(
+                "UPDATE jobs_one_off SET status = $1
+                WHERE id = $2 AND target = $3 AND status = $4
+                RETURNING job_id",
+                ReminderStatus::Cancelled.as_str(),
+                id,
+                chat_id.0,
+                ReminderStatus::Pending.as_str()
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+        ReminderKind::Recurring => {
+            sqlx::query_scalar!(
+                "UPDATE jobs_cron SET cancelled = true
+                WHERE id = $1 AND target = $2 AND type = $3 AND cancelled = false
+                RETURNING job_id",
+                id,
+                chat_id.0,
+                CronJobType::RecurringReminder.as_str()
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+    }
+    .context("reminder does not exist or is no longer pending")?;
+
+    if let Some(job_id) = job_id {
+        sched.remove(&job_id).await?;
+    } else {
+        tracing::warn!("reminder has no job id");
+    }
+    if kind == ReminderKind::OneOff {
+        cancel_warnings(&mut tx, sched, id).await?;
+    }
+
+    tx.commit()
+        .await
+        .context("failed to commit sql transaction to cancel reminder.")?;
+    Ok(())
+}
+
+/// Swaps the scheduled job of a pending one-off reminder and updates its row.
+///
+/// The row stays locked until the new job is in the scheduler, and the old job
+/// has been removed from it. Its advance warnings are moved along with it.
+#[tracing::instrument(skip(bot, pool, sched, message, media))]
+#[allow(clippy::too_many_arguments)]
+pub async fn reschedule_reminder(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    chat_id: ChatId,
+    id: i32,
+    due: OffsetDateTime,
+    message: &str,
+    media: Option<&ReminderMedia>,
+) -> anyhow::Result<()> {
+    let mut tx = pool
+        .begin()
+        .await
+        .context("failed to acquire a postgres connection from pool.")?;
+
+    let record = sqlx::query!(
+        "SELECT job_id, username FROM jobs_one_off
+        WHERE id = $1 AND target = $2 AND status = $3
+        FOR UPDATE",
+        id,
+        chat_id.0,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .context("reminder does not exist or is no longer pending")?;
+
+    let job = one_off_reminder_job(
+        bot.clone(),
+        pool.clone(),
+        id,
+        chat_id.0,
+        record.username,
+        message.to_string(),
+        due,
+    )?;
+    let job_id = job.guid();
+
+    sqlx::query!(
+        "UPDATE jobs_one_off
+        SET due = $1, message = $2, media_type = $3, file_id = $4, job_id = $5
+        WHERE id = $6",
+        due,
+        message,
+        media.map(|x| x.kind.as_str()),
+        media.map(|x| x.file_id.as_str()),
+        job_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sched.add(job).await?;
+    if let Some(old_job_id) = record.job_id {
+        if let Err(e) = sched.remove(&old_job_id).await {
+            sched.remove(&job_id).await?;
+            return Err(e.into());
+        }
+    }
+    cancel_warnings(&mut tx, sched, id).await?;
+
+    tx.commit()
+        .await
+        .context("failed to commit sql transaction to reschedule reminder.")?;
+
+    schedule_warnings(bot, pool, sched, Some(id)).await?;
+    Ok(())
+}
+
+/// Reopens the one-off reminder wizard with the reminder's values.
+async fn edit_reminder(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    pool: &PgPool,
+    p: CallbackState,
+    (id, page): (i32, usize),
+) -> anyhow::Result<()> {
+    let record = sqlx::query!(
+        "SELECT due, message, media_type, file_id FROM jobs_one_off
+        WHERE id = $1 AND target = $2 AND status = $3
+        AND due >= CURRENT_TIMESTAMP",
+        id,
+        chat_id.0,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(record) = record else {
+        let notice = Some("That reminder is no longer pending.");
+        reminder_list_page(bot, chat_id, msg_id, pool, page, notice).await?;
+        return Ok(());
+    };
+
+    let tz = chat_timezone(pool, chat_id.0).await?;
+    let now = now_in(tz);
+    let due = to_local(tz, record.due);
+    let first_day = if (due.year(), due.month()) == (now.year(), now.month()) {
+        now.day()
+    } else {
+        1
+    };
+
+    p.update(CallbackPage::RemindDate {
+        editing: Some(EditingReminder {
+            id,
+            due: record.due,
+            message: record.message,
+            media: ReminderMedia::from_columns(record.media_type.as_deref(), record.file_id),
+        }),
+    })
+    .await?;
+    date_page(
+        bot,
+        chat_id,
+        msg_id,
+        first_day,
+        due.month().into(),
+        due.year(),
+        tz,
+    )
+    .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn reminder_list_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    page: usize,
+    pool: PgPool,
+    sched: JobScheduler,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    if data.trim().is_empty() {
+        return Ok(());
+    }
+
+    let action = match ReminderAction::try_from(data.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            expired_callback_msg(bot, chat.id, *id).await?;
+            bail!("{e}");
+        }
+    };
+
+    match action {
+        ReminderAction::Page(page) => {
+            let page = reminder_list_page(bot, chat.id, *id, &pool, page, None).await?;
+            p.update(CallbackPage::ReminderList { page }).await?;
+        }
+        ReminderAction::Inspect(kind, reminder_id) => {
+            reminder_details_page(bot, chat.id, *id, &pool, (kind, reminder_id), page).await?;
+        }
+        ReminderAction::Cancel(kind, reminder_id) => {
+            let notice = match cancel_reminder(&pool, &sched, chat.id, kind, reminder_id).await {
+                Ok(()) => "Reminder cancelled ✅",
+                Err(e) => {
+                    tracing::error!("{e:#?}");
+                    "That reminder is no longer pending."
+                }
+            };
+            let page = reminder_list_page(bot, chat.id, *id, &pool, page, Some(notice)).await?;
+            p.update(CallbackPage::ReminderList { page }).await?;
+        }
+        ReminderAction::Edit(reminder_id) => {
+            edit_reminder(bot, chat.id, *id, &pool, p, (reminder_id, page)).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReminderAction, ReminderKind};
+
+    #[test]
+    fn reminder_action_data() {
+        let actions = [
+            ReminderAction::Page(3),
+            ReminderAction::Inspect(ReminderKind::OneOff, 12),
+            ReminderAction::Cancel(ReminderKind::Recurring, 7),
+            ReminderAction::Edit(12),
+        ];
+        for action in actions {
+            let data = action.to_data();
+            assert_eq!(ReminderAction::try_from(data.as_str()).unwrap(), action);
+        }
+        assert!(ReminderAction::try_from("cancel:weekly:7").is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! Buttons attached to delivered one-off reminders.
+//!
+//! These buttons stay usable whatever the wizard state of the chat is,
+//! hence they are handled before entering the [`CallbackPage`](super::CallbackPage) dialogue.
+
+use anyhow::bail;
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use teloxide::{
+    payloads::EditMessageCaptionSetters,
+    requests::Requester,
+    types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup, Message},
+    Bot,
+};
+use time::{
+    error::ComponentRange, macros::format_description, Duration, OffsetDateTime, PrimitiveDateTime,
+};
+use tokio_cron_scheduler::JobScheduler;
+
+use crate::{
+    jobs::{add_one_off_reminder, NewReminder, ReminderStatus},
+    media::ReminderMedia,
+    timezone::{chat_timezone, from_local, now_in, to_local},
+};
+
+use super::expired_callback_msg;
+
+const SNOOZE: &str = "snooze";
+const DONE: &str = "done";
+
+const TEN_MINUTES: &str = "10m";
+const ONE_HOUR: &str = "1h";
+const TOMORROW: &str = "tomorrow";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Snooze {
+    TenMinutes,
+    OneHour,
+    Tomorrow,
+}
+
+impl Snooze {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::TenMinutes => TEN_MINUTES,
+            Self::OneHour => ONE_HOUR,
+            Self::Tomorrow => TOMORROW,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::TenMinutes => "Snooze 10m",
+            Self::OneHour => "Snooze 1h",
+            Self::Tomorrow => "Tomorrow",
+        }
+    }
+
+    /// when the snoozed reminder is due, in the local time of `tz`.
+    fn due(self, now: OffsetDateTime, tz: Tz) -> Result<OffsetDateTime, ComponentRange> {
+        let now = now.replace_second(0)?.replace_nanosecond(0)?;
+        let due = match self {
+            Self::TenMinutes => to_local(tz, now + Duration::minutes(10)),
+            Self::OneHour => to_local(tz, now + Duration::hours(1)),
+            Self::Tomorrow => from_local(
+                tz,
+                PrimitiveDateTime::new(now.date() + Duration::days(1), now.time()),
+            ),
+        };
+        Ok(due)
+    }
+}
+
+impl TryFrom<&str> for Snooze {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            TEN_MINUTES => Ok(Self::TenMinutes),
+            ONE_HOUR => Ok(Self::OneHour),
+            TOMORROW => Ok(Self::Tomorrow),
+            unknown => Err(format!("{unknown} is not a supported snooze duration.")),
+        }
+    }
+}
+
+/// What each button under a delivered reminder does.
+///
+/// The callback data is in the format of `snooze:duration:id` or `done:id`,
+/// where `id` is the row of the delivered reminder in `jobs_one_off`.
+#[derive(Debug, PartialEq)]
+enum DeliveredAction {
+    Snooze(Snooze, i32),
+    Done(i32),
+}
+
+impl DeliveredAction {
+    fn to_data(&self) -> String {
+        match self {
+            Self::Snooze(snooze, id) => format!("{SNOOZE}:{}:{id}", snooze.as_str()),
+            Self::Done(id) => format!("{DONE}:{id}"),
+        }
+    }
+}
+
+impl TryFrom<&str> for DeliveredAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let invalid = || format!("{value} is not a supported delivered reminder action.");
+        match parts.as_slice() {
+            [SNOOZE, snooze, id] => Ok(Self::Snooze(
+                Snooze::try_from(*snooze)?,
+                id.parse().map_err(|_| invalid())?,
+            )),
+            [DONE, id] => Ok(Self::Done(id.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// keyboard attached to the delivered reminder of the `id` row.
+pub fn delivered_reminder_keyboard(id: i32) -> InlineKeyboardMarkup {
+    let snooze_row = [Snooze::TenMinutes, Snooze::OneHour, Snooze::Tomorrow]
+        .map(|x| {
+            InlineKeyboardButton::callback(x.label(), DeliveredAction::Snooze(x, id).to_data())
+        })
+        .to_vec();
+    let done_row = vec![InlineKeyboardButton::callback(
+        "Done",
+        DeliveredAction::Done(id).to_data(),
+    )];
+    InlineKeyboardMarkup::new(vec![snooze_row, done_row])
+}
+
+#[allow(clippy::needless_pass_by_value)]
+pub fn is_delivered_reminder(q: CallbackQuery) -> bool {
+    q.data
+        .as_deref()
+        .is_some_and(|x| DeliveredAction::try_from(x).is_ok())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delivered_reminder_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    pool: PgPool,
+    sched: JobScheduler,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(msg) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+    let Message { id, chat, .. } = msg;
+
+    let action = match DeliveredAction::try_from(data.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            expired_callback_msg(bot, chat.id, *id).await?;
+            bail!("{e}");
+        }
+    };
+    let by = q
+        .from
+        .username
+        .as_ref()
+        .map(|x| format!(" by @{x}"))
+        .unwrap_or_default();
+
+    let note = match action {
+        DeliveredAction::Snooze(snooze, reminder_id) => {
+            let record = sqlx::query!(
+                "SELECT message, username, deliver_dm, media_type, file_id, urgent FROM jobs_one_off
+                WHERE id = $1 AND target = $2",
+                reminder_id,
+                chat.id.0
+            )
+            .fetch_optional(&pool)
+            .await?;
+            let Some(record) = record else {
+                expired_callback_msg(bot, chat.id, *id).await?;
+                bail!("reminder {reminder_id} does not exist in this chat");
+            };
+
+            let assignees = sqlx::query_scalar!(
+                "SELECT user_id FROM jobs_one_off_assignees WHERE reminder_id = $1",
+                reminder_id
+            )
+            .fetch_all(&pool)
+            .await?;
+
+            let tz = chat_timezone(&pool, chat.id.0).await?;
+            let due = snooze.due(now_in(tz), tz)?;
+            let reminder = NewReminder {
+                target: chat.id.0,
+                username: record.username,
+                message: record.message,
+                due,
+                media: ReminderMedia::from_columns(record.media_type.as_deref(), record.file_id),
+                snoozed_from: Some(reminder_id),
+                assignees,
+                deliver_dm: record.deliver_dm,
+                lead_times: Vec::new(),
+                urgent: record.urgent,
+            };
+            add_one_off_reminder(&bot, &pool, &sched, reminder).await?;
+
+            let due = due.format(format_description!(
+                "[day] [month repr:short] [hour]:[minute]"
+            ))?;
+            format!("💤 Snoozed until {due}{by}")
+        }
+        DeliveredAction::Done(reminder_id) => {
+            let result = sqlx::query!(
+                "UPDATE jobs_one_off SET status = $1 WHERE id = $2 AND target = $3 AND status = $4",
+                ReminderStatus::Done.as_str(),
+                reminder_id,
+                chat.id.0,
+                ReminderStatus::Sent.as_str()
+            )
+            .execute(&pool)
+            .await?;
+            if result.rows_affected() == 0 {
+                expired_callback_msg(bot, chat.id, *id).await?;
+                bail!("reminder {reminder_id} is not a sent reminder of this chat");
+            }
+            format!("✅ Done{by}")
+        }
+    };
+
+    // reminders with media carry their text in the caption
+    if let Some(text) = msg.text() {
+        bot.edit_message_text(chat.id, *id, format!("{text}\n\n{note}"))
+            .await?;
+    } else {
+        let caption = msg.caption().unwrap_or_default();
+        bot.edit_message_caption(chat.id, *id)
+            .caption(format!("{caption}\n\n{note}"))
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+    use time::macros::datetime;
+
+    use super::{DeliveredAction, Snooze};
+
+    #[test]
+    fn delivered_action_data() {
+        let actions = [
+            DeliveredAction::Snooze(Snooze::TenMinutes, 12),
+            DeliveredAction::Snooze(Snooze::Tomorrow, 7),
+            DeliveredAction::Done(12),
+        ];
+        for action in actions {
+            let data = action.to_data();
+            assert_eq!(DeliveredAction::try_from(data.as_str()).unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn snooze_due() {
+        let tz = Tz::Europe__London;
+        let now = datetime!(2024-03-30 18:42:31 +0);
+        assert_eq!(
+            Snooze::TenMinutes.due(now, tz).unwrap(),
+            datetime!(2024-03-30 18:52 +0)
+        );
+        // the clocks go forward overnight, but the reminder stays at 18:42
+        assert_eq!(
+            Snooze::Tomorrow.due(now, tz).unwrap(),
+            datetime!(2024-03-31 18:42 +1)
+        );
+    }
+}
@@ -1,4 +1,7 @@
 use anyhow::bail;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use teloxide::{
     payloads::EditMessageTextSetters,
     requests::Requester,
@@ -8,13 +11,19 @@ use teloxide::{
     Bot,
 };
 use time::{
-    macros::{format_description, offset},
-    Date, OffsetDateTime,
+    error::ComponentRange, macros::format_description, Date, Duration, OffsetDateTime,
+    PrimitiveDateTime, Time,
 };
 
-use crate::callbacks::expired_callback_msg;
+use crate::{
+    callbacks::expired_callback_msg,
+    timezone::{chat_timezone, from_local, now_in, to_local},
+};
 
-use super::{date_page, remind_text_page, CallbackPage, CallbackState};
+use super::{
+    date_page, recurrence_page, recurring_text_page, remind_text_page, CallbackPage, CallbackState,
+    EditingReminder, Recurrence,
+};
 
 const BACK: &str = "Back";
 const NEXT: &str = "Next";
@@ -29,6 +38,21 @@ const HOUR_DOWN: &str = "HourDown";
 const TEN_MINUTE_DOWN: &str = "TenMinuteDown";
 const MINUTE_DOWN: &str = "MinuteDown";
 
+const FIVE_MINUTES_UP: &str = "FiveMinutesUp";
+const FIFTEEN_MINUTES_UP: &str = "FifteenMinutesUp";
+const FIVE_MINUTES_DOWN: &str = "FiveMinutesDown";
+const FIFTEEN_MINUTES_DOWN: &str = "FifteenMinutesDown";
+const STEP_MODE: &str = "StepMode";
+
+const IN_HALF_HOUR: &str = "in-30m";
+const IN_ONE_HOUR: &str = "in-1h";
+const THIS_EVENING: &str = "evening";
+const TOMORROW_MORNING: &str = "tomorrow-9";
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+/// the grid which the coarse steps keep the time on.
+const COARSE_GRID: i32 = 5;
+
 enum TimeSelect {
     TenHourUp,
     HourUp,
@@ -39,6 +63,13 @@ enum TimeSelect {
     HourDown,
     TenMinuteDown,
     MinuteDown,
+
+    FiveMinutesUp,
+    FifteenMinutesUp,
+    FiveMinutesDown,
+    FifteenMinutesDown,
+    /// switches between the digit arrows and the coarse steps.
+    StepMode,
 }
 
 impl TimeSelect {
@@ -53,6 +84,12 @@ impl TimeSelect {
             TimeSelect::HourDown => HOUR_DOWN,
             TimeSelect::TenMinuteDown => TEN_MINUTE_DOWN,
             TimeSelect::MinuteDown => MINUTE_DOWN,
+
+            TimeSelect::FiveMinutesUp => FIVE_MINUTES_UP,
+            TimeSelect::FifteenMinutesUp => FIFTEEN_MINUTES_UP,
+            TimeSelect::FiveMinutesDown => FIVE_MINUTES_DOWN,
+            TimeSelect::FifteenMinutesDown => FIFTEEN_MINUTES_DOWN,
+            TimeSelect::StepMode => STEP_MODE,
         }
     }
 }
@@ -72,16 +109,96 @@ impl TryFrom<String> for TimeSelect {
             MINUTE_UP => Ok(Self::MinuteUp),
             MINUTE_DOWN => Ok(Self::MinuteDown),
 
+            FIVE_MINUTES_UP => Ok(Self::FiveMinutesUp),
+            FIFTEEN_MINUTES_UP => Ok(Self::FifteenMinutesUp),
+            FIVE_MINUTES_DOWN => Ok(Self::FiveMinutesDown),
+            FIFTEEN_MINUTES_DOWN => Ok(Self::FifteenMinutesDown),
+            STEP_MODE => Ok(Self::StepMode),
+
             unknown => Err(format!("{unknown} is unsupported.")),
         }
     }
 }
-#[derive(Clone, Debug)]
+
+/// The buttons which pick a one-off reminder time in one tap.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TimePreset {
+    InHalfHour,
+    InOneHour,
+    ThisEvening,
+    TomorrowMorning,
+}
+
+impl TimePreset {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::InHalfHour => IN_HALF_HOUR,
+            Self::InOneHour => IN_ONE_HOUR,
+            Self::ThisEvening => THIS_EVENING,
+            Self::TomorrowMorning => TOMORROW_MORNING,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::InHalfHour => "In 30m",
+            Self::InOneHour => "In 1h",
+            Self::ThisEvening => "This evening 20:00",
+            Self::TomorrowMorning => "Tomorrow 09:00",
+        }
+    }
+
+    /// when the reminder is due, in the local time of `tz`.
+    ///
+    /// the evening is the next one when it is already past 20:00.
+    fn due(self, now: OffsetDateTime, tz: Tz) -> Result<OffsetDateTime, ComponentRange> {
+        let now = to_local(tz, now.replace_second(0)?.replace_nanosecond(0)?);
+        let at = |date: Date, hour: u8| -> Result<OffsetDateTime, ComponentRange> {
+            Ok(from_local(
+                tz,
+                PrimitiveDateTime::new(date, Time::from_hms(hour, 0, 0)?),
+            ))
+        };
+        let due = match self {
+            Self::InHalfHour => to_local(tz, now + Duration::minutes(30)),
+            Self::InOneHour => to_local(tz, now + Duration::hours(1)),
+            Self::ThisEvening => {
+                let evening = at(now.date(), 20)?;
+                if evening > now {
+                    evening
+                } else {
+                    at(now.date() + Duration::days(1), 20)?
+                }
+            }
+            Self::TomorrowMorning => at(now.date() + Duration::days(1), 9)?,
+        };
+        Ok(due)
+    }
+}
+
+impl TryFrom<&str> for TimePreset {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            IN_HALF_HOUR => Ok(Self::InHalfHour),
+            IN_ONE_HOUR => Ok(Self::InOneHour),
+            THIS_EVENING => Ok(Self::ThisEvening),
+            TOMORROW_MORNING => Ok(Self::TomorrowMorning),
+            unknown => Err(format!("{unknown} is not a supported time preset.")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RemindTime {
     pub tenth_hour: u8,
     pub hour: u8,
     pub tenth_minute: u8,
     pub minute: u8,
+    /// steps by 5 and 15 minutes, instead of by digit.
+    #[serde(default)]
+    pub coarse: bool,
 }
 
 impl Default for RemindTime {
@@ -91,12 +208,13 @@ impl Default for RemindTime {
             hour: 2,
             tenth_minute: 0,
             minute: 0,
+            coarse: false,
         }
     }
 }
 
 impl RemindTime {
-    fn new(hour: u8, minute: u8) -> Result<Self, String> {
+    pub fn new(hour: u8, minute: u8) -> Result<Self, String> {
         if hour > 23 {
             return Err(format!("invalid hour: {hour}"));
         }
@@ -115,6 +233,7 @@ impl RemindTime {
             hour,
             tenth_minute,
             minute,
+            coarse: false,
         })
     }
 
@@ -219,6 +338,54 @@ impl RemindTime {
             self.minute -= 1;
         }
     }
+
+    pub fn hour_value(&self) -> u8 {
+        self.tenth_hour * 10 + self.hour
+    }
+
+    pub fn minute_value(&self) -> u8 {
+        self.tenth_minute * 10 + self.minute
+    }
+
+    /// moves the time by `minutes` along the 5 minute grid, wrapping around midnight.
+    fn step(&mut self, minutes: i32) {
+        let current = i32::from(self.hour_value()) * 60 + i32::from(self.minute_value());
+        let snapped = if minutes > 0 {
+            current - current % COARSE_GRID
+        } else {
+            current + (COARSE_GRID - current % COARSE_GRID) % COARSE_GRID
+        };
+        let total = (snapped + minutes).rem_euclid(MINUTES_PER_DAY);
+        let hour = u8::try_from(total / 60).unwrap_or_default();
+        let minute = u8::try_from(total % 60).unwrap_or_default();
+        if let Ok(x) = Self::new(hour, minute) {
+            *self = Self {
+                coarse: self.coarse,
+                ..x
+            };
+        }
+    }
+
+    /// applies the arrow pressed in the time keyboard.
+    fn select(&mut self, data: String) -> Result<(), String> {
+        let time_select: TimeSelect = data.try_into()?;
+        match time_select {
+            TimeSelect::TenHourUp => self.tenth_hour_up(),
+            TimeSelect::HourUp => self.hour_up(),
+            TimeSelect::TenMinuteUp => self.tenth_minute_up(),
+            TimeSelect::MinuteUp => self.minute_up(),
+            TimeSelect::TenHourDown => self.tenth_hour_down(),
+            TimeSelect::HourDown => self.hour_down(),
+            TimeSelect::TenMinuteDown => self.tenth_minute_down(),
+            TimeSelect::MinuteDown => self.minute_down(),
+            TimeSelect::FiveMinutesUp => self.step(5),
+            TimeSelect::FifteenMinutesUp => self.step(15),
+            TimeSelect::FiveMinutesDown => self.step(-5),
+            TimeSelect::FifteenMinutesDown => self.step(-15),
+            TimeSelect::StepMode => self.coarse = !self.coarse,
+        }
+        Ok(())
+    }
 }
 
 pub async fn time_page(
@@ -238,16 +405,11 @@ year: {year}
 month: {month} 
 day: {day}
 
-Now, let's choose the time. 🐢
+Now, let's choose the time, or pick one of the shortcuts. 🐢
 The time is in 24 hours format."
     );
 
-    let time_pick = time_keyboard(
-        remind_time.tenth_hour,
-        remind_time.hour,
-        remind_time.tenth_minute,
-        remind_time.minute,
-    );
+    let time_pick = time_keyboard(&remind_time, true);
 
     bot.edit_message_text(chat_id, msg_id, text)
         .reply_markup(time_pick)
@@ -255,19 +417,51 @@ The time is in 24 hours format."
     Ok(())
 }
 
-#[tracing::instrument(skip_all)]
-fn time_keyboard(tenth_hour: u8, hour: u8, tenth_minute: u8, minute: u8) -> InlineKeyboardMarkup {
-    let up_arrow: &str = "↑";
+/// `presets` adds the shortcuts, which only make sense for one-off reminders.
+fn time_keyboard(remind_time: &RemindTime, presets: bool) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = if remind_time.coarse {
+        coarse_rows(remind_time)
+    } else {
+        digit_rows(remind_time)
+    };
 
-    tracing::debug!(?tenth_hour);
-    tracing::debug!(?hour);
-    tracing::debug!(?tenth_minute);
-    tracing::debug!(?minute);
+    let mode = if remind_time.coarse {
+        "🔢 Step by digit"
+    } else {
+        "⏩ Step by 5/15 min"
+    };
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        mode,
+        TimeSelect::StepMode.as_str(),
+    )]);
+
+    if presets {
+        keyboard.extend(
+            [
+                [TimePreset::InHalfHour, TimePreset::InOneHour],
+                [TimePreset::ThisEvening, TimePreset::TomorrowMorning],
+            ]
+            .map(|row| {
+                row.map(|x| InlineKeyboardButton::callback(x.label(), x.as_str()))
+                    .to_vec()
+            }),
+        );
+    }
+
+    keyboard.push(vec![
+        InlineKeyboardButton::callback(BACK, BACK),
+        InlineKeyboardButton::callback(NEXT, NEXT),
+    ]);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+fn digit_rows(remind_time: &RemindTime) -> Vec<Vec<InlineKeyboardButton>> {
+    let up_arrow: &str = "↑";
 
-    let tenth_hour = tenth_hour.to_string();
-    let hour = hour.to_string();
-    let tenth_minute = tenth_minute.to_string();
-    let minute = minute.to_string();
+    let tenth_hour = remind_time.tenth_hour.to_string();
+    let hour = remind_time.hour.to_string();
+    let tenth_minute = remind_time.tenth_minute.to_string();
+    let minute = remind_time.minute.to_string();
 
     let up_btn_row: Vec<InlineKeyboardButton> = vec![
         InlineKeyboardButton::callback(up_arrow, TimeSelect::TenHourUp.as_str()),
@@ -290,14 +484,29 @@ fn time_keyboard(tenth_hour: u8, hour: u8, tenth_minute: u8, minute: u8) -> Inli
         InlineKeyboardButton::callback(down_arrow, TimeSelect::MinuteDown.as_str()),
     ];
 
-    let last_row: Vec<InlineKeyboardButton> = vec![
-        InlineKeyboardButton::callback(BACK, BACK),
-        InlineKeyboardButton::callback(NEXT, NEXT),
+    vec![up_btn_row, time_row, down_btn_row]
+}
+
+fn coarse_rows(remind_time: &RemindTime) -> Vec<Vec<InlineKeyboardButton>> {
+    let time = format!(
+        "{:02}:{:02}",
+        remind_time.hour_value(),
+        remind_time.minute_value()
+    );
+
+    let up_btn_row: Vec<InlineKeyboardButton> = vec![
+        InlineKeyboardButton::callback("+1h", TimeSelect::HourUp.as_str()),
+        InlineKeyboardButton::callback("+15m", TimeSelect::FifteenMinutesUp.as_str()),
+        InlineKeyboardButton::callback("+5m", TimeSelect::FiveMinutesUp.as_str()),
+    ];
+    let time_row: Vec<InlineKeyboardButton> = vec![InlineKeyboardButton::callback(time, " ")];
+    let down_btn_row: Vec<InlineKeyboardButton> = vec![
+        InlineKeyboardButton::callback("-1h", TimeSelect::HourDown.as_str()),
+        InlineKeyboardButton::callback("-15m", TimeSelect::FifteenMinutesDown.as_str()),
+        InlineKeyboardButton::callback("-5m", TimeSelect::FiveMinutesDown.as_str()),
     ];
-    let keyboard: Vec<Vec<InlineKeyboardButton>> =
-        vec![up_btn_row, time_row, down_btn_row, last_row];
 
-    InlineKeyboardMarkup::new(keyboard)
+    vec![up_btn_row, time_row, down_btn_row]
 }
 
 #[tracing::instrument(skip_all)]
@@ -305,7 +514,8 @@ pub async fn time_callback(
     bot: Bot,
     q: CallbackQuery,
     p: CallbackState,
-    (naive_date, remind_time): (Date, RemindTime),
+    (naive_date, remind_time, editing): (Date, RemindTime, Option<EditingReminder>),
+    pool: PgPool,
 ) -> anyhow::Result<()> {
     bot.answer_callback_query(q.id.clone()).await?;
     let Some(ref data) = q.data else {
@@ -325,11 +535,26 @@ pub async fn time_callback(
         return Ok(());
     }
 
-    let now = OffsetDateTime::now_utc().to_offset(offset!(+8));
+    let tz = chat_timezone(&pool, chat.id.0).await?;
+    let now = now_in(tz);
+
+    if let Ok(preset) = TimePreset::try_from(data.as_str()) {
+        let chosen_datetime = preset.due(now, tz)?;
+        time_check(&bot, chat.id, chosen_datetime, now).await?;
+
+        p.update(CallbackPage::ConfirmDateTime {
+            date_time: chosen_datetime,
+            editing: editing.clone(),
+        })
+        .await?;
+
+        remind_text_page(bot, chat.id, *msg_id, chosen_datetime, editing.as_ref()).await?;
+        return Ok(());
+    }
 
     match data.as_ref() {
         BACK => {
-            p.update(CallbackPage::RemindDate).await?;
+            p.update(CallbackPage::RemindDate { editing }).await?;
             date_page(
                 bot,
                 chat.id,
@@ -337,45 +562,19 @@ pub async fn time_callback(
                 now.day(),
                 now.month().into(),
                 now.year(),
+                tz,
             )
             .await?;
         }
         NEXT => {
             tracing::debug!("Next is pressed");
-            let hour = remind_time.tenth_hour * 10 + remind_time.hour;
-            let minute = remind_time.tenth_minute * 10 + remind_time.minute;
+            let hour = remind_time.hour_value();
+            let minute = remind_time.minute_value();
 
             let naive_datetime = naive_date.with_hms(hour, minute, 0)?;
             tracing::debug!("{naive_datetime:#?}");
 
-            let chosen_datetime = naive_datetime
-                .assume_utc()
-                .to_offset(offset!(+8))
-                .replace_year(naive_datetime.year())
-                .map_err(|e| {
-                    tracing::debug!("year");
-                    e
-                })?
-                .replace_month(naive_datetime.month())
-                .map_err(|e| {
-                    tracing::debug!("month");
-                    e
-                })?
-                .replace_day(naive_datetime.day())
-                .map_err(|e| {
-                    tracing::debug!("day");
-                    e
-                })?
-                .replace_hour(hour)
-                .map_err(|e| {
-                    tracing::debug!("hour");
-                    e
-                })?
-                .replace_minute(minute)
-                .map_err(|e| {
-                    tracing::debug!("minute");
-                    e
-                })?;
+            let chosen_datetime = from_local(tz, naive_datetime);
 
             tracing::debug!("{chosen_datetime:#?}");
 
@@ -384,35 +583,24 @@ pub async fn time_callback(
 
             p.update(CallbackPage::ConfirmDateTime {
                 date_time: chosen_datetime,
+                editing: editing.clone(),
             })
             .await?;
 
-            remind_text_page(bot, chat.id, *msg_id, chosen_datetime).await?;
+            remind_text_page(bot, chat.id, *msg_id, chosen_datetime, editing.as_ref()).await?;
         }
         _ => {
             let mut remind_time = remind_time;
-            let time_select: TimeSelect = match data.clone().try_into() {
-                Ok(x) => x,
-                Err(e) => {
-                    tracing::error!(e);
-                    expired_callback_msg(bot, chat.id, *msg_id).await?;
-                    bail!("can't parse data into TimeSelect");
-                }
-            };
-            match time_select {
-                TimeSelect::TenHourUp => remind_time.tenth_hour_up(),
-                TimeSelect::HourUp => remind_time.hour_up(),
-                TimeSelect::TenMinuteUp => remind_time.tenth_minute_up(),
-                TimeSelect::MinuteUp => remind_time.minute_up(),
-                TimeSelect::TenHourDown => remind_time.tenth_hour_down(),
-                TimeSelect::HourDown => remind_time.hour_down(),
-                TimeSelect::TenMinuteDown => remind_time.tenth_minute_down(),
-                TimeSelect::MinuteDown => remind_time.minute_down(),
-            };
+            if let Err(e) = remind_time.select(data.clone()) {
+                tracing::error!(e);
+                expired_callback_msg(bot, chat.id, *msg_id).await?;
+                bail!("can't parse data into TimeSelect");
+            }
 
             p.update(CallbackPage::RemindDateTime {
                 date: naive_date,
                 time: remind_time.clone(),
+                editing,
             })
             .await?;
 
@@ -422,11 +610,95 @@ pub async fn time_callback(
     Ok(())
 }
 
+pub async fn recurring_time_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    recurrence: &Recurrence,
+    remind_time: RemindTime,
+) -> anyhow::Result<()> {
+    let text = format!(
+        r"You have chosen: {}
+
+Now, let's choose the time. 🐢
+The time is in 24 hours format.",
+        recurrence.describe()
+    );
+
+    let time_pick = time_keyboard(&remind_time, false);
+
+    bot.edit_message_text(chat_id, msg_id, text)
+        .reply_markup(time_pick)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn recurring_time_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    (recurrence, remind_time): (Recurrence, RemindTime),
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty spaces.");
+        bail!("no callback query data")
+    };
+    let Some(Message {
+        chat, id: msg_id, ..
+    }) = q.regular_message()
+    else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    if data.trim().is_empty() {
+        return Ok(());
+    }
+
+    match data.as_ref() {
+        BACK => {
+            p.update(CallbackPage::Recurrence).await?;
+            recurrence_page(bot, chat.id, *msg_id).await?;
+        }
+        NEXT => {
+            let schedule =
+                recurrence.schedule(remind_time.hour_value(), remind_time.minute_value());
+            tracing::debug!("{schedule:#?}");
+
+            p.update(CallbackPage::ConfirmRecurrence {
+                schedule: schedule.clone(),
+            })
+            .await?;
+
+            recurring_text_page(bot, chat.id, *msg_id, &schedule).await?;
+        }
+        _ => {
+            let mut remind_time = remind_time;
+            if let Err(e) = remind_time.select(data.clone()) {
+                tracing::error!(e);
+                expired_callback_msg(bot, chat.id, *msg_id).await?;
+                bail!("can't parse data into TimeSelect");
+            }
+
+            p.update(CallbackPage::RecurringTime {
+                recurrence: recurrence.clone(),
+                time: remind_time.clone(),
+            })
+            .await?;
+
+            recurring_time_page(bot, chat.id, *msg_id, &recurrence, remind_time).await?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn change_time_callback(
     bot: Bot,
     q: CallbackQuery,
     p: CallbackState,
-    date_time: OffsetDateTime,
+    (date_time, editing): (OffsetDateTime, Option<EditingReminder>),
 ) -> anyhow::Result<()> {
     bot.answer_callback_query(q.id.clone()).await?;
 
@@ -449,6 +721,7 @@ pub async fn change_time_callback(
     p.update(CallbackPage::RemindDateTime {
         date: naive_date,
         time: remind_time.clone(),
+        editing,
     })
     .await?;
 
@@ -478,3 +751,65 @@ The current time is {current_time}."
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+    use time::macros::datetime;
+
+    use super::{RemindTime, TimePreset};
+
+    #[test]
+    fn preset_due() {
+        let tz = Tz::Asia__Singapore;
+        let now = datetime!(2024-07-12 19:48:31 +8);
+        assert_eq!(
+            TimePreset::InHalfHour.due(now, tz).unwrap(),
+            datetime!(2024-07-12 20:18 +8)
+        );
+        assert_eq!(
+            TimePreset::ThisEvening.due(now, tz).unwrap(),
+            datetime!(2024-07-12 20:00 +8)
+        );
+        assert_eq!(
+            TimePreset::TomorrowMorning.due(now, tz).unwrap(),
+            datetime!(2024-07-13 09:00 +8)
+        );
+
+        // past 20:00, so the evening is tomorrow's
+        let late = datetime!(2024-07-12 21:05 +8);
+        assert_eq!(
+            TimePreset::ThisEvening.due(late, tz).unwrap(),
+            datetime!(2024-07-13 20:00 +8)
+        );
+        // the preset follows the date in the chat, not in UTC
+        let utc = datetime!(2024-07-12 21:05 UTC);
+        assert_eq!(
+            TimePreset::ThisEvening.due(utc, tz).unwrap(),
+            datetime!(2024-07-13 20:00 +8)
+        );
+        assert_eq!(
+            TimePreset::InOneHour.due(utc, tz).unwrap(),
+            datetime!(2024-07-13 06:05 +8)
+        );
+    }
+
+    #[test]
+    fn coarse_steps() {
+        let mut time = RemindTime::new(12, 3).unwrap();
+        time.step(5);
+        assert_eq!((time.hour_value(), time.minute_value()), (12, 5));
+        time.step(15);
+        assert_eq!((time.hour_value(), time.minute_value()), (12, 20));
+
+        let mut time = RemindTime::new(12, 3).unwrap();
+        time.step(-5);
+        assert_eq!((time.hour_value(), time.minute_value()), (12, 0));
+
+        let mut time = RemindTime::new(23, 50).unwrap();
+        time.step(15);
+        assert_eq!((time.hour_value(), time.minute_value()), (0, 5));
+        time.step(-15);
+        assert_eq!((time.hour_value(), time.minute_value()), (23, 50));
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::bail;
+use chrono_tz::{Tz, TZ_VARIANTS};
+use gaia::stickers::Stickers;
+use sqlx::PgPool;
+use teloxide::{
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    requests::Requester,
+    types::{
+        CallbackQuery, ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId,
+    },
+    Bot,
+};
+use time::macros::format_description;
+use tokio_cron_scheduler::JobScheduler;
+
+use crate::{
+    jobs::reschedule_cron_jobs,
+    timezone::{chat_timezone, now_in, set_chat_timezone},
+};
+
+use super::{expired_callback_msg, CallbackPage, CallbackState};
+
+/// only canonical IANA zones under these regions are listed in the picker.
+const REGIONS: [&str; 9] = [
+    "Africa",
+    "America",
+    "Antarctica",
+    "Asia",
+    "Atlantic",
+    "Australia",
+    "Europe",
+    "Indian",
+    "Pacific",
+];
+
+/// number of zones shown in each page
+const PAGE_SIZE: usize = 24;
+const ZONES_PER_ROW: usize = 3;
+
+const REGION_LIST: &str = "regions";
+const REGION: &str = "region";
+const ZONE: &str = "zone";
+
+/// What each button in the time zone picker does.
+///
+/// The callback data is in the format of `regions`, `region:name:page` or `zone:name`.
+#[derive(Debug, PartialEq)]
+enum TimezoneAction {
+    Regions,
+    Region(String, usize),
+    Zone(Tz),
+}
+
+impl TimezoneAction {
+    fn to_data(&self) -> String {
+        match self {
+            Self::Regions => REGION_LIST.to_string(),
+            Self::Region(region, page) => format!("{REGION}:{region}:{page}"),
+            Self::Zone(tz) => format!("{ZONE}:{}", tz.name()),
+        }
+    }
+}
+
+impl TryFrom<&str> for TimezoneAction {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.splitn(3, ':').collect();
+        let invalid = || format!("{value} is not a supported timezone action.");
+        match parts.as_slice() {
+            [REGION_LIST] => Ok(Self::Regions),
+            [REGION, region, page] if REGIONS.contains(region) => Ok(Self::Region(
+                (*region).to_string(),
+                page.parse().map_err(|_| invalid())?,
+            )),
+            [ZONE, name] => Ok(Self::Zone(name.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn zones_in_region(region: &str) -> Vec<Tz> {
+    let prefix = format!("{region}/");
+    TZ_VARIANTS
+        .iter()
+        .filter(|x| x.name().starts_with(&prefix))
+        .copied()
+        .collect()
+}
+
+fn region_keyboard() -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = REGIONS
+        .chunks(ZONES_PER_ROW)
+        .map(|row| {
+            row.iter()
+                .map(|x| {
+                    InlineKeyboardButton::callback(
+                        *x,
+                        TimezoneAction::Region((*x).to_string(), 0).to_data(),
+                    )
+                })
+                .collect()
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "UTC",
+        TimezoneAction::Zone(Tz::UTC).to_data(),
+    )]);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+fn zone_keyboard(region: &str, page: usize) -> InlineKeyboardMarkup {
+    let zones = zones_in_region(region);
+    let prefix = format!("{region}/");
+
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = zones
+        .iter()
+        .skip(page * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .collect::<Vec<&Tz>>()
+        .chunks(ZONES_PER_ROW)
+        .map(|row| {
+            row.iter()
+                .map(|x| {
+                    let label = x.name().trim_start_matches(&prefix).replace('_', " ");
+                    InlineKeyboardButton::callback(label, TimezoneAction::Zone(**x).to_data())
+                })
+                .collect()
+        })
+        .collect();
+
+    let last_page = zones.len().saturating_sub(1) / PAGE_SIZE;
+    if last_page > 0 {
+        let prev = if page > 0 {
+            InlineKeyboardButton::callback(
+                "<<",
+                TimezoneAction::Region(region.to_string(), page - 1).to_data(),
+            )
+        } else {
+            InlineKeyboardButton::callback(" ", " ")
+        };
+        let next = if page < last_page {
+            InlineKeyboardButton::callback(
+                ">>",
+                TimezoneAction::Region(region.to_string(), page + 1).to_data(),
+            )
+        } else {
+            InlineKeyboardButton::callback(" ", " ")
+        };
+        let title = InlineKeyboardButton::callback(format!("{}/{}", page + 1, last_page + 1), " ");
+        keyboard.push(vec![prev, title, next]);
+    }
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        "Back",
+        TimezoneAction::Regions.to_data(),
+    )]);
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+fn timezone_text(tz: Tz) -> anyhow::Result<String> {
+    let now = now_in(tz).format(format_description!("[hour]:[minute]"))?;
+    Ok(format!(
+        r"This chat is using {tz} time (now {now}). 🐢
+
+Pick the region of your time zone."
+    ))
+}
+
+/// Sets the time zone straight away when it is given, otherwise opens the picker.
+pub async fn timezone_command(
+    bot: Bot,
+    chat_id: ChatId,
+    args: &str,
+    callback: CallbackState,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    stickers: &Stickers,
+) -> anyhow::Result<()> {
+    let args = args.trim();
+    if !args.is_empty() {
+        if let Ok(tz) = args.parse::<Tz>() {
+            let text = change_timezone(&bot, chat_id, tz, pool, sched, stickers).await?;
+            bot.send_message(chat_id, text).await?;
+            return Ok(());
+        }
+        bot.send_message(chat_id, format!("I don't know the time zone {args} 😅"))
+            .await?;
+    }
+
+    let tz = chat_timezone(pool, chat_id.0).await?;
+    let sent = bot
+        .send_message(chat_id, timezone_text(tz)?)
+        .reply_markup(region_keyboard())
+        .await?;
+    callback.start(sent.id, CallbackPage::Timezone).await?;
+    Ok(())
+}
+
+async fn timezone_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    pool: &PgPool,
+) -> anyhow::Result<()> {
+    let tz = chat_timezone(pool, chat_id.0).await?;
+    bot.edit_message_text(chat_id, msg_id, timezone_text(tz)?)
+        .reply_markup(region_keyboard())
+        .await?;
+    Ok(())
+}
+
+async fn zone_page(
+    bot: Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    region: &str,
+    page: usize,
+) -> anyhow::Result<()> {
+    bot.edit_message_text(
+        chat_id,
+        msg_id,
+        format!("Pick your time zone in {region} 🐢"),
+    )
+    .reply_markup(zone_keyboard(region, page))
+    .await?;
+    Ok(())
+}
+
+/// Saves the chat's time zone and moves its greetings and recurring reminders
+/// over to it.
+async fn change_timezone(
+    bot: &Bot,
+    chat_id: ChatId,
+    tz: Tz,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    stickers: &Stickers,
+) -> anyhow::Result<String> {
+    if !set_chat_timezone(pool, chat_id.0, tz).await? {
+        return Ok("I don't know this chat yet 😅 say /start first.".to_string());
+    }
+    reschedule_cron_jobs(bot, stickers, pool, sched, chat_id.0, tz).await?;
+
+    let now = now_in(tz).format(format_description!("[hour]:[minute]"))?;
+    Ok(format!("This chat is now using {tz} time (now {now}). 🐢"))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn timezone_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    p: CallbackState,
+    pool: PgPool,
+    sched: JobScheduler,
+    stickers: Stickers,
+) -> anyhow::Result<()> {
+    bot.answer_callback_query(q.id.clone()).await?;
+
+    let Some(ref data) = q.data else {
+        tracing::error!("query data is None. should contain string or empty string.");
+        bail!("no query callback data")
+    };
+    let Some(Message { id, chat, .. }) = q.regular_message() else {
+        tracing::error!("no message data from telegram");
+        bail!("no telegram message data")
+    };
+
+    if data.trim().is_empty() {
+        return Ok(());
+    }
+
+    let action = match TimezoneAction::try_from(data.as_str()) {
+        Ok(x) => x,
+        Err(e) => {
+            expired_callback_msg(bot, chat.id, *id).await?;
+            bail!("{e}");
+        }
+    };
+
+    match action {
+        TimezoneAction::Regions => timezone_page(bot, chat.id, *id, &pool).await?,
+        TimezoneAction::Region(region, page) => {
+            zone_page(bot, chat.id, *id, &region, page).await?;
+        }
+        TimezoneAction::Zone(tz) => {
+            let text = change_timezone(&bot, chat.id, tz, &pool, &sched, &stickers).await?;
+            p.reset().await?;
+            bot.edit_message_text(chat.id, *id, text).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+
+    use super::{zones_in_region, TimezoneAction, REGIONS};
+
+    #[test]
+    fn timezone_action_data() {
+        let actions = [
+            TimezoneAction::Regions,
+            TimezoneAction::Region("America".to_string(), 2),
+            TimezoneAction::Zone(Tz::America__Argentina__Buenos_Aires),
+        ];
+        for action in actions {
+            let data = action.to_data();
+            assert_eq!(TimezoneAction::try_from(data.as_str()).unwrap(), action);
+        }
+    }
+
+    #[test]
+    fn zone_data_fits_in_button() {
+        for region in REGIONS {
+            for tz in zones_in_region(region) {
+                assert!(TimezoneAction::Zone(tz).to_data().len() <= 64);
+            }
+        }
+    }
+}
@@ -1,13 +1,4 @@
-use async_openai::{
-    config::OpenAIConfig,
-    error::OpenAIError,
-    types::{
-        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs, Role,
-    },
-    Client,
-};
+use gaia::llm::LlmSettings;
 use sqlx::{PgPool, Postgres, Transaction};
 use teloxide::{
     payloads::SendMessageSetters,
@@ -17,25 +8,16 @@ use teloxide::{
 };
 use time::OffsetDateTime;
 
-use crate::bot::BOT_NAME;
-
-/// number of tokens from chatgpt's response
-const MAX_TOKENS: u16 = 512;
-/// chatgpt model used for query
-const MODEL: &str = "gpt-3.5-turbo";
-// number of past chat records to retrieve
-const PAST_LOG_COUNT: i64 = 20;
+use crate::{
+    llm::{ChatMessage, ChatRole, Llm, LlmError},
+    llm_config::{chat_llm_config, LlmConfig},
+    persona::system_prompt,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ChatError {
     #[error(transparent)]
-    OpenAIError(#[from] OpenAIError),
-
-    #[error("no chat completion choices available.")]
-    NoChatCompletion,
-
-    #[error("bot did not respond")]
-    NoContent,
+    LlmError(#[from] LlmError),
 
     #[error("chat was prompted by bot.")]
     IsBot,
@@ -53,13 +35,14 @@ pub enum ChatError {
 #[tracing::instrument(skip_all)]
 pub async fn user_chat(
     bot: Bot,
-    client: Client<OpenAIConfig>,
+    llm: Llm,
     msg: Message,
     pool: PgPool,
+    settings: LlmSettings,
 ) -> anyhow::Result<()> {
     if let Some(chat_msg) = msg.text() {
         tracing::debug!("some1 is chatting with bot");
-        bot_chat(bot, client, &msg, chat_msg, pool).await?;
+        bot_chat(bot, llm, &msg, chat_msg, pool, &settings).await?;
     }
     Ok(())
 }
@@ -68,12 +51,13 @@ pub async fn user_chat(
 #[allow(deprecated)]
 pub async fn bot_chat(
     bot: Bot,
-    client: Client<OpenAIConfig>,
+    llm: Llm,
     msg: &Message,
     chat_msg: impl Into<String>,
     pool: PgPool,
+    settings: &LlmSettings,
 ) -> Result<Message, ChatError> {
-    let chat_response = match chatgpt_chat(client, msg, chat_msg.into(), pool).await {
+    let chat_response = match llm_chat(llm, msg, chat_msg.into(), pool, settings).await {
         Ok(response) => {
             bot.send_message(msg.chat.id, response)
                 .parse_mode(ParseMode::Markdown)
@@ -93,11 +77,12 @@ pub async fn bot_chat(
 }
 
 #[tracing::instrument(skip_all)]
-pub async fn chatgpt_chat(
-    client: Client<OpenAIConfig>,
+pub async fn llm_chat(
+    llm: Llm,
     msg: &Message,
     chat_msg: String,
     pool: PgPool,
+    settings: &LlmSettings,
 ) -> Result<String, ChatError> {
     if chat_msg.is_empty() {
         return Err(ChatError::EmptyMessageFromUser);
@@ -108,6 +93,8 @@ pub async fn chatgpt_chat(
         }
     };
 
+    let system_prompt = system_prompt(&pool, msg.chat.id.0).await?;
+    let config = chat_llm_config(&pool, settings, msg.chat.id.0).await?;
     let mut tx = pool.begin().await?;
 
     let username = match &msg.from {
@@ -117,58 +104,32 @@ pub async fn chatgpt_chat(
         },
         None => None,
     };
-    let mut past_logs = get_logs(&mut tx, msg.chat.id.0).await?;
+    let mut past_logs = get_logs(&mut tx, msg.chat.id.0, &config).await?;
 
-    save_chat_logs(&mut tx, msg.chat.id.0, Role::User, &chat_msg, username).await?;
+    save_chat_logs(&mut tx, msg.chat.id.0, ChatRole::User, &chat_msg, username).await?;
 
-    let chat_req = match username {
-        Some(x) => ChatCompletionRequestUserMessageArgs::default()
-            .content(chat_msg)
-            .name(x)
-            .build()?
-            .into(),
-
-        None => ChatCompletionRequestUserMessageArgs::default()
-            .content(chat_msg)
-            .build()?
-            .into(),
+    let chat_req = ChatMessage {
+        role: ChatRole::User,
+        name: username.cloned(),
+        content: chat_msg,
     };
 
-    let sys_msg = ChatCompletionRequestSystemMessageArgs::default()
-        .content(format!(
-            "You are a cute and bubbly yet wise and ancient male turtle and your name is {}.",
-            BOT_NAME.get().unwrap()
-        ))
-        .build()?
-        .into();
+    let sys_msg = ChatMessage {
+        role: ChatRole::System,
+        name: None,
+        content: system_prompt,
+    };
 
     let mut chat_cmp_msg = vec![sys_msg];
     chat_cmp_msg.append(&mut past_logs);
     chat_cmp_msg.push(chat_req);
-    tracing::debug!("chat_cmp_msg is {chat_cmp_msg:#?}");
-
-    let request = CreateChatCompletionRequestArgs::default()
-        .max_tokens(MAX_TOKENS)
-        .model(MODEL)
-        .messages(chat_cmp_msg)
-        .build()?;
-
-    let response = client.chat().create(request).await?;
 
-    let chat_response = response
-        .choices
-        .first()
-        .ok_or(ChatError::NoChatCompletion)?
-        .message
-        .content
-        .as_ref()
-        .ok_or(ChatError::NoContent)?
-        .to_owned();
+    let chat_response = llm.complete(chat_cmp_msg, &config.options).await?;
 
     save_chat_logs(
         &mut tx,
         msg.chat.id.0,
-        Role::Assistant,
+        ChatRole::Assistant,
         &chat_response,
         None,
     )
@@ -191,44 +152,39 @@ struct PastMsg {
 ///
 /// If it can't parse any role, it won't be able to get previous chat messages.
 /// Function will just return an empty vector.
-/// The silver lining is that less tokens will be sent to OpenAI,
+/// The silver lining is that less tokens will be sent to the model,
 /// resulting in lower costs.
 #[tracing::instrument(skip_all)]
 async fn get_logs(
     tx: &mut Transaction<'_, Postgres>,
     msg_id: i64,
-) -> Result<Vec<ChatCompletionRequestMessage>, ChatError> {
+    config: &LlmConfig,
+) -> Result<Vec<ChatMessage>, ChatError> {
     let past_msges: Vec<PastMsg> = sqlx::query_as!(
         PastMsg,
         r#"
         SELECT name, role, content FROM chatlogs
         WHERE message_id = $1
-        AND datetime>= CURRENT_TIMESTAMP - INTERVAL '1 hour'
+        AND datetime >= $2
         ORDER BY datetime DESC
-        LIMIT $2
+        LIMIT $3
         "#,
         msg_id,
-        PAST_LOG_COUNT
+        OffsetDateTime::now_utc() - config.history_age,
+        config.history_count
     )
     .fetch_all(&mut **tx)
     .await?;
-    let mut past_req_msges: Vec<ChatCompletionRequestMessage> = past_msges
+    let mut past_req_msges: Vec<ChatMessage> = past_msges
         .into_iter()
-        .map(|x| match x.role.to_lowercase().as_str().trim() {
-            "user" => Ok(ChatCompletionRequestMessage::User(
-                ChatCompletionRequestUserMessageArgs::default()
-                    .name(x.name.unwrap_or_default())
-                    .content(x.content)
-                    .build()?,
-            )),
-            "assistant" => Ok(ChatCompletionRequestMessage::Assistant(
-                ChatCompletionRequestAssistantMessageArgs::default()
-                    .content(x.content)
-                    .build()?,
-            )),
-            _ => Err(OpenAIError::InvalidArgument("invalid role".to_string())),
+        .filter_map(|x| match ChatRole::try_from(x.role.as_str()) {
+            Ok(role @ (ChatRole::User | ChatRole::Assistant)) => Some(ChatMessage {
+                role,
+                name: x.name,
+                content: x.content,
+            }),
+            _ => None,
         })
-        .filter_map(std::result::Result::ok)
         .collect();
     past_req_msges.reverse();
     tracing::debug!("{past_req_msges:#?}");
@@ -239,11 +195,11 @@ async fn get_logs(
 async fn save_chat_logs(
     tx: &mut Transaction<'_, Postgres>,
     msg_id: i64,
-    role: Role,
+    role: ChatRole,
     content: &String,
     username: Option<&String>,
 ) -> Result<(), ChatError> {
-    let role_str = role.to_string();
+    let role_str = role.as_str();
     let now = OffsetDateTime::now_utc();
     sqlx::query!(
         r#"
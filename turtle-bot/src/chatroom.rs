@@ -2,10 +2,10 @@
 
 use anyhow::Context;
 use sqlx::{PgPool, Postgres, Transaction};
-use teloxide::types::Message;
+use teloxide::{requests::Requester, types::Message, Bot, RequestError};
 use time::OffsetDateTime;
 
-use crate::handlers::is_group_chat;
+use crate::handlers::{is_group_chat, is_not_group_chat};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ChatRoomError {
@@ -209,3 +209,25 @@ pub async fn leave(pool: &PgPool, chat_id: i64) -> Result<(), ChatRoomError> {
         .context("failed to commit sql transaction to store new chatroom.")?;
     Ok(())
 }
+
+/// whether the sender of the message may change the settings of the chat.
+///
+/// anyone may in private chats. in groups, only the admins may, including those
+/// sending as the group itself.
+pub async fn is_chat_admin(bot: &Bot, msg: &Message) -> Result<bool, RequestError> {
+    if is_not_group_chat(msg.clone()) {
+        return Ok(true);
+    }
+    if msg
+        .sender_chat
+        .as_ref()
+        .is_some_and(|x| x.id == msg.chat.id)
+    {
+        return Ok(true);
+    }
+    let Some(user) = &msg.from else {
+        return Ok(false);
+    };
+    let member = bot.get_chat_member(msg.chat.id, user.id).await?;
+    Ok(member.is_privileged())
+}
@@ -1,22 +1,31 @@
 use anyhow::{anyhow, Context};
-use gaia::stickers::Stickers;
+use gaia::{llm::LlmSettings, stickers::Stickers};
 use rand::{
     distributions::{Alphanumeric, DistString},
     thread_rng,
 };
 use sqlx::PgPool;
 use teloxide::{requests::Requester, types::Message, utils::command::BotCommands, Bot};
-use time::{format_description::well_known::Rfc2822, macros::offset, OffsetDateTime};
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+use tokio_cron_scheduler::JobScheduler;
 
 use crate::{
     bot::{BotDialogue, ChatState},
     callbacks::CallbackPage,
     chatroom::ChatRoom,
+    greeting::greeting_command,
     handlers::{is_group_chat, is_not_group_chat},
+    llm_config::llm_config_command,
+    persona::persona_command,
+    quiet_hours::quiet_command,
+    timezone::{chat_timezone, now_in},
 };
 
 use super::{
-    callbacks::{new_occurence_page, CallbackState},
+    callbacks::{
+        cancel_command, export_command, new_occurence_page, new_reminder_list_page,
+        quick_remind_page, timezone_command, CallbackState,
+    },
     sticker::send_sticker,
 };
 
@@ -38,16 +47,32 @@ pub enum Command {
     Chat,
     /// Stop bot from responding to messages
     Shutup,
-    /// Set reminder
-    Remind,
-    /// Current datetime (GMT+8)
+    /// Set reminder, eg. /remind tomorrow 9am submit report
+    Remind(String),
+    /// See and cancel pending reminders
+    Reminders,
+    /// Current datetime
     DateTime,
+    /// Set the time zone of this chat, eg. /timezone Europe/London
+    Timezone(String),
+    /// Manage the morning and night greetings, eg. /greeting morning time 7:30am
+    Greeting(String),
+    /// Set the quiet hours of this chat, eg. /quiet 22:00 07:00
+    Quiet(String),
+    /// Change who the bot is in this chat, eg. /persona tutor. Admins only
+    Persona(String),
+    /// Change the chat model and its limits, eg. /llmconfig temperature 0.3. Admins only
+    LlmConfig(String),
+    /// Export reminders as a calendar file. Send me an .ics file to import one
+    Export,
+    /// Cancel the reminder or setting you are in the middle of
+    Cancel,
     #[command(hide)]
     Feed,
 }
 impl Command {
     #[tracing::instrument(name = "answer commands", skip_all)]
-    #[allow(deprecated)]
+    #[allow(deprecated, clippy::too_many_arguments)]
     pub async fn answer(
         bot: Bot,
         msg: Message,
@@ -56,6 +81,8 @@ impl Command {
         dialogue: BotDialogue,
         callback: CallbackState,
         pool: PgPool,
+        sched: JobScheduler,
+        llm_settings: LlmSettings,
     ) -> anyhow::Result<()> {
         let chat_id = msg.chat.id;
         let user = msg.from().ok_or_else(|| anyhow!("not a valid user"))?;
@@ -153,11 +180,23 @@ impl Command {
                     .await?;
             }
             Self::DateTime => {
-                let now = OffsetDateTime::now_utc()
-                    .to_offset(offset!(+8))
-                    .format(&Rfc2822)?;
-                bot.send_message(chat_id, now).await?;
+                let tz = chat_timezone(&pool, chat_id.0).await?;
+                let now = now_in(tz).format(&Rfc2822)?;
+                bot.send_message(chat_id, format!("{now} ({tz})")).await?;
             }
+            Self::Timezone(args) => {
+                timezone_command(bot, chat_id, &args, callback, &pool, &sched, &stickers).await?;
+            }
+            Self::Greeting(args) => {
+                greeting_command(bot, chat_id, &args, &pool, &sched, &stickers).await?;
+            }
+            Self::Quiet(args) => quiet_command(bot, chat_id, &args, &pool).await?,
+            Self::Persona(args) => persona_command(bot, &msg, &args, &pool).await?,
+            Self::LlmConfig(args) => {
+                llm_config_command(bot, &msg, &args, &pool, &llm_settings).await?;
+            }
+            Self::Export => export_command(bot, chat_id, &pool).await?,
+            Self::Cancel => cancel_command(bot, chat_id, callback).await?,
             Self::Chat => {
                 dialogue.update(ChatState::Talk).await?;
                 send_sticker(&bot, &chat_id, stickers.hello).await?;
@@ -174,9 +213,14 @@ impl Command {
                 send_sticker(&bot, &chat_id, stickers.coming_soon).await?;
                 bot.send_message(chat_id, "~ feature coming soon ~").await?;
             }
-            Self::Remind => {
-                callback.update(CallbackPage::Occcurence).await?;
-                new_occurence_page(bot, msg.chat.id).await?;
+            Self::Remind(args) => {
+                if !quick_remind_page(bot.clone(), chat_id, &args, callback.clone(), &pool).await? {
+                    let sent = new_occurence_page(bot, msg.chat.id).await?;
+                    callback.start(sent, CallbackPage::Occcurence).await?;
+                }
+            }
+            Self::Reminders => {
+                new_reminder_list_page(bot, msg.chat.id, &pool, callback).await?;
             }
             Self::Start => {
                 let chat_room = ChatRoom::new(&msg);
@@ -0,0 +1,295 @@
+//! for managing the morning and night greetings of a chat with `/greeting`
+
+use gaia::stickers::Stickers;
+use sqlx::PgPool;
+use teloxide::{requests::Requester, types::ChatId, Bot};
+use time::Time;
+use tokio_cron_scheduler::JobScheduler;
+
+use crate::{
+    callbacks::parse_time,
+    jobs::{
+        chat_greeting, remove_greeting, save_greeting, send_greeting, CronJobType, GreetingSchedule,
+    },
+};
+
+const USAGE: &str = r"Manage the greetings of this chat 🐢
+
+/greeting morning time 7:30am
+/greeting night message Sleep tight everyone!
+/greeting morning pause
+/greeting morning resume
+/greeting night remove
+/greeting night preview";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Greeting {
+    Morning,
+    Night,
+}
+
+impl Greeting {
+    fn job_type(self) -> CronJobType {
+        match self {
+            Self::Morning => CronJobType::MorningGreeting,
+            Self::Night => CronJobType::NightGreeting,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Morning => "morning",
+            Self::Night => "night",
+        }
+    }
+
+    fn sticker(self, stickers: &Stickers) -> String {
+        match self {
+            Self::Morning => stickers.hello.clone(),
+            Self::Night => stickers.sleep.clone(),
+        }
+    }
+
+    /// used when the message is set before the time.
+    fn default_time(self) -> Time {
+        match self {
+            Self::Morning => Time::from_hms(8, 0, 0),
+            Self::Night => Time::from_hms(22, 0, 0),
+        }
+        .unwrap_or(Time::MIDNIGHT)
+    }
+
+    /// used when the time is set before the message.
+    fn default_message(self) -> &'static str {
+        match self {
+            Self::Morning => "Good morning everyone! ☀️",
+            Self::Night => "Good night everyone! 🌙",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum GreetingAction {
+    SetTime(Time),
+    SetMessage(String),
+    Pause,
+    Resume,
+    Remove,
+    Preview,
+}
+
+/// parses the arguments of `/greeting`, eg. `morning time 7:30am`.
+fn parse_greeting(args: &str) -> Option<(Greeting, GreetingAction)> {
+    let mut parts = args.trim().splitn(3, char::is_whitespace);
+    let greeting = match parts.next()?.to_lowercase().as_str() {
+        "morning" => Greeting::Morning,
+        "night" => Greeting::Night,
+        _ => return None,
+    };
+    let action = parts.next()?.to_lowercase();
+    let rest = parts.next().unwrap_or_default().trim();
+
+    let action = match action.as_str() {
+        "time" => {
+            let tokens: Vec<&str> = rest.split_whitespace().collect();
+            let (time, used) = parse_time(&tokens)?;
+            if used != tokens.len() {
+                return None;
+            }
+            GreetingAction::SetTime(time)
+        }
+        "message" if !rest.is_empty() => GreetingAction::SetMessage(rest.to_string()),
+        "pause" => GreetingAction::Pause,
+        "resume" => GreetingAction::Resume,
+        "remove" => GreetingAction::Remove,
+        "preview" => GreetingAction::Preview,
+        _ => return None,
+    };
+    Some((greeting, action))
+}
+
+fn daily_cron(time: Time) -> String {
+    format!("0 {} {} * * *", time.minute(), time.hour())
+}
+
+/// shows daily cron expressions as `HH:MM`, and others as they are.
+fn describe_cron(cron_str: &str) -> String {
+    let fields: Vec<&str> = cron_str.split_whitespace().collect();
+    match fields.as_slice() {
+        ["0", minute, hour, "*", "*", "*"] => match (minute.parse::<u8>(), hour.parse::<u8>()) {
+            (Ok(minute), Ok(hour)) => format!("{hour:02}:{minute:02} daily"),
+            _ => format!("cron `{cron_str}`"),
+        },
+        _ => format!("cron `{cron_str}`"),
+    }
+}
+
+async fn greetings_text(pool: &PgPool, chat_id: ChatId) -> anyhow::Result<String> {
+    let mut lines = Vec::new();
+    for greeting in [Greeting::Morning, Greeting::Night] {
+        let line = match chat_greeting(pool, chat_id.0, &greeting.job_type()).await? {
+            Some(x) => {
+                let paused = if x.paused { " (paused)" } else { "" };
+                format!(
+                    "{}: {}{paused}\n{}",
+                    greeting.name(),
+                    describe_cron(&x.cron_str),
+                    x.message
+                )
+            }
+            None => format!("{}: not set", greeting.name()),
+        };
+        lines.push(line);
+    }
+    Ok(format!("{}\n\n{USAGE}", lines.join("\n\n")))
+}
+
+/// Sets, pauses, resumes, removes or previews the greetings of the chat.
+///
+/// shows the greetings of the chat when the arguments can't be used.
+pub async fn greeting_command(
+    bot: Bot,
+    chat_id: ChatId,
+    args: &str,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    stickers: &Stickers,
+) -> anyhow::Result<()> {
+    let Some((greeting, action)) = parse_greeting(args) else {
+        bot.send_message(chat_id, greetings_text(pool, chat_id).await?)
+            .await?;
+        return Ok(());
+    };
+
+    let known_chat = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM chatrooms WHERE id = $1) AS "exists!""#,
+        chat_id.0
+    )
+    .fetch_one(pool)
+    .await?;
+    if !known_chat {
+        bot.send_message(chat_id, "I don't know this chat yet 😅 say /start first.")
+            .await?;
+        return Ok(());
+    }
+
+    let job_type = greeting.job_type();
+    let name = greeting.name();
+    let current = chat_greeting(pool, chat_id.0, &job_type).await?;
+
+    let (schedule, text) = match (action, current) {
+        (GreetingAction::SetTime(time), current) => {
+            let text = format!(
+                "The {name} greeting is now sent at {:02}:{:02} every day. 🐢",
+                time.hour(),
+                time.minute()
+            );
+            let schedule = GreetingSchedule {
+                cron_str: daily_cron(time),
+                message: current
+                    .map_or_else(|| greeting.default_message().to_string(), |x| x.message),
+                paused: false,
+            };
+            (schedule, text)
+        }
+        (GreetingAction::SetMessage(message), current) => {
+            let (cron_str, paused) = current.map_or_else(
+                || (daily_cron(greeting.default_time()), false),
+                |x| (x.cron_str, x.paused),
+            );
+            let text = format!(
+                "The {name} greeting is updated, sent at {}. 🐢",
+                describe_cron(&cron_str)
+            );
+            let schedule = GreetingSchedule {
+                cron_str,
+                message,
+                paused,
+            };
+            (schedule, text)
+        }
+        (GreetingAction::Pause | GreetingAction::Resume, None) => {
+            bot.send_message(chat_id, format!("There's no {name} greeting yet 😅"))
+                .await?;
+            return Ok(());
+        }
+        (action @ (GreetingAction::Pause | GreetingAction::Resume), Some(x)) => {
+            let paused = action == GreetingAction::Pause;
+            let text = if paused {
+                format!("The {name} greeting is paused. 💤")
+            } else {
+                format!("The {name} greeting is back on. 🐢")
+            };
+            let schedule = GreetingSchedule {
+                cron_str: x.cron_str,
+                message: x.message,
+                paused,
+            };
+            (schedule, text)
+        }
+        (GreetingAction::Remove, _) => {
+            let text = if remove_greeting(pool, sched, chat_id.0, &job_type).await? {
+                format!("The {name} greeting is removed. 🐢")
+            } else {
+                format!("There's no {name} greeting yet 😅")
+            };
+            bot.send_message(chat_id, text).await?;
+            return Ok(());
+        }
+        (GreetingAction::Preview, current) => {
+            let message =
+                current.map_or_else(|| greeting.default_message().to_string(), |x| x.message);
+            send_greeting(bot, chat_id.0, message, greeting.sticker(stickers)).await;
+            return Ok(());
+        }
+    };
+
+    save_greeting(
+        &bot,
+        pool,
+        sched,
+        chat_id.0,
+        &job_type,
+        greeting.sticker(stickers),
+        schedule,
+    )
+    .await?;
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::time;
+
+    use super::{describe_cron, parse_greeting, Greeting, GreetingAction};
+
+    #[test]
+    fn greeting_arguments() {
+        assert_eq!(
+            parse_greeting("morning time 7:30am"),
+            Some((Greeting::Morning, GreetingAction::SetTime(time!(7:30))))
+        );
+        assert_eq!(
+            parse_greeting("Night message  Sleep tight  everyone!"),
+            Some((
+                Greeting::Night,
+                GreetingAction::SetMessage("Sleep tight  everyone!".to_string())
+            ))
+        );
+        assert_eq!(
+            parse_greeting("night pause"),
+            Some((Greeting::Night, GreetingAction::Pause))
+        );
+        assert_eq!(parse_greeting(""), None);
+        assert_eq!(parse_greeting("morning message"), None);
+        assert_eq!(parse_greeting("morning time 7:30am sharp"), None);
+        assert_eq!(parse_greeting("evening pause"), None);
+    }
+
+    #[test]
+    fn cron_description() {
+        assert_eq!(describe_cron("0 5 7 * * *"), "07:05 daily");
+        assert_eq!(describe_cron("0 0 9 * * Mon"), "cron `0 0 9 * * Mon`");
+    }
+}
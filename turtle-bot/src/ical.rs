@@ -0,0 +1,966 @@
+//! for moving reminders between the bot and calendar apps as iCalendar (`.ics`) files
+//!
+//! only the parts of [RFC 5545](https://www.rfc-editor.org/rfc/rfc5545) that
+//! reminders need are supported.
+
+use std::collections::HashMap;
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::{
+    macros::format_description, Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time,
+    UtcOffset, Weekday,
+};
+
+use crate::{
+    jobs::ReminderStatus,
+    media::{preview_text, ReminderMedia},
+    timezone::{from_local, is_daylight_saving, parse_timezone, to_local},
+};
+
+const PRODID: &str = "-//bobby-turtle//reminders//EN";
+/// lines longer than this are folded.
+const MAX_LINE_OCTETS: usize = 75;
+/// all-day events are imported as reminders at this hour.
+const ALL_DAY_HOUR: u8 = 9;
+/// long enough to find the next 29th of february.
+const MAX_DAYS_AHEAD: u16 = 366 * 8;
+/// how far ahead the offset changes of a time zone are written, which covers
+/// the first occurrence of every recurring event.
+const TIMEZONE_DAYS_AHEAD: u16 = 366 * 10;
+
+const WEEKDAYS: [(Weekday, &str, &str); 7] = [
+    (Weekday::Monday, "Mon", "MO"),
+    (Weekday::Tuesday, "Tue", "TU"),
+    (Weekday::Wednesday, "Wed", "WE"),
+    (Weekday::Thursday, "Thu", "TH"),
+    (Weekday::Friday, "Fri", "FR"),
+    (Weekday::Saturday, "Sat", "SA"),
+    (Weekday::Sunday, "Sun", "SU"),
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// An event written to an iCalendar file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub start: OffsetDateTime,
+    /// the time zone of the chat, which the recurrence follows.
+    pub tz: Tz,
+    pub rrule: Option<String>,
+}
+
+/// The pending reminders of chats, as calendar events.
+#[derive(Debug, Default)]
+pub struct CalendarExport {
+    pub events: Vec<CalendarEvent>,
+    /// recurring reminders whose cron expression can't be written as an RRULE.
+    pub skipped: usize,
+}
+
+/// A reminder read from an iCalendar file, which is not saved yet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImportedReminder {
+    OneOff {
+        due: OffsetDateTime,
+        message: String,
+    },
+    Recurring {
+        cron_str: String,
+        message: String,
+    },
+}
+
+impl ImportedReminder {
+    pub fn message(&self) -> &str {
+        match self {
+            Self::OneOff { message, .. } | Self::Recurring { message, .. } => message,
+        }
+    }
+}
+
+/// The reminders read from an iCalendar file.
+#[derive(Debug, Default)]
+pub struct CalendarImport {
+    pub reminders: Vec<ImportedReminder>,
+    /// events which are over or cancelled, or repeat in ways a cron expression can't,
+    /// eg. with upcoming occurrences moved or left out.
+    pub skipped: usize,
+    /// the `TZID`s which aren't IANA time zones, whose times are read in the chat's instead.
+    pub unknown_timezones: Vec<String>,
+}
+
+/// The schedule of a recurring reminder, which both cron and RRULE can express.
+///
+/// an empty list matches every value.
+#[derive(Debug, PartialEq)]
+struct RepeatRule {
+    hour: u8,
+    minute: u8,
+    weekdays: Vec<Weekday>,
+    month_days: Vec<u8>,
+    months: Vec<Month>,
+}
+
+impl RepeatRule {
+    /// cron is `OR` between day of month and day of week, whereas RRULE is `AND`,
+    /// so only one of them can be used.
+    fn new(
+        (hour, minute): (u8, u8),
+        weekdays: Vec<Weekday>,
+        month_days: Vec<u8>,
+        months: Vec<Month>,
+    ) -> Option<Self> {
+        if !weekdays.is_empty() && (!month_days.is_empty() || !months.is_empty()) {
+            return None;
+        }
+        Some(Self {
+            hour,
+            minute,
+            weekdays,
+            month_days,
+            months,
+        })
+    }
+
+    /// reads cron expressions in `tokio_cron_scheduler`'s format, with seconds in front.
+    fn from_cron(cron_str: &str) -> Option<Self> {
+        let fields: Vec<&str> = cron_str.split_whitespace().collect();
+        let (second, minute, hour, month_day, month, weekday) = match fields.as_slice() {
+            [s, m, h, dom, mon, dow] | [s, m, h, dom, mon, dow, "*"] => {
+                (*s, *m, *h, *dom, *mon, *dow)
+            }
+            _ => return None,
+        };
+        if second != "0" {
+            return None;
+        }
+        let minute = minute.parse().ok().filter(|x| *x < 60)?;
+        let hour = hour.parse().ok().filter(|x| *x < 24)?;
+
+        let month_days = parse_list(month_day, |x| {
+            x.parse().ok().filter(|x| (1..=31).contains(x))
+        })?;
+        let months = parse_list(month, cron_month)?
+            .into_iter()
+            .map(|x| Month::try_from(x).ok())
+            .collect::<Option<Vec<Month>>>()?;
+        let weekdays = parse_list(weekday, cron_weekday)?
+            .into_iter()
+            .map(|x| WEEKDAYS[usize::from(x)].0)
+            .collect();
+        Self::new((hour, minute), weekdays, month_days, months)
+    }
+
+    /// reads the RRULE of an event starting at `start`, in local time.
+    ///
+    /// rules which end, skip occurrences, or repeat more than once a day are not supported.
+    fn from_rrule(rrule: &str, start: PrimitiveDateTime) -> Option<Self> {
+        let mut parts = HashMap::new();
+        for part in rrule.split(';') {
+            let (key, value) = part.split_once('=')?;
+            parts.insert(key.to_uppercase(), value.to_uppercase());
+        }
+        if parts.keys().any(|x| {
+            !matches!(
+                x.as_str(),
+                "FREQ" | "INTERVAL" | "BYDAY" | "BYMONTHDAY" | "BYMONTH" | "WKST"
+            )
+        }) || parts.get("INTERVAL").is_some_and(|x| x != "1")
+        {
+            return None;
+        }
+
+        let mut weekdays = match parts.get("BYDAY") {
+            Some(x) => x
+                .split(',')
+                .map(|day| WEEKDAYS.iter().find(|y| y.2 == day).map(|y| y.0))
+                .collect::<Option<Vec<Weekday>>>()?,
+            None => Vec::new(),
+        };
+        let mut month_days = match parts.get("BYMONTHDAY") {
+            Some(x) => x
+                .split(',')
+                .map(|day| day.parse().ok().filter(|x| (1..=31).contains(x)))
+                .collect::<Option<Vec<u8>>>()?,
+            None => Vec::new(),
+        };
+        let mut months = match parts.get("BYMONTH") {
+            Some(x) => x
+                .split(',')
+                .map(|month| month.parse().ok().and_then(|x: u8| Month::try_from(x).ok()))
+                .collect::<Option<Vec<Month>>>()?,
+            None => Vec::new(),
+        };
+
+        match parts.get("FREQ")?.as_str() {
+            "WEEKLY" if weekdays.is_empty() => weekdays.push(start.weekday()),
+            "MONTHLY" if month_days.is_empty() && weekdays.is_empty() => {
+                month_days.push(start.day());
+            }
+            "YEARLY" if weekdays.is_empty() => {
+                if months.is_empty() {
+                    months.push(start.month());
+                }
+                if month_days.is_empty() {
+                    month_days.push(start.day());
+                }
+            }
+            "DAILY" | "WEEKLY" | "MONTHLY" => {}
+            _ => return None,
+        }
+        Self::new((start.hour(), start.minute()), weekdays, month_days, months)
+    }
+
+    fn to_cron(&self) -> String {
+        let month_days = join_or_any(self.month_days.iter().map(ToString::to_string));
+        let months = join_or_any(self.months.iter().map(|x| u8::from(*x).to_string()));
+        let weekdays = join_or_any(
+            self.weekdays
+                .iter()
+                .map(|x| weekday_names(*x).0.to_string()),
+        );
+        format!(
+            "0 {} {} {month_days} {months} {weekdays}",
+            self.minute, self.hour
+        )
+    }
+
+    fn to_rrule(&self) -> String {
+        let list = |values: Vec<String>| values.join(",");
+        let month_days = list(self.month_days.iter().map(ToString::to_string).collect());
+        let months = list(
+            self.months
+                .iter()
+                .map(|x| u8::from(*x).to_string())
+                .collect(),
+        );
+
+        if !self.weekdays.is_empty() {
+            let weekdays = list(
+                self.weekdays
+                    .iter()
+                    .map(|x| weekday_names(*x).1.to_string())
+                    .collect(),
+            );
+            return format!("FREQ=WEEKLY;BYDAY={weekdays}");
+        }
+        match (self.months.is_empty(), self.month_days.is_empty()) {
+            (true, true) => "FREQ=DAILY".to_string(),
+            (true, false) => format!("FREQ=MONTHLY;BYMONTHDAY={month_days}"),
+            (false, true) => format!("FREQ=DAILY;BYMONTH={months}"),
+            (false, false) => format!("FREQ=YEARLY;BYMONTH={months};BYMONTHDAY={month_days}"),
+        }
+    }
+
+    fn matches(&self, date: Date) -> bool {
+        (self.weekdays.is_empty() || self.weekdays.contains(&date.weekday()))
+            && (self.month_days.is_empty() || self.month_days.contains(&date.day()))
+            && (self.months.is_empty() || self.months.contains(&date.month()))
+    }
+
+    /// the first occurrence after `now`, in local time.
+    fn next_after(&self, now: PrimitiveDateTime) -> Option<PrimitiveDateTime> {
+        let time = Time::from_hms(self.hour, self.minute, 0).ok()?;
+        let mut date = now.date();
+        for _ in 0..MAX_DAYS_AHEAD {
+            let candidate = date.with_time(time);
+            if candidate > now && self.matches(date) {
+                return Some(candidate);
+            }
+            date = date.next_day()?;
+        }
+        None
+    }
+}
+
+fn weekday_names(weekday: Weekday) -> (&'static str, &'static str) {
+    WEEKDAYS
+        .iter()
+        .find(|x| x.0 == weekday)
+        .map_or(("Mon", "MO"), |x| (x.1, x.2))
+}
+
+fn join_or_any(values: impl Iterator<Item = String>) -> String {
+    let values: Vec<String> = values.collect();
+    if values.is_empty() {
+        "*".to_string()
+    } else {
+        values.join(",")
+    }
+}
+
+/// index of the weekday in [`WEEKDAYS`], from its cron name eg. `Mon`.
+fn cron_weekday(name: &str) -> Option<u8> {
+    let name = name.get(..3)?;
+    let index = WEEKDAYS
+        .iter()
+        .position(|x| x.1.eq_ignore_ascii_case(name))?;
+    u8::try_from(index).ok()
+}
+
+/// the month number, from its number or its cron name eg. `Jan`.
+fn cron_month(value: &str) -> Option<u8> {
+    if let Ok(x) = value.parse::<u8>() {
+        return (1..=12).contains(&x).then_some(x);
+    }
+    let name = value.get(..3)?;
+    let index = MONTHS.iter().position(|x| x.eq_ignore_ascii_case(name))?;
+    u8::try_from(index + 1).ok()
+}
+
+/// reads a cron field made of values and ranges, eg. `Mon-Wed,Fri`.
+///
+/// `*` and `?` become an empty list.
+fn parse_list(field: &str, parse: impl Fn(&str) -> Option<u8>) -> Option<Vec<u8>> {
+    if field == "*" || field == "?" {
+        return Some(Vec::new());
+    }
+    let mut values = Vec::new();
+    for item in field.split(',') {
+        match item.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (parse(from)?, parse(to)?);
+                if from > to {
+                    return None;
+                }
+                values.extend(from..=to);
+            }
+            None => values.push(parse(item)?),
+        }
+    }
+    Some(values)
+}
+
+/// The first occurrence of the cron expression after `now`, and its RRULE.
+///
+/// `None` when the cron expression can't be written as an RRULE.
+pub fn cron_event(cron_str: &str, now: OffsetDateTime, tz: Tz) -> Option<(OffsetDateTime, String)> {
+    let rule = RepeatRule::from_cron(cron_str)?;
+    let now = to_local(tz, now);
+    let start = rule.next_after(PrimitiveDateTime::new(now.date(), now.time()))?;
+    Some((from_local(tz, start), rule.to_rrule()))
+}
+
+/// who set the reminder, and in which chat.
+fn event_description(username: Option<&str>, chat_title: Option<&str>) -> Option<String> {
+    let lines: Vec<String> = [
+        username.map(|x| format!("From: @{x}")),
+        chat_title.map(|x| format!("Chat: {x}")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Gets the pending reminders and cron jobs of the chats as calendar events.
+pub async fn calendar_events(
+    pool: &PgPool,
+    chat_ids: &[i64],
+    now: OffsetDateTime,
+) -> Result<CalendarExport, sqlx::Error> {
+    let one_offs = sqlx::query!(
+        "SELECT j.id, j.message, j.username, j.due, j.media_type, j.file_id, c.title, c.timezone
+        FROM jobs_one_off j JOIN chatrooms c ON c.id = j.target
+        WHERE j.target = ANY($1) AND j.status = $2 AND j.due > $3
+        ORDER BY j.due",
+        chat_ids,
+        ReminderStatus::Pending.as_str(),
+        now
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let cron_jobs = sqlx::query!(
+        "SELECT j.id, j.cron_str, j.message, j.username, c.title, c.timezone
+        FROM jobs_cron j JOIN chatrooms c ON c.id = j.target
+        WHERE j.target = ANY($1) AND j.cancelled = false AND j.paused = false
+        ORDER BY j.id",
+        chat_ids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut export = CalendarExport::default();
+    for x in one_offs {
+        let media = ReminderMedia::from_columns(x.media_type.as_deref(), x.file_id);
+        export.events.push(CalendarEvent {
+            uid: format!("one-off-{}@bobby-turtle", x.id),
+            summary: preview_text(media.as_ref(), &x.message),
+            description: event_description(Some(&x.username), x.title.as_deref()),
+            start: x.due,
+            tz: parse_timezone(&x.timezone),
+            rrule: None,
+        });
+    }
+    for x in cron_jobs {
+        let tz = parse_timezone(&x.timezone);
+        let Some((start, rrule)) = cron_event(&x.cron_str, now, tz) else {
+            export.skipped += 1;
+            continue;
+        };
+        export.events.push(CalendarEvent {
+            uid: format!("cron-{}@bobby-turtle", x.id),
+            summary: x.message,
+            description: event_description(x.username.as_deref(), x.title.as_deref()),
+            start,
+            tz,
+            rrule: Some(rrule),
+        });
+    }
+    Ok(export)
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(x) => unescaped.push(x),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// splits lines longer than [`MAX_LINE_OCTETS`], without splitting characters.
+fn fold_line(line: &str, lines: &mut Vec<String>) {
+    let mut current = String::new();
+    for c in line.chars() {
+        if current.len() + c.len_utf8() > MAX_LINE_OCTETS {
+            lines.push(current);
+            current = " ".to_string();
+        }
+        current.push(c);
+    }
+    lines.push(current);
+}
+
+/// Writes the `VTIMEZONE` of `tz`, with its offset changes from `now`
+/// until [`TIMEZONE_DAYS_AHEAD`] later.
+fn vtimezone(tz: Tz, now: OffsetDateTime, lines: &mut Vec<String>) {
+    let local_format = format_description!("[year][month][day]T[hour][minute][second]");
+    let offset_format = format_description!("[offset_hour sign:mandatory][offset_minute]");
+    let offset = |x: OffsetDateTime| to_local(tz, x).offset();
+
+    // the instants at which the offset changes, starting with the offset of `now`
+    let mut changes = vec![now];
+    let mut day = now;
+    for _ in 0..TIMEZONE_DAYS_AHEAD {
+        let next = day + Duration::days(1);
+        if offset(next) != offset(day) {
+            let (mut before, mut after) = (day, next);
+            while after - before > Duration::SECOND {
+                let middle = before + (after - before) / 2;
+                if offset(middle) == offset(before) {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            changes.push(after);
+        }
+        day = next;
+    }
+
+    lines.push("BEGIN:VTIMEZONE".to_string());
+    lines.push(format!("TZID:{}", tz.name()));
+    for start in changes {
+        let (from, to) = (offset(start - Duration::SECOND), offset(start));
+        let kind = if is_daylight_saving(tz, start) {
+            "DAYLIGHT"
+        } else {
+            "STANDARD"
+        };
+        lines.push(format!("BEGIN:{kind}"));
+        lines.push(format!(
+            "DTSTART:{}",
+            start
+                .to_offset(from)
+                .format(&local_format)
+                .unwrap_or_default()
+        ));
+        lines.push(format!(
+            "TZOFFSETFROM:{}",
+            from.format(&offset_format).unwrap_or_default()
+        ));
+        lines.push(format!(
+            "TZOFFSETTO:{}",
+            to.format(&offset_format).unwrap_or_default()
+        ));
+        lines.push(format!("END:{kind}"));
+    }
+    lines.push("END:VTIMEZONE".to_string());
+}
+
+/// Writes the events as an iCalendar file named `name`.
+///
+/// one-off events are in UTC, while recurring events follow the local time of their chat,
+/// whose time zone is written along.
+#[must_use]
+pub fn to_ics(name: &str, events: &[CalendarEvent], now: OffsetDateTime) -> String {
+    let utc_format = format_description!("[year][month][day]T[hour][minute][second]Z");
+    let local_format = format_description!("[year][month][day]T[hour][minute][second]");
+    let utc = |x: OffsetDateTime| {
+        x.to_offset(UtcOffset::UTC)
+            .format(&utc_format)
+            .unwrap_or_default()
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{PRODID}"),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    fold_line(&format!("X-WR-CALNAME:{}", escape_text(name)), &mut lines);
+    let mut timezones: Vec<Tz> = Vec::new();
+    for event in events.iter().filter(|x| x.rrule.is_some()) {
+        if !timezones.contains(&event.tz) {
+            timezones.push(event.tz);
+            vtimezone(event.tz, now, &mut lines);
+        }
+    }
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        fold_line(&format!("UID:{}", event.uid), &mut lines);
+        lines.push(format!("DTSTAMP:{}", utc(now)));
+        match &event.rrule {
+            Some(rrule) => {
+                let start = to_local(event.tz, event.start)
+                    .format(&local_format)
+                    .unwrap_or_default();
+                lines.push(format!("DTSTART;TZID={}:{start}", event.tz.name()));
+                lines.push(format!("RRULE:{rrule}"));
+            }
+            None => lines.push(format!("DTSTART:{}", utc(event.start))),
+        }
+        fold_line(
+            &format!("SUMMARY:{}", escape_text(&event.summary)),
+            &mut lines,
+        );
+        if let Some(description) = &event.description {
+            fold_line(
+                &format!("DESCRIPTION:{}", escape_text(description)),
+                &mut lines,
+            );
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = lines.join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+/// A content line, eg. `DTSTART;TZID=Europe/London:20240702T090000`.
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|x| x.0 == name).map(|x| x.1)
+    }
+}
+
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    let (head, value) = line.split_once(':')?;
+    let mut head = head.split(';');
+    let name = head.next()?.to_uppercase();
+    let params = head
+        .filter_map(|x| x.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"')))
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value,
+    })
+}
+
+/// the `value` of a date or time property, eg. one of the dates of an `EXDATE`, in UTC.
+///
+/// times without a time zone, or with an unknown one, are read in `tz`.
+fn parse_time(property: &Property, value: &str, tz: Tz) -> Option<OffsetDateTime> {
+    let date_format = format_description!("[year][month][day]");
+    let local_format = format_description!("[year][month][day]T[hour][minute][second]");
+
+    if property.param("VALUE") == Some("DATE") {
+        let date = Date::parse(value, &date_format).ok()?;
+        let time = Time::from_hms(ALL_DAY_HOUR, 0, 0).ok()?;
+        return Some(from_local(tz, date.with_time(time)));
+    }
+    if let Some(value) = value.strip_suffix('Z') {
+        return Some(
+            PrimitiveDateTime::parse(value, &local_format)
+                .ok()?
+                .assume_utc(),
+        );
+    }
+    let local = PrimitiveDateTime::parse(value, &local_format).ok()?;
+    let event_tz = property
+        .param("TZID")
+        .and_then(|x| x.parse::<Tz>().ok())
+        .unwrap_or(tz);
+    Some(from_local(event_tz, local))
+}
+
+fn find_property<'a>(properties: &'a [Property<'a>], name: &str) -> Option<&'a Property<'a>> {
+    properties.iter().find(|x| x.name == name)
+}
+
+/// joins folded lines back together.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// the reminder of the event, where `exceptions` are the occurrences which are
+/// left out or moved, which cron can't follow.
+fn event_reminder(
+    properties: &[Property],
+    exceptions: &[OffsetDateTime],
+    tz: Tz,
+    now: OffsetDateTime,
+) -> Option<ImportedReminder> {
+    let find = |name: &str| find_property(properties, name);
+
+    if find("STATUS").is_some_and(|x| x.value.eq_ignore_ascii_case("CANCELLED")) {
+        return None;
+    }
+    let start = find("DTSTART").and_then(|x| parse_time(x, x.value, tz))?;
+    let message = find("SUMMARY")
+        .map(|x| unescape_text(x.value))
+        .filter(|x| !x.trim().is_empty())
+        .unwrap_or_else(|| "Untitled event".to_string());
+
+    match find("RRULE") {
+        Some(_) if exceptions.iter().any(|x| *x > now) => None,
+        Some(rrule) => {
+            let local = to_local(tz, start);
+            let repeat = RepeatRule::from_rrule(
+                rrule.value,
+                PrimitiveDateTime::new(local.date(), local.time()),
+            )?;
+            Some(ImportedReminder::Recurring {
+                cron_str: repeat.to_cron(),
+                message,
+            })
+        }
+        None if start > now => Some(ImportedReminder::OneOff {
+            due: start,
+            message,
+        }),
+        None => None,
+    }
+}
+
+/// Reads the events of an iCalendar file as reminders.
+///
+/// events with a `RECURRENCE-ID` change an occurrence of a recurring event,
+/// and are only read as exceptions of it.
+pub fn import_reminders(ics: &str, tz: Tz, now: OffsetDateTime) -> CalendarImport {
+    let mut import = CalendarImport::default();
+    let mut events: Vec<Vec<Property>> = Vec::new();
+    let mut event: Option<Vec<Property>> = None;
+
+    let lines = unfold(ics);
+    for line in &lines {
+        let Some(property) = parse_property(line) else {
+            continue;
+        };
+        match (property.name.as_str(), property.value, event.as_mut()) {
+            ("BEGIN", "VEVENT", _) => event = Some(Vec::new()),
+            ("END", "VEVENT", Some(_)) => events.extend(event.take()),
+            (_, _, Some(properties)) => {
+                let tzid = property.param("TZID");
+                if let Some(tzid) = tzid.filter(|x| x.parse::<Tz>().is_err()) {
+                    if !import.unknown_timezones.iter().any(|x| x == tzid) {
+                        import.unknown_timezones.push(tzid.to_string());
+                    }
+                }
+                properties.push(property);
+            }
+            _ => {}
+        }
+    }
+
+    // the occurrences moved or cancelled by other events, by the UID of their recurring event
+    let mut moved: HashMap<&str, Vec<OffsetDateTime>> = HashMap::new();
+    for properties in &events {
+        let uid = find_property(properties, "UID");
+        let id = find_property(properties, "RECURRENCE-ID");
+        if let (Some(uid), Some(id)) = (uid, id) {
+            if let Some(x) = parse_time(id, id.value, tz) {
+                moved.entry(uid.value).or_default().push(x);
+            }
+        }
+    }
+
+    for properties in &events {
+        if find_property(properties, "RECURRENCE-ID").is_some() {
+            continue;
+        }
+        let mut exceptions: Vec<OffsetDateTime> = properties
+            .iter()
+            .filter(|x| x.name == "EXDATE")
+            .flat_map(|x| {
+                x.value
+                    .split(',')
+                    .filter_map(|value| parse_time(x, value, tz))
+            })
+            .collect();
+        if let Some(x) = find_property(properties, "UID").and_then(|x| moved.get(x.value)) {
+            exceptions.extend(x);
+        }
+        match event_reminder(properties, &exceptions, tz, now) {
+            Some(x) => import.reminders.push(x),
+            None => import.skipped += 1,
+        }
+    }
+    import
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+    use time::{
+        macros::{datetime, format_description},
+        Month, Weekday,
+    };
+
+    use super::*;
+
+    const TZ: Tz = Tz::Asia__Singapore;
+
+    #[test]
+    fn cron_rrules() {
+        let rrule = |cron_str| RepeatRule::from_cron(cron_str).map(|x| x.to_rrule());
+        assert_eq!(rrule("0 30 9 * * *"), Some("FREQ=DAILY".to_string()));
+        assert_eq!(
+            rrule("0 0 8 * * Mon-Wed,Fri"),
+            Some("FREQ=WEEKLY;BYDAY=MO,TU,WE,FR".to_string())
+        );
+        assert_eq!(
+            rrule("0 0 8 1,15 * *"),
+            Some("FREQ=MONTHLY;BYMONTHDAY=1,15".to_string())
+        );
+        assert_eq!(
+            rrule("0 0 8 25 Dec *"),
+            Some("FREQ=YEARLY;BYMONTH=12;BYMONTHDAY=25".to_string())
+        );
+        assert_eq!(rrule("0 */5 * * * *"), None);
+        assert_eq!(rrule("0 0 8 1 * Mon"), None);
+    }
+
+    #[test]
+    fn rrule_crons() {
+        let start = datetime!(2024-07-02 09:30);
+        let cron = |rrule| RepeatRule::from_rrule(rrule, start).map(|x| x.to_cron());
+        assert_eq!(cron("FREQ=WEEKLY"), Some("0 30 9 * * Tue".to_string()));
+        assert_eq!(
+            cron("FREQ=WEEKLY;BYDAY=MO,TH;WKST=MO"),
+            Some("0 30 9 * * Mon,Thu".to_string())
+        );
+        assert_eq!(cron("FREQ=YEARLY"), Some("0 30 9 2 7 *".to_string()));
+        assert_eq!(cron("FREQ=DAILY;COUNT=5"), None);
+        assert_eq!(cron("FREQ=WEEKLY;INTERVAL=2"), None);
+        assert_eq!(cron("FREQ=MONTHLY;BYDAY=1MO"), None);
+    }
+
+    #[test]
+    fn next_occurrence() {
+        let rule = RepeatRule::new((9, 0), vec![Weekday::Monday], Vec::new(), Vec::new()).unwrap();
+        // a tuesday
+        assert_eq!(
+            rule.next_after(datetime!(2024-07-02 10:00)),
+            Some(datetime!(2024-07-08 09:00))
+        );
+        let rule = RepeatRule::new((9, 0), Vec::new(), vec![29], vec![Month::February]).unwrap();
+        assert_eq!(
+            rule.next_after(datetime!(2024-07-02 10:00)),
+            Some(datetime!(2028-02-29 09:00))
+        );
+    }
+
+    #[test]
+    fn export() {
+        let events = [
+            CalendarEvent {
+                uid: "one-off-1@bobby-turtle".to_string(),
+                summary: "pay rent, water; gas".to_string(),
+                description: Some(
+                    "From: @bob\nChat: a very long group chat title which goes on and on"
+                        .to_string(),
+                ),
+                start: datetime!(2024-07-02 09:00 +8),
+                tz: TZ,
+                rrule: None,
+            },
+            CalendarEvent {
+                uid: "recurring-2@bobby-turtle".to_string(),
+                summary: "standup".to_string(),
+                description: None,
+                start: datetime!(2024-07-03 01:30 UTC),
+                tz: TZ,
+                rrule: Some("FREQ=DAILY".to_string()),
+            },
+        ];
+        let ics = to_ics("Reminders", &events, datetime!(2024-07-01 00:00 UTC));
+        assert!(ics.contains("DTSTART:20240702T010000Z\r\n"));
+        assert!(ics.contains("SUMMARY:pay rent\\, water\\; gas\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Asia/Singapore:20240703T093000\r\nRRULE:FREQ=DAILY\r\n"));
+        assert!(ics.contains(
+            "BEGIN:VTIMEZONE\r\nTZID:Asia/Singapore\r\nBEGIN:STANDARD\r\n\
+            DTSTART:20240701T080000\r\nTZOFFSETFROM:+0800\r\nTZOFFSETTO:+0800\r\n\
+            END:STANDARD\r\nEND:VTIMEZONE\r\n"
+        ));
+        assert!(ics.lines().all(|x| x.len() <= MAX_LINE_OCTETS));
+    }
+
+    #[test]
+    fn timezone_changes() {
+        let mut lines = Vec::new();
+        vtimezone(
+            Tz::Europe__London,
+            datetime!(2024-07-01 00:00 UTC),
+            &mut lines,
+        );
+        assert_eq!(
+            lines[2..10],
+            [
+                "BEGIN:DAYLIGHT",
+                "DTSTART:20240701T010000",
+                "TZOFFSETFROM:+0100",
+                "TZOFFSETTO:+0100",
+                "END:DAYLIGHT",
+                "BEGIN:STANDARD",
+                "DTSTART:20241027T020000",
+                "TZOFFSETFROM:+0100",
+            ]
+        );
+        // twice a year for the years ahead
+        assert_eq!(lines.iter().filter(|x| *x == "BEGIN:DAYLIGHT").count(), 11);
+    }
+
+    #[test]
+    fn import() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Europe/London:20240710T090000\r\n\
+            SUMMARY:dentist\\, bring\r\n  card\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART:20240701T010000Z\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\n\
+            SUMMARY:gym\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20240601\r\n\
+            SUMMARY:over\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let import = import_reminders(ics, TZ, datetime!(2024-07-02 00:00 UTC));
+        assert!(import.unknown_timezones.is_empty());
+        assert_eq!(
+            import.reminders,
+            vec![
+                ImportedReminder::OneOff {
+                    due: datetime!(2024-07-10 08:00 UTC),
+                    message: "dentist, bring card".to_string()
+                },
+                ImportedReminder::Recurring {
+                    cron_str: "0 0 9 * * Mon,Fri".to_string(),
+                    message: "gym".to_string()
+                }
+            ]
+        );
+        assert_eq!(import.skipped, 1);
+    }
+
+    #[test]
+    fn import_exceptions() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:gym\r\n\
+            DTSTART:20240701T010000Z\r\n\
+            RRULE:FREQ=DAILY\r\n\
+            EXDATE:20240601T010000Z,20240703T010000Z\r\n\
+            SUMMARY:gym\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup\r\n\
+            DTSTART:20240701T010000Z\r\n\
+            RRULE:FREQ=DAILY\r\n\
+            SUMMARY:standup\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup\r\n\
+            RECURRENCE-ID:20240705T010000Z\r\n\
+            DTSTART:20240705T020000Z\r\n\
+            SUMMARY:standup\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:review\r\n\
+            DTSTART:20240701T010000Z\r\n\
+            RRULE:FREQ=DAILY\r\n\
+            EXDATE:20240601T010000Z\r\n\
+            SUMMARY:review\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;TZID=Pacific Standard Time:20240710T090000\r\n\
+            STATUS:CANCELLED\r\n\
+            SUMMARY:dentist\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let import = import_reminders(ics, TZ, datetime!(2024-07-02 00:00 UTC));
+        assert_eq!(
+            import.reminders,
+            vec![ImportedReminder::Recurring {
+                cron_str: "0 0 9 * * *".to_string(),
+                message: "review".to_string()
+            }]
+        );
+        assert_eq!(import.skipped, 3);
+        assert_eq!(import.unknown_timezones, vec!["Pacific Standard Time"]);
+    }
+
+    #[test]
+    fn cron_start() {
+        let (start, rrule) =
+            cron_event("0 0 8 * * Mon", datetime!(2024-07-02 00:00 UTC), TZ).unwrap();
+        assert_eq!(start, datetime!(2024-07-08 00:00 UTC));
+        assert_eq!(rrule, "FREQ=WEEKLY;BYDAY=MO");
+        let local = format_description!("[hour]");
+        assert_eq!(to_local(TZ, start).format(&local).unwrap(), "08");
+    }
+}
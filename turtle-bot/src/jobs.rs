@@ -1,12 +1,30 @@
 mod greetings;
+mod overdue;
+mod recurring;
 mod reminders;
+mod warnings;
+mod whispers;
 
-use gaia::stickers::Stickers;
+use chrono_tz::Tz;
+use gaia::{reminder::ReminderSettings, stickers::Stickers};
 use sqlx::PgPool;
 use teloxide::Bot;
+use time::{Duration, OffsetDateTime};
 use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use uuid::Uuid;
 
-use crate::jobs::{greetings::get_greetings, reminders::get_reminders};
+use crate::jobs::{
+    greetings::get_greetings, overdue::deliver_overdue_reminders,
+    recurring::get_recurring_reminders, reminders::get_reminders, whispers::get_scheduled_whispers,
+};
+
+pub use greetings::{
+    chat_greeting, greeting_job, remove_greeting, save_greeting, send_greeting, GreetingSchedule,
+};
+pub use recurring::{add_recurring_reminder, recurring_reminder_job, update_recurring_reminder};
+pub use reminders::{add_one_off_reminder, one_off_reminder_job, NewReminder, ReminderStatus};
+pub use warnings::{cancel_warnings, lead_time_label, schedule_warnings};
+pub use whispers::{add_scheduled_whisper, cancel_scheduled_whisper, NewWhisper};
 
 #[derive(thiserror::Error, Debug)]
 pub enum CronJobError {
@@ -24,37 +42,91 @@ pub async fn add_job(scheduler: JobScheduler, job: Job) {
     }
 }
 
+struct JobMetadata {
+    id: i32,
+    guid: Uuid,
+}
+
+/// Update `jobs_cron` with the new `job_id/Uuid`.
+#[tracing::instrument(skip_all)]
+async fn update_job(data: JobMetadata, pool: PgPool) {
+    if let Err(e) = sqlx::query!(
+        "UPDATE jobs_cron set job_id=$1 WHERE id=$2",
+        data.guid,
+        data.id
+    )
+    .execute(&pool)
+    .await
+    {
+        tracing::error!(error = %e);
+    }
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn init_scheduler(
     bot: &Bot,
     stickers: &Stickers,
     pool: &PgPool,
+    reminder: &ReminderSettings,
 ) -> Result<JobScheduler, CronJobError> {
     let scheduler = JobScheduler::new().await?;
+    let now = OffsetDateTime::now_utc();
     let mut greeting_jobs = get_greetings(bot, stickers, pool).await.map_err(|e| {
         tracing::error!(error = %e);
         e
     })?;
-    let mut remind_jobs = get_reminders(bot, pool).await.map_err(|e| {
+    let mut remind_jobs = get_reminders(bot, pool, now).await.map_err(|e| {
         tracing::error!(error = %e);
         e
     })?;
 
+    let mut recurring_jobs = get_recurring_reminders(bot, pool).await.map_err(|e| {
+        tracing::error!(error = %e);
+        e
+    })?;
+    let grace = Duration::minutes(reminder.grace_period_minutes.into());
+
+    let mut whisper_jobs = get_scheduled_whispers(bot, pool, now, grace)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e);
+            e
+        })?;
+
     greeting_jobs.append(&mut remind_jobs);
+    greeting_jobs.append(&mut recurring_jobs);
+    greeting_jobs.append(&mut whisper_jobs);
 
     for job in greeting_jobs {
         tokio::spawn(add_job(scheduler.clone(), job));
     }
 
+    schedule_warnings(bot, pool, &scheduler, None)
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e);
+            e
+        })?;
+
+    tokio::spawn(deliver_overdue_reminders(
+        bot.clone(),
+        pool.clone(),
+        scheduler.clone(),
+        now,
+        grace,
+    ));
+
     scheduler.shutdown_on_ctrl_c();
     scheduler.start().await?;
     tracing::debug!("scheduler started");
     Ok(scheduler)
 }
 
+#[derive(Debug)]
 pub enum CronJobType {
     MorningGreeting,
     NightGreeting,
+    RecurringReminder,
 }
 
 impl CronJobType {
@@ -62,6 +134,90 @@ impl CronJobType {
         match self {
             Self::MorningGreeting => "morning-greeting",
             Self::NightGreeting => "night-greeting",
+            Self::RecurringReminder => "recurring-reminder",
+        }
+    }
+}
+
+impl TryFrom<&str> for CronJobType {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "morning-greeting" => Ok(Self::MorningGreeting),
+            "night-greeting" => Ok(Self::NightGreeting),
+            "recurring-reminder" => Ok(Self::RecurringReminder),
+            unknown => Err(format!("{unknown} is not a supported cron job type")),
+        }
+    }
+}
+
+/// Replaces the cron jobs of `chat_id` with ones following the local time of `tz`.
+#[tracing::instrument(skip(bot, stickers, pool, sched))]
+pub async fn reschedule_cron_jobs(
+    bot: &Bot,
+    stickers: &Stickers,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    chat_id: i64,
+    tz: Tz,
+) -> Result<(), CronJobError> {
+    let cron_jobs = sqlx::query!(
+        r#"SELECT id, job_id, type as "job_type", cron_str, message, username
+        FROM jobs_cron WHERE target = $1 AND cancelled = false AND paused = false"#,
+        chat_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for cron_job in cron_jobs {
+        let job = match CronJobType::try_from(cron_job.job_type.as_str()) {
+            Ok(CronJobType::MorningGreeting) => greeting_job(
+                bot.clone(),
+                pool.clone(),
+                chat_id,
+                cron_job.message,
+                stickers.hello.clone(),
+                &cron_job.cron_str,
+                tz,
+            )?,
+            Ok(CronJobType::NightGreeting) => greeting_job(
+                bot.clone(),
+                pool.clone(),
+                chat_id,
+                cron_job.message,
+                stickers.sleep.clone(),
+                &cron_job.cron_str,
+                tz,
+            )?,
+            Ok(CronJobType::RecurringReminder) => recurring_reminder_job(
+                bot.clone(),
+                pool.clone(),
+                chat_id,
+                cron_job.username,
+                cron_job.message,
+                &cron_job.cron_str,
+                tz,
+            )?,
+            Err(e) => {
+                tracing::warn!(e);
+                continue;
+            }
+        };
+
+        let guid = job.guid();
+        sched.add(job).await?;
+        if let Some(old_job_id) = cron_job.job_id {
+            sched.remove(&old_job_id).await?;
         }
+        update_job(
+            JobMetadata {
+                id: cron_job.id,
+                guid,
+            },
+            pool.clone(),
+        )
+        .await;
     }
+    Ok(())
 }
@@ -2,23 +2,22 @@ use chrono_tz::Tz;
 use gaia::stickers::Stickers;
 use sqlx::PgPool;
 use teloxide::{requests::Requester, types::ChatId, Bot};
-use tokio_cron_scheduler::Job;
-use uuid::Uuid;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
 
-use crate::sticker::send_sticker;
+use crate::{
+    quiet_hours::is_quiet,
+    sticker::send_sticker,
+    timezone::{chat_timezone, parse_timezone},
+};
 
-use super::{CronJobError, CronJobType};
+use super::{update_job, CronJobError, CronJobType, JobMetadata};
 
 struct Greeting {
     id: i32,
     target: i64,
     cron_str: String,
     message: String,
-}
-
-struct JobMetadata {
-    id: i32,
-    guid: Uuid,
+    timezone: String,
 }
 
 struct GreetingJob {
@@ -61,23 +60,8 @@ pub async fn get_greetings(
     Ok(greeting_jobs)
 }
 
-/// Update database with the new `job_id/Uuid`.
-#[tracing::instrument(skip_all)]
-async fn update_job(data: JobMetadata, pool: PgPool) {
-    if let Err(e) = sqlx::query!(
-        "UPDATE jobs_cron set job_id=$1 WHERE id=$2",
-        data.guid,
-        data.id
-    )
-    .execute(&pool)
-    .await
-    {
-        tracing::error!(error = %e);
-    }
-}
-
 #[tracing::instrument(skip_all)]
-async fn send_greeting(bot: Bot, msg_id: i64, msg: String, sticker: String) {
+pub async fn send_greeting(bot: Bot, msg_id: i64, msg: String, sticker: String) {
     if let Err(e) = send_sticker(&bot, &ChatId(msg_id), sticker).await {
         tracing::error!(error = %e);
     };
@@ -99,8 +83,9 @@ async fn greeting(
     let jobs_in_db: Vec<Greeting> = sqlx::query_as!(
         Greeting,
         "
-        SELECT id, target, cron_str, message from jobs_cron
-        WHERE type = $1
+        SELECT j.id, j.target, j.cron_str, j.message, c.timezone
+        FROM jobs_cron j JOIN chatrooms c ON c.id = j.target
+        WHERE j.type = $1 AND j.cancelled = false AND j.paused = false
         ",
         job_type
     )
@@ -115,17 +100,15 @@ async fn greeting(
     };
 
     for cron_job in jobs_in_db {
-        let bot = bot.clone();
-        let sticker = sticker.to_owned();
-
-        // TODO: remove `chrono_tz` dependency
-        let job = Job::new_async_tz(cron_job.cron_str.as_str(), Tz::Singapore, move |_, _| {
-            let bot = bot.clone();
-            let sticker = sticker.clone();
-            let msg = cron_job.message.clone();
-
-            Box::pin(send_greeting(bot, cron_job.target, msg, sticker))
-        });
+        let job = greeting_job(
+            bot.clone(),
+            pool.clone(),
+            cron_job.target,
+            cron_job.message,
+            sticker.to_owned(),
+            &cron_job.cron_str,
+            parse_timezone(&cron_job.timezone),
+        );
 
         match job {
             Ok(x) => {
@@ -145,3 +128,178 @@ async fn greeting(
         metadata: job_metadata,
     })
 }
+
+/// Creates the cron job which sends the greeting to `target` chat,
+/// following the local time of `tz`.
+///
+/// the greeting is skipped during the quiet hours of the chat.
+pub fn greeting_job(
+    bot: Bot,
+    pool: PgPool,
+    target: i64,
+    message: String,
+    sticker: String,
+    cron_str: &str,
+    tz: Tz,
+) -> Result<Job, JobSchedulerError> {
+    Job::new_async_tz(cron_str, tz, move |_, _| {
+        let bot = bot.clone();
+        let pool = pool.clone();
+        let sticker = sticker.clone();
+        let msg = message.clone();
+
+        Box::pin(async move {
+            if is_quiet(&pool, target).await {
+                tracing::debug!("greeting skipped during quiet hours");
+                return;
+            }
+            send_greeting(bot, target, msg, sticker).await;
+        })
+    })
+}
+
+/// The greeting of a chat, as stored in `jobs_cron`.
+pub struct ChatGreeting {
+    pub cron_str: String,
+    pub message: String,
+    pub paused: bool,
+}
+
+/// Gets the greeting of `job_type` in the chat, the latest one if there are many.
+pub async fn chat_greeting(
+    pool: &PgPool,
+    chat_id: i64,
+    job_type: &CronJobType,
+) -> Result<Option<ChatGreeting>, sqlx::Error> {
+    sqlx::query_as!(
+        ChatGreeting,
+        "SELECT cron_str, message, paused FROM jobs_cron
+        WHERE target = $1 AND type = $2 AND cancelled = false
+        ORDER BY id DESC
+        LIMIT 1",
+        chat_id,
+        job_type.as_str()
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The values of a greeting to be saved.
+pub struct GreetingSchedule {
+    pub cron_str: String,
+    pub message: String,
+    pub paused: bool,
+}
+
+/// Saves the greeting of the chat, and replaces its job in the scheduler.
+///
+/// paused greetings are saved without a job.
+#[tracing::instrument(skip(bot, pool, sched, sticker, greeting))]
+pub async fn save_greeting(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    chat_id: i64,
+    job_type: &CronJobType,
+    sticker: String,
+    greeting: GreetingSchedule,
+) -> Result<(), CronJobError> {
+    let GreetingSchedule {
+        cron_str,
+        message,
+        paused,
+    } = greeting;
+    let tz = chat_timezone(pool, chat_id).await?;
+    let mut tx = pool.begin().await?;
+
+    let old = sqlx::query!(
+        "SELECT id, job_id FROM jobs_cron
+        WHERE target = $1 AND type = $2 AND cancelled = false
+        ORDER BY id DESC
+        LIMIT 1
+        FOR UPDATE",
+        chat_id,
+        job_type.as_str()
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let job = if paused {
+        None
+    } else {
+        Some(greeting_job(
+            bot.clone(),
+            pool.clone(),
+            chat_id,
+            message.clone(),
+            sticker,
+            &cron_str,
+            tz,
+        )?)
+    };
+    let job_id = job.as_ref().map(Job::guid);
+
+    if let Some(ref old) = old {
+        sqlx::query!(
+            "UPDATE jobs_cron SET job_id = $1, cron_str = $2, message = $3, paused = $4
+            WHERE id = $5",
+            job_id,
+            cron_str,
+            message,
+            paused,
+            old.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    } else {
+        sqlx::query!(
+            "INSERT INTO jobs_cron (target, job_id, type, cron_str, message, paused)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+            chat_id,
+            job_id,
+            job_type.as_str(),
+            cron_str,
+            message,
+            paused
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    // the jobs are swapped once the row is saved, so a failed commit leaves the old job running
+    if let Some(job) = job {
+        sched.add(job).await?;
+    }
+    if let Some(old_job_id) = old.and_then(|x| x.job_id) {
+        sched.remove(&old_job_id).await?;
+    }
+    Ok(())
+}
+
+/// Removes the greeting of the chat from database and the scheduler.
+///
+/// returns `false` when the chat has no such greeting.
+#[tracing::instrument(skip(pool, sched))]
+pub async fn remove_greeting(
+    pool: &PgPool,
+    sched: &JobScheduler,
+    chat_id: i64,
+    job_type: &CronJobType,
+) -> Result<bool, CronJobError> {
+    let job_ids = sqlx::query_scalar!(
+        "UPDATE jobs_cron SET cancelled = true
+        WHERE target = $1 AND type = $2 AND cancelled = false
+        RETURNING job_id",
+        chat_id,
+        job_type.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for job_id in job_ids.iter().flatten() {
+        sched.remove(job_id).await?;
+    }
+    Ok(!job_ids.is_empty())
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use sqlx::PgPool;
+use teloxide::{requests::Requester, types::ChatId, Bot};
+use time::{macros::format_description, Duration, OffsetDateTime};
+use tokio_cron_scheduler::JobScheduler;
+
+use crate::{
+    media::{preview_text, ReminderMedia},
+    timezone::{parse_timezone, to_local, DEFAULT_TIMEZONE},
+};
+
+use super::reminders::{defer_reminder, deliver_reminder, reminder_text, ReminderStatus};
+
+struct OverdueReminder {
+    id: i32,
+    target: i64,
+    message: String,
+    username: String,
+    due: OffsetDateTime,
+    media_type: Option<String>,
+    file_id: Option<String>,
+    timezone: String,
+}
+
+/// Sends the reminders which came due while the bot was down.
+///
+/// Reminders that are more than `grace` late are marked as missed instead,
+/// and each chat gets a summary of the reminders it missed. reminders of chats
+/// in their quiet hours wait until they are over, like the ones which come due on time.
+#[tracing::instrument(skip_all)]
+pub async fn deliver_overdue_reminders(
+    bot: Bot,
+    pool: PgPool,
+    sched: JobScheduler,
+    now: OffsetDateTime,
+    grace: Duration,
+) {
+    if let Err(e) = overdue_reminders(&bot, &pool, &sched, now, grace).await {
+        tracing::error!("error delivering overdue reminders: {e:#?}");
+    }
+}
+
+async fn overdue_reminders(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    now: OffsetDateTime,
+    grace: Duration,
+) -> anyhow::Result<()> {
+    let due_format = format_description!("[day] [month repr:short] [year] [hour]:[minute]");
+
+    let reminders: Vec<OverdueReminder> = sqlx::query_as!(
+        OverdueReminder,
+        r#"
+        SELECT j.id, j.target, j.message, j.username, j.due, j.media_type, j.file_id,
+        COALESCE(c.timezone, $3) as "timezone!"
+        FROM jobs_one_off j LEFT JOIN chatrooms c ON c.id = j.target
+        WHERE j.status = $1 AND j.due < $2
+        ORDER BY j.due"#,
+        ReminderStatus::Pending.as_str(),
+        now,
+        DEFAULT_TIMEZONE.name()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut missed: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+
+    for remind in reminders {
+        let delay = now - remind.due;
+        if delay > grace {
+            sqlx::query!(
+                "UPDATE jobs_one_off SET status = $1 WHERE id = $2",
+                ReminderStatus::Missed.as_str(),
+                remind.id
+            )
+            .execute(pool)
+            .await?;
+
+            let due = to_local(parse_timezone(&remind.timezone), remind.due).format(due_format)?;
+            let media = ReminderMedia::from_columns(remind.media_type.as_deref(), remind.file_id);
+            missed.entry(remind.target).or_default().push(format!(
+                "• {due} - {}",
+                preview_text(media.as_ref(), &remind.message)
+            ));
+            continue;
+        }
+
+        let deferred = defer_reminder(
+            bot,
+            pool,
+            sched,
+            remind.id,
+            remind.target,
+            &remind.username,
+            &remind.message,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("error deferring overdue reminder past quiet hours: {e:#?}");
+            false
+        });
+        if deferred {
+            continue;
+        }
+
+        let text = format!(
+            "{}\n\n(delayed by {})",
+            reminder_text(&remind.username, &remind.message),
+            describe_delay(delay)
+        );
+        deliver_reminder(bot, pool, remind.id, remind.target, text).await;
+    }
+
+    for (target, lines) in missed {
+        let text = format!(
+            "I was away and missed {} reminder(s) 😅 🐢\n\n{}",
+            lines.len(),
+            lines.join("\n")
+        );
+        if let Err(e) = bot.send_message(ChatId(target), text).await {
+            tracing::error!("error sending missed reminders summary {e:#?}");
+        }
+    }
+    Ok(())
+}
+
+fn describe_delay(delay: Duration) -> String {
+    let days = delay.whole_days();
+    let hours = delay.whole_hours() % 24;
+    let minutes = delay.whole_minutes() % 60;
+    match (days, hours, minutes) {
+        (0, 0, 0) => "less than a minute".to_string(),
+        (0, 0, m) => format!("{m}m"),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::describe_delay;
+
+    #[test]
+    fn delay_description() {
+        assert_eq!(describe_delay(Duration::seconds(42)), "less than a minute");
+        assert_eq!(describe_delay(Duration::minutes(15)), "15m");
+        assert_eq!(describe_delay(Duration::minutes(125)), "2h 5m");
+        assert_eq!(describe_delay(Duration::hours(26)), "1d 2h");
+    }
+}
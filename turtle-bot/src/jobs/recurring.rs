@@ -0,0 +1,210 @@
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use teloxide::{payloads::SendMessageSetters, requests::Requester, types::ChatId, Bot};
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+
+use crate::{
+    quiet_hours::is_quiet,
+    timezone::{chat_timezone, parse_timezone},
+};
+
+use super::{update_job, CronJobError, CronJobType, JobMetadata};
+
+struct RecurringReminder {
+    id: i32,
+    target: i64,
+    cron_str: String,
+    message: String,
+    username: Option<String>,
+    timezone: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_recurring_reminders(bot: &Bot, pool: &PgPool) -> Result<Vec<Job>, CronJobError> {
+    let reminders: Vec<RecurringReminder> = sqlx::query_as!(
+        RecurringReminder,
+        "
+        SELECT j.id, j.target, j.cron_str, j.message, j.username, c.timezone
+        FROM jobs_cron j JOIN chatrooms c ON c.id = j.target
+        WHERE j.type = $1 AND j.cancelled = false
+        ",
+        CronJobType::RecurringReminder.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut job_vec: Vec<Job> = Vec::new();
+
+    for remind in reminders {
+        let job = recurring_reminder_job(
+            bot.clone(),
+            pool.clone(),
+            remind.target,
+            remind.username,
+            remind.message,
+            &remind.cron_str,
+            parse_timezone(&remind.timezone),
+        );
+
+        match job {
+            Ok(x) => {
+                tokio::spawn(update_job(
+                    JobMetadata {
+                        id: remind.id,
+                        guid: x.guid(),
+                    },
+                    pool.clone(),
+                ));
+                job_vec.push(x);
+            }
+            Err(e) => tracing::error!(error = %e),
+        }
+    }
+
+    Ok(job_vec)
+}
+
+/// Creates the cron job which sends the reminder to `target` chat,
+/// following the local time of `tz`.
+///
+/// the reminder is sent without a notification during the quiet hours of the chat.
+pub fn recurring_reminder_job(
+    bot: Bot,
+    pool: PgPool,
+    target: i64,
+    username: Option<String>,
+    message: String,
+    cron_str: &str,
+    tz: Tz,
+) -> Result<Job, JobSchedulerError> {
+    let text = match username {
+        Some(username) => format!(
+            r"From: @{username}
+
+{message}"
+        ),
+        None => message,
+    };
+
+    Job::new_async_tz(cron_str, tz, move |_, _| {
+        let bot = bot.clone();
+        let pool = pool.clone();
+        let text = text.clone();
+        Box::pin(async move {
+            let silent = is_quiet(&pool, target).await;
+            if let Err(e) = bot
+                .send_message(ChatId(target), text)
+                .disable_notification(silent)
+                .await
+            {
+                tracing::error!("error sending recurring reminder {e:#?}");
+            }
+        })
+    })
+}
+
+/// Saves a new recurring reminder and schedules it.
+///
+/// returns the id of the reminder.
+#[tracing::instrument(skip(bot, pool, sched, message))]
+pub async fn add_recurring_reminder(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    target: i64,
+    username: Option<String>,
+    message: String,
+    cron_str: &str,
+) -> Result<i32, CronJobError> {
+    let tz = chat_timezone(pool, target).await?;
+    let job = recurring_reminder_job(
+        bot.clone(),
+        pool.clone(),
+        target,
+        username.clone(),
+        message.clone(),
+        cron_str,
+        tz,
+    )?;
+
+    let job_id = job.guid();
+    let id = sqlx::query_scalar!(
+        r#"INSERT INTO jobs_cron
+        (target, job_id, type, cron_str, message, username)
+        VALUES
+        ($1, $2, $3, $4, $5, $6)
+        RETURNING id"#,
+        target,
+        job_id,
+        CronJobType::RecurringReminder.as_str(),
+        cron_str,
+        message,
+        username
+    )
+    .fetch_one(pool)
+    .await?;
+    sched.add(job).await?;
+    Ok(id)
+}
+
+/// Swaps the scheduled job of a recurring reminder and updates its row.
+///
+/// returns `false` when `target` chat has no such reminder.
+#[tracing::instrument(skip(bot, pool, sched, message))]
+pub async fn update_recurring_reminder(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    target: i64,
+    id: i32,
+    cron_str: &str,
+    message: &str,
+) -> Result<bool, CronJobError> {
+    let tz = chat_timezone(pool, target).await?;
+    let mut tx = pool.begin().await?;
+
+    let record = sqlx::query!(
+        "SELECT job_id, username FROM jobs_cron
+        WHERE id = $1 AND target = $2 AND type = $3 AND cancelled = false
+        FOR UPDATE",
+        id,
+        target,
+        CronJobType::RecurringReminder.as_str()
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(record) = record else {
+        return Ok(false);
+    };
+
+    let job = recurring_reminder_job(
+        bot.clone(),
+        pool.clone(),
+        target,
+        record.username,
+        message.to_string(),
+        cron_str,
+        tz,
+    )?;
+    let job_id = job.guid();
+
+    sqlx::query!(
+        "UPDATE jobs_cron SET cron_str = $1, message = $2, job_id = $3 WHERE id = $4",
+        cron_str,
+        message,
+        job_id,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sched.add(job).await?;
+    if let Some(old_job_id) = record.job_id {
+        if let Err(e) = sched.remove(&old_job_id).await {
+            sched.remove(&job_id).await?;
+            return Err(e.into());
+        }
+    }
+    tx.commit().await?;
+    Ok(true)
+}
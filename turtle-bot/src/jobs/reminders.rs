@@ -1,14 +1,56 @@
 use std::time::Duration;
 
 use sqlx::PgPool;
-use teloxide::{requests::Requester, types::ChatId, Bot};
+use teloxide::{
+    types::{ChatId, UserId},
+    utils::html,
+    Bot,
+};
 use time::OffsetDateTime;
-use tokio_cron_scheduler::Job;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
 use uuid::Uuid;
 
-use super::CronJobError;
+use crate::{
+    callbacks::delivered_reminder_keyboard,
+    media::{send_with_media, ReminderMedia},
+    quiet_hours::{is_quiet, quiet_until},
+};
+
+use super::{warnings::schedule_warnings, CronJobError};
+
+/// number of times a reminder is sent before it is marked as failed.
+const MAX_ATTEMPTS: i32 = 4;
+
+/// The lifecycle of a one-off reminder, stored in the `status` column of `jobs_one_off`.
+///
+/// A reminder starts as `Pending`, and ends up in one of the other states.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReminderStatus {
+    Pending,
+    Sent,
+    /// telegram did not accept the reminder after [`MAX_ATTEMPTS`] tries.
+    Failed,
+    Cancelled,
+    /// the reminder came due while the bot was down, and is too late to be sent.
+    Missed,
+    /// the reminder was sent, and someone in the chat marked it as done.
+    Done,
+}
+
+impl ReminderStatus {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::Missed => "missed",
+            Self::Done => "done",
+        }
+    }
+}
 
-#[derive(Clone)]
 struct Reminder {
     id: i32,
     target: i64,
@@ -21,14 +63,21 @@ struct RemindMetadata {
     id: i32,
     job_id: Uuid,
 }
-pub async fn get_reminders(bot: &Bot, pool: &PgPool) -> Result<Vec<Job>, CronJobError> {
+/// Gets the pending reminders which are due from `now` onwards.
+pub async fn get_reminders(
+    bot: &Bot,
+    pool: &PgPool,
+    now: OffsetDateTime,
+) -> Result<Vec<Job>, CronJobError> {
     let reminders: Vec<Reminder> = sqlx::query_as!(
         Reminder,
         "
         SELECT id, target, message, username, due
-        FROM jobs_one_off WHERE completed = false 
+        FROM jobs_one_off WHERE status = $1
         AND due IS NOT NULL
-        AND due >= CURRENT_TIMESTAMP"
+        AND due >= $2",
+        ReminderStatus::Pending.as_str(),
+        now
     )
     .fetch_all(pool)
     .await?;
@@ -36,42 +85,19 @@ pub async fn get_reminders(bot: &Bot, pool: &PgPool) -> Result<Vec<Job>, CronJob
         return Ok(Vec::new());
     }
 
-    let now = OffsetDateTime::now_utc();
     let mut job_vec: Vec<Job> = Vec::new();
     let mut job_metadata: Vec<RemindMetadata> = Vec::new();
 
     for remind in reminders {
-        let time_delta_secs = (remind.due - now).whole_seconds();
-        let seconds = u64::from_le_bytes(time_delta_secs.to_le_bytes());
-
-        let remindx = remind.clone();
-        let botx = bot.clone();
-        let poolx = pool.clone();
-        let job = Job::new_one_shot_async(Duration::from_secs(seconds), move |_, _| {
-            let bot = botx.clone();
-            let remind = remindx.clone();
-            let pool = poolx.clone();
-            Box::pin(async move {
-                let text = format!(
-                    r"From: @{}
-
-{}",
-                    remind.username, remind.message
-                );
-                if let Err(e) = bot.send_message(ChatId(remind.target), text).await {
-                    tracing::error!("error sending one-off-job {e:#?}");
-                }
-
-                if let Err(e) = sqlx::query!(
-                    r#"UPDATE jobs_one_off SET completed = $1 WHERE target = $2 and due = $3 and username = $4 "#,
-                    true, remind.target, remind.due, remind.username)
-                .execute(&pool)
-                .await
-                {
-                    tracing::error!("error updating completed one_off_job in database: {e:#?}");
-                }
-            })
-        })?;
+        let job = one_off_reminder_job(
+            bot.clone(),
+            pool.clone(),
+            remind.id,
+            remind.target,
+            remind.username,
+            remind.message,
+            remind.due,
+        )?;
 
         let job_id = job.guid();
 
@@ -87,11 +113,372 @@ pub async fn get_reminders(bot: &Bot, pool: &PgPool) -> Result<Vec<Job>, CronJob
     }
     Ok(job_vec)
 }
+/// A one-off reminder which is not saved yet.
+pub struct NewReminder {
+    pub target: i64,
+    pub username: String,
+    pub message: String,
+    pub due: OffsetDateTime,
+    /// sent along with the message, which becomes its caption.
+    pub media: Option<ReminderMedia>,
+    /// the delivered reminder which was snoozed into this one.
+    pub snoozed_from: Option<i32>,
+    /// user ids of the members mentioned in the reminder.
+    pub assignees: Vec<i64>,
+    /// send the reminder privately to assignees who started the bot.
+    pub deliver_dm: bool,
+    /// minutes before `due` at which a heads-up is sent as well.
+    pub lead_times: Vec<i32>,
+    /// sent during the quiet hours of the chat, instead of waiting until they are over.
+    pub urgent: bool,
+}
+
+/// Saves a new pending one-off reminder and schedules it.
+///
+/// returns the id of the reminder.
+pub async fn add_one_off_reminder(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    reminder: NewReminder,
+) -> Result<i32, CronJobError> {
+    let NewReminder {
+        target,
+        username,
+        message,
+        due,
+        media,
+        snoozed_from,
+        assignees,
+        deliver_dm,
+        lead_times,
+        urgent,
+    } = reminder;
+
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query_scalar!(
+        r#"INSERT INTO jobs_one_off
+        (target, type, due, status, message, username, snoozed_from, deliver_dm, media_type, file_id, urgent)
+        VALUES
+        ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id"#,
+        target,
+        "normal",
+        due,
+        ReminderStatus::Pending.as_str(),
+        message,
+        username,
+        snoozed_from,
+        deliver_dm,
+        media.as_ref().map(|x| x.kind.as_str()),
+        media.map(|x| x.file_id),
+        urgent
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO jobs_one_off_assignees (reminder_id, user_id)
+        SELECT $1, * FROM UNNEST($2::BIGINT[])",
+        id,
+        &assignees
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO jobs_one_off_warnings (reminder_id, lead_minutes)
+        SELECT $1, * FROM UNNEST($2::INTEGER[])",
+        id,
+        &lead_times
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let job = one_off_reminder_job(
+        bot.clone(),
+        pool.clone(),
+        id,
+        target,
+        username,
+        message,
+        due,
+    )?;
+    let job_id = job.guid();
+    sched.add(job).await?;
+    update_job(RemindMetadata { id, job_id }, pool.clone()).await;
+    schedule_warnings(bot, pool, sched, Some(id)).await?;
+    Ok(id)
+}
+
+/// Creates the one-shot job which sends the reminder to `target` chat when it is due.
+///
+/// reminders which are not urgent wait until the quiet hours of the chat are over.
+pub fn one_off_reminder_job(
+    bot: Bot,
+    pool: PgPool,
+    id: i32,
+    target: i64,
+    username: String,
+    message: String,
+    due: OffsetDateTime,
+) -> Result<Job, JobSchedulerError> {
+    // rounded up to at least a second, as a reminder deferred to the end of the quiet hours
+    // would otherwise run within them, and be deferred to the same instant over and over
+    let delta = due - OffsetDateTime::now_utc();
+    let time_delta_secs = delta.whole_seconds() + i64::from(delta.subsec_nanoseconds() > 0);
+    let seconds = u64::try_from(time_delta_secs).unwrap_or_default().max(1);
+
+    Job::new_one_shot_async(Duration::from_secs(seconds), move |_, sched| {
+        let bot = bot.clone();
+        let pool = pool.clone();
+        let username = username.clone();
+        let message = message.clone();
+        Box::pin(async move {
+            let deferred = defer_reminder(&bot, &pool, &sched, id, target, &username, &message)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("error deferring reminder past quiet hours: {e:#?}");
+                    false
+                });
+            if !deferred {
+                let text = reminder_text(&username, &message);
+                deliver_reminder(&bot, &pool, id, target, text).await;
+            }
+        })
+    })
+}
+
+/// Moves the reminder to the end of the quiet hours of its chat, when it comes due within them.
+///
+/// returns `false` when the reminder is to be sent now, which urgent reminders always are.
+pub(super) async fn defer_reminder(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    id: i32,
+    target: i64,
+    username: &str,
+    message: &str,
+) -> Result<bool, CronJobError> {
+    let urgent = sqlx::query_scalar!("SELECT urgent FROM jobs_one_off WHERE id = $1", id)
+        .fetch_one(pool)
+        .await?;
+    if urgent {
+        return Ok(false);
+    }
+    let Some(due) = quiet_until(pool, target, OffsetDateTime::now_utc()).await? else {
+        return Ok(false);
+    };
+
+    let job = one_off_reminder_job(
+        bot.clone(),
+        pool.clone(),
+        id,
+        target,
+        username.to_string(),
+        message.to_string(),
+        due,
+    )?;
+    let job_id = job.guid();
+    sqlx::query!(
+        "UPDATE jobs_one_off SET due = $1, job_id = $2 WHERE id = $3",
+        due,
+        job_id,
+        id
+    )
+    .execute(pool)
+    .await?;
+    sched.add(job).await?;
+    tracing::debug!("reminder deferred to {due} for quiet hours");
+    Ok(true)
+}
+
+pub fn reminder_text(username: &str, message: &str) -> String {
+    format!(
+        r"From: @{username}
+
+{message}"
+    )
+}
+
+struct ReminderAssignee {
+    user_id: i64,
+    username: Option<String>,
+    first_name: Option<String>,
+    can_dm: bool,
+}
+
+impl ReminderAssignee {
+    fn mention(&self) -> String {
+        match (&self.username, &self.first_name) {
+            (Some(username), _) => format!("@{}", html::escape(username)),
+            (None, first_name) => html::user_mention(
+                UserId(u64::from_le_bytes(self.user_id.to_le_bytes())),
+                first_name.as_deref().unwrap_or("member"),
+            ),
+        }
+    }
+}
+
+/// What is needed to deliver a reminder, besides its text.
+#[derive(Default)]
+struct Delivery {
+    media: Option<ReminderMedia>,
+    deliver_dm: bool,
+    assignees: Vec<ReminderAssignee>,
+}
+
+async fn reminder_delivery(pool: &PgPool, id: i32) -> Result<Delivery, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT deliver_dm, media_type, file_id FROM jobs_one_off WHERE id = $1",
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    // a user can be messaged privately once they started a chat with the bot.
+    let assignees = sqlx::query_as!(
+        ReminderAssignee,
+        r#"
+        SELECT a.user_id, m.username, m.first_name AS "first_name?",
+        EXISTS (
+            SELECT 1 FROM chatrooms c
+            WHERE c.id = a.user_id AND c.is_group = false AND c.left_at IS NULL
+        ) AS "can_dm!"
+        FROM jobs_one_off_assignees a
+        JOIN jobs_one_off j ON j.id = a.reminder_id
+        LEFT JOIN chat_members m ON m.chat_id = j.target AND m.user_id = a.user_id
+        WHERE a.reminder_id = $1
+        ORDER BY a.user_id"#,
+        id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(Delivery {
+        media: ReminderMedia::from_columns(record.media_type.as_deref(), record.file_id),
+        deliver_dm: record.deliver_dm,
+        assignees,
+    })
+}
+
+/// Sends the reminder to the assignees who asked for it by DM, and mentions
+/// the other assignees in the group chat.
+///
+/// returns the text for the group chat, or `None` when every assignee got it by DM.
+async fn notify_assignees(
+    bot: &Bot,
+    delivery: &Delivery,
+    text: String,
+    silent: bool,
+) -> Option<String> {
+    if delivery.assignees.is_empty() {
+        return Some(text);
+    }
+
+    let mut mentions = Vec::new();
+    for assignee in &delivery.assignees {
+        if delivery.deliver_dm && assignee.can_dm {
+            match send_with_media(
+                bot,
+                ChatId(assignee.user_id),
+                text.clone(),
+                delivery.media.as_ref(),
+                None,
+                silent,
+                &mut None,
+            )
+            .await
+            {
+                Ok(_) => continue,
+                Err(e) => tracing::error!("error sending reminder by DM: {e:#?}"),
+            }
+        }
+        mentions.push(assignee.mention());
+    }
+
+    if mentions.is_empty() {
+        return None;
+    }
+    Some(format!("{text}\n\nFor: {}", mentions.join(", ")))
+}
+
+/// Sends the reminder, retrying with backoff when telegram fails,
+/// and records the outcome in its row.
+///
+/// reminders sent during the quiet hours of the chat don't make a sound.
+#[tracing::instrument(skip(bot, pool, text))]
+pub async fn deliver_reminder(bot: &Bot, pool: &PgPool, id: i32, target: i64, text: String) {
+    let text = html::escape(&text);
+    let delivery = reminder_delivery(pool, id).await.unwrap_or_else(|e| {
+        tracing::error!("error getting reminder delivery details: {e:#?}");
+        Delivery::default()
+    });
+    let silent = is_quiet(pool, target).await;
+    let Some(text) = notify_assignees(bot, &delivery, text, silent).await else {
+        set_status(pool, id, ReminderStatus::Sent, 1).await;
+        return;
+    };
+
+    let mut attempts = 0;
+    let mut media_sent = None;
+    let status = loop {
+        attempts += 1;
+        let Err(e) = send_with_media(
+            bot,
+            ChatId(target),
+            text.clone(),
+            delivery.media.as_ref(),
+            Some(delivered_reminder_keyboard(id)),
+            silent,
+            &mut media_sent,
+        )
+        .await
+        else {
+            break ReminderStatus::Sent;
+        };
+        tracing::error!("error sending one-off-job (attempt {attempts}): {e:#?}");
+
+        if let Err(e) = sqlx::query!(
+            "UPDATE jobs_one_off SET attempts = $1, last_error = $2 WHERE id = $3",
+            attempts,
+            e.to_string(),
+            id
+        )
+        .execute(pool)
+        .await
+        {
+            tracing::error!("error recording failed one_off_job attempt: {e:#?}");
+        }
+
+        if attempts >= MAX_ATTEMPTS {
+            break ReminderStatus::Failed;
+        }
+        tokio::time::sleep(Duration::from_secs(1 << attempts)).await;
+    };
+    set_status(pool, id, status, attempts).await;
+}
+
+/// records the outcome of the delivery, unless the reminder was cancelled meanwhile.
+async fn set_status(pool: &PgPool, id: i32, status: ReminderStatus, attempts: i32) {
+    if let Err(e) = sqlx::query!(
+        "UPDATE jobs_one_off SET status = $1, attempts = $2 WHERE id = $3 AND status = $4",
+        status.as_str(),
+        attempts,
+        id,
+        ReminderStatus::Pending.as_str()
+    )
+    .execute(pool)
+    .await
+    {
+        tracing::error!("error updating status of one_off_job in database: {e:#?}");
+    }
+}
+
 /// Update database with the new `job_id/Uuid`.
 #[tracing::instrument(skip_all)]
 async fn update_job(data: RemindMetadata, pool: PgPool) {
     if let Err(e) = sqlx::query!(
-        "UPDATE jobs_cron set job_id=$1 WHERE id=$2",
+        "UPDATE jobs_one_off set job_id=$1 WHERE id=$2",
         data.job_id,
         data.id
     )
@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use sqlx::{PgConnection, PgPool};
+use teloxide::{payloads::SendMessageSetters, requests::Requester, types::ChatId, Bot};
+use time::OffsetDateTime;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use uuid::Uuid;
+
+use crate::{
+    media::{preview_text, ReminderMedia},
+    quiet_hours::is_quiet,
+};
+
+use super::{
+    reminders::{reminder_text, ReminderStatus},
+    CronJobError,
+};
+
+const MINUTES_PER_HOUR: i32 = 60;
+const MINUTES_PER_DAY: i32 = 24 * MINUTES_PER_HOUR;
+const MINUTES_PER_WEEK: i32 = 7 * MINUTES_PER_DAY;
+
+/// An advance warning of a pending one-off reminder.
+struct Warning {
+    reminder_id: i32,
+    lead_minutes: i32,
+    target: i64,
+    username: String,
+    message: String,
+    due: OffsetDateTime,
+    media_type: Option<String>,
+    file_id: Option<String>,
+}
+
+/// how long before the reminder the warning is sent, eg. `1 day` or `15 min`.
+#[must_use]
+pub fn lead_time_label(minutes: i32) -> String {
+    let (amount, unit) = if minutes % MINUTES_PER_WEEK == 0 {
+        (minutes / MINUTES_PER_WEEK, "week")
+    } else if minutes % MINUTES_PER_DAY == 0 {
+        (minutes / MINUTES_PER_DAY, "day")
+    } else if minutes % MINUTES_PER_HOUR == 0 {
+        (minutes / MINUTES_PER_HOUR, "hour")
+    } else {
+        return format!("{minutes} min");
+    };
+    if amount == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{amount} {unit}s")
+    }
+}
+
+/// the warnings of pending reminders which are still ahead of `now`.
+///
+/// all pending reminders are included when `reminder_id` is `None`.
+async fn pending_warnings(
+    pool: &PgPool,
+    reminder_id: Option<i32>,
+    now: OffsetDateTime,
+) -> Result<Vec<Warning>, sqlx::Error> {
+    sqlx::query_as!(
+        Warning,
+        "SELECT w.reminder_id, w.lead_minutes, j.target, j.username, j.message, j.due,
+        j.media_type, j.file_id
+        FROM jobs_one_off_warnings w JOIN jobs_one_off j ON j.id = w.reminder_id
+        WHERE j.status = $1 AND ($2::INTEGER IS NULL OR j.id = $2)
+        AND j.due - make_interval(mins => w.lead_minutes) > $3",
+        ReminderStatus::Pending.as_str(),
+        reminder_id,
+        now
+    )
+    .fetch_all(pool)
+    .await
+}
+
+fn warning_job(bot: Bot, pool: PgPool, warning: Warning) -> Result<Job, JobSchedulerError> {
+    let send_at = warning.due - time::Duration::minutes(warning.lead_minutes.into());
+    let seconds = (send_at - OffsetDateTime::now_utc()).whole_seconds();
+
+    let media = ReminderMedia::from_columns(warning.media_type.as_deref(), warning.file_id);
+    let text = format!(
+        "⏰ Heads up, this is due in {}:\n\n{}",
+        lead_time_label(warning.lead_minutes),
+        reminder_text(
+            &warning.username,
+            &preview_text(media.as_ref(), &warning.message)
+        )
+    );
+    let target = warning.target;
+
+    Job::new_one_shot_async(
+        Duration::from_secs(u64::try_from(seconds).unwrap_or_default()),
+        move |job_id, _| {
+            let bot = bot.clone();
+            let pool = pool.clone();
+            let text = text.clone();
+            Box::pin(async move {
+                let silent = is_quiet(&pool, target).await;
+                if let Err(e) = bot
+                    .send_message(ChatId(target), text)
+                    .disable_notification(silent)
+                    .await
+                {
+                    tracing::error!("error sending reminder warning: {e:#?}");
+                }
+                if let Err(e) = sqlx::query!(
+                    "UPDATE jobs_one_off_warnings SET job_id = NULL WHERE job_id = $1",
+                    job_id
+                )
+                .execute(&pool)
+                .await
+                {
+                    tracing::error!("error clearing job id of sent warning: {e:#?}");
+                }
+            })
+        },
+    )
+}
+
+/// Schedules the advance warnings of a pending one-off reminder which are still ahead.
+///
+/// the warnings of all pending reminders are scheduled when `reminder_id` is `None`.
+#[tracing::instrument(skip(bot, pool, sched))]
+pub async fn schedule_warnings(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    reminder_id: Option<i32>,
+) -> Result<(), CronJobError> {
+    for warning in pending_warnings(pool, reminder_id, OffsetDateTime::now_utc()).await? {
+        let (id, lead_minutes) = (warning.reminder_id, warning.lead_minutes);
+        let job = warning_job(bot.clone(), pool.clone(), warning)?;
+        let job_id = job.guid();
+        sched.add(job).await?;
+        sqlx::query!(
+            "UPDATE jobs_one_off_warnings SET job_id = $1
+            WHERE reminder_id = $2 AND lead_minutes = $3",
+            job_id,
+            id,
+            lead_minutes
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Removes the scheduled advance warnings of a one-off reminder.
+///
+/// the lead times are kept, so that the warnings can be scheduled again.
+#[tracing::instrument(skip(conn, sched))]
+pub async fn cancel_warnings(
+    conn: &mut PgConnection,
+    sched: &JobScheduler,
+    reminder_id: i32,
+) -> Result<(), CronJobError> {
+    let job_ids: Vec<Uuid> = sqlx::query_scalar!(
+        r#"UPDATE jobs_one_off_warnings SET job_id = NULL
+        WHERE reminder_id = $1 AND job_id IS NOT NULL
+        RETURNING job_id AS "job_id!""#,
+        reminder_id
+    )
+    .fetch_all(conn)
+    .await?;
+
+    for job_id in job_ids {
+        sched.remove(&job_id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lead_time_label;
+
+    #[test]
+    fn lead_time_labels() {
+        assert_eq!(lead_time_label(15), "15 min");
+        assert_eq!(lead_time_label(90), "90 min");
+        assert_eq!(lead_time_label(60), "1 hour");
+        assert_eq!(lead_time_label(180), "3 hours");
+        assert_eq!(lead_time_label(1440), "1 day");
+        assert_eq!(lead_time_label(10080), "1 week");
+    }
+}
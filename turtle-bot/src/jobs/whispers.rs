@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use teloxide::{
+    payloads::SendMessageSetters,
+    requests::Requester,
+    types::{ChatId, InputFile, InputMedia, InputMediaPhoto, ParseMode},
+    Bot, RequestError,
+};
+use time::OffsetDateTime;
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use uuid::Uuid;
+
+use super::{reminders::ReminderStatus, CronJobError};
+
+/// A message from the web app which is sent to a chat later on.
+pub struct NewWhisper {
+    pub user_id: Uuid,
+    pub chat_id: i64,
+    /// the text of the message, or the caption of the photos.
+    pub message: Option<String>,
+    /// sent as an album, when there are any.
+    pub photos: Vec<Vec<u8>>,
+    pub send_at: OffsetDateTime,
+}
+
+struct Whisper {
+    telegram_chat_id: i64,
+    message: Option<String>,
+    photos: Vec<Vec<u8>>,
+}
+
+struct PendingWhisper {
+    id: i32,
+    send_at: OffsetDateTime,
+}
+
+/// Gets the jobs of the scheduled whispers which are still pending.
+///
+/// whispers that are more than `grace` late are marked as missed, and the
+/// others which are late are sent straight away.
+#[tracing::instrument(skip(bot, pool))]
+pub async fn get_scheduled_whispers(
+    bot: &Bot,
+    pool: &PgPool,
+    now: OffsetDateTime,
+    grace: time::Duration,
+) -> Result<Vec<Job>, CronJobError> {
+    sqlx::query!(
+        "UPDATE telegram_scheduled_whispers SET status = $1
+        WHERE status = $2 AND send_at < $3",
+        ReminderStatus::Missed.as_str(),
+        ReminderStatus::Pending.as_str(),
+        now - grace
+    )
+    .execute(pool)
+    .await?;
+
+    let whispers = sqlx::query_as!(
+        PendingWhisper,
+        "SELECT id, send_at FROM telegram_scheduled_whispers WHERE status = $1",
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut job_vec = Vec::new();
+    for whisper in whispers {
+        let job = whisper_job(bot.clone(), pool.clone(), whisper.id, whisper.send_at)?;
+        set_job_id(pool, whisper.id, job.guid()).await?;
+        job_vec.push(job);
+    }
+    Ok(job_vec)
+}
+
+/// Saves a whisper to be sent at `send_at`, and schedules it.
+///
+/// returns the id of the whisper.
+#[tracing::instrument(skip(bot, pool, sched, whisper))]
+pub async fn add_scheduled_whisper(
+    bot: &Bot,
+    pool: &PgPool,
+    sched: &JobScheduler,
+    whisper: NewWhisper,
+) -> Result<i32, CronJobError> {
+    let id = sqlx::query_scalar!(
+        "INSERT INTO telegram_scheduled_whispers
+        (user_id, telegram_chat_id, message, photos, send_at, status, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id",
+        whisper.user_id,
+        whisper.chat_id,
+        whisper.message,
+        &whisper.photos,
+        whisper.send_at,
+        ReminderStatus::Pending.as_str(),
+        OffsetDateTime::now_utc()
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let job = whisper_job(bot.clone(), pool.clone(), id, whisper.send_at)?;
+    let job_id = job.guid();
+    sched.add(job).await?;
+    set_job_id(pool, id, job_id).await?;
+    Ok(id)
+}
+
+/// Cancels a pending whisper of the user, and removes its job.
+///
+/// returns `false` when the user has no such pending whisper.
+#[tracing::instrument(skip(pool, sched))]
+pub async fn cancel_scheduled_whisper(
+    pool: &PgPool,
+    sched: &JobScheduler,
+    user_id: Uuid,
+    id: i32,
+) -> Result<bool, CronJobError> {
+    let record = sqlx::query!(
+        "UPDATE telegram_scheduled_whispers SET status = $1
+        WHERE id = $2 AND user_id = $3 AND status = $4
+        RETURNING job_id",
+        ReminderStatus::Cancelled.as_str(),
+        id,
+        user_id,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(record) = record else {
+        return Ok(false);
+    };
+    if let Some(job_id) = record.job_id {
+        sched.remove(&job_id).await?;
+    }
+    Ok(true)
+}
+
+async fn set_job_id(pool: &PgPool, id: i32, job_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE telegram_scheduled_whispers SET job_id = $1 WHERE id = $2",
+        job_id,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+fn whisper_job(
+    bot: Bot,
+    pool: PgPool,
+    id: i32,
+    send_at: OffsetDateTime,
+) -> Result<Job, JobSchedulerError> {
+    let seconds = (send_at - OffsetDateTime::now_utc()).whole_seconds();
+
+    Job::new_one_shot_async(
+        Duration::from_secs(u64::try_from(seconds).unwrap_or_default()),
+        move |_, _| {
+            let bot = bot.clone();
+            let pool = pool.clone();
+            Box::pin(async move {
+                deliver_whisper(&bot, &pool, id).await;
+            })
+        },
+    )
+}
+
+/// Sends the whisper, unless it was cancelled in the meantime.
+#[tracing::instrument(skip(bot, pool))]
+async fn deliver_whisper(bot: &Bot, pool: &PgPool, id: i32) {
+    // marked as sent before sending, so that it can't be cancelled halfway
+    let whisper = sqlx::query_as!(
+        Whisper,
+        "UPDATE telegram_scheduled_whispers SET status = $1
+        WHERE id = $2 AND status = $3
+        RETURNING telegram_chat_id, message, photos",
+        ReminderStatus::Sent.as_str(),
+        id,
+        ReminderStatus::Pending.as_str()
+    )
+    .fetch_optional(pool)
+    .await;
+
+    let whisper = match whisper {
+        Ok(Some(x)) => x,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("error getting scheduled whisper: {e:#?}");
+            return;
+        }
+    };
+
+    let Err(e) = send_whisper(bot, whisper).await else {
+        return;
+    };
+    tracing::error!("error sending scheduled whisper: {e:#?}");
+    if let Err(e) = sqlx::query!(
+        "UPDATE telegram_scheduled_whispers SET status = $1, last_error = $2 WHERE id = $3",
+        ReminderStatus::Failed.as_str(),
+        e.to_string(),
+        id
+    )
+    .execute(pool)
+    .await
+    {
+        tracing::error!("error recording failed scheduled whisper: {e:#?}");
+    }
+}
+
+/// sends the message the way the web app does, in markdown, or as an album
+/// of photos with the message as its caption.
+#[allow(deprecated)]
+async fn send_whisper(bot: &Bot, whisper: Whisper) -> Result<(), RequestError> {
+    let Whisper {
+        telegram_chat_id,
+        message,
+        photos,
+    } = whisper;
+    let chat_id = ChatId(telegram_chat_id);
+
+    if photos.is_empty() {
+        bot.send_message(chat_id, message.unwrap_or_default())
+            .parse_mode(ParseMode::Markdown)
+            .await?;
+        return Ok(());
+    }
+
+    let mut caption = message;
+    let media: Vec<InputMedia> = photos
+        .into_iter()
+        .map(|x| {
+            let photo = InputMediaPhoto::new(InputFile::memory(x));
+            InputMedia::Photo(match caption.take() {
+                Some(caption) => photo.caption(caption),
+                None => photo,
+            })
+        })
+        .collect();
+    bot.send_media_group(chat_id, media).await?;
+    Ok(())
+}
@@ -3,29 +3,59 @@ mod callbacks;
 mod chat;
 pub mod chatroom;
 mod commands;
+mod greeting;
 mod handlers;
+mod ical;
 mod jobs;
+mod llm;
+mod llm_config;
+mod media;
 mod member;
+mod persona;
+mod quiet_hours;
 mod sticker;
+mod storage;
+mod timezone;
+mod wizard;
 
 use anyhow::Context;
-use async_openai::Client;
 use bot::{bot_handler, init_bot_details, ChatState};
-use callbacks::CallbackPage;
 use gaia::{app::AppSettings, environment::Environment, Settings};
-use jobs::init_scheduler;
+use llm::chat_provider;
 use sqlx::PgPool;
+use storage::BotStorage;
 use teloxide::{
-    dispatching::{dialogue::InMemStorage, Dispatcher},
+    dispatching::Dispatcher,
     dptree,
     error_handlers::LoggingErrorHandler,
     update_listeners::webhooks::{self, Options},
     Bot,
 };
+use time::Duration;
+use tokio_cron_scheduler::JobScheduler;
+use wizard::{expire_wizards, WizardStorage};
 
+pub use callbacks::{cancel_reminder, parse_cron, reschedule_reminder, ReminderKind};
+pub use ical::{calendar_events, to_ics};
+pub use jobs::{
+    add_one_off_reminder, add_recurring_reminder, add_scheduled_whisper, cancel_scheduled_whisper,
+    init_scheduler, update_recurring_reminder, NewReminder, NewWhisper, ReminderStatus,
+};
+pub use media::ReminderMedia;
+
+/// `sched` is shared with the web app, so that reminders changed there are
+/// rescheduled straight away.
 #[tracing::instrument(skip_all, name = "turtle bot")]
-pub async fn start_bot(tele_bot: Bot, env: Environment, settings: Settings, pool: PgPool) {
-    let chatgpt = Client::new();
+pub async fn start_bot(
+    tele_bot: Bot,
+    env: Environment,
+    settings: Settings,
+    pool: PgPool,
+    sched: JobScheduler,
+) {
+    let llm = chat_provider(&settings.llm)
+        .map_err(|e| tracing::error!("{e:#?}"))
+        .expect("unable to set up llm provider");
 
     let options = get_webhook_options(&settings.application, &env);
 
@@ -34,22 +64,27 @@ pub async fn start_bot(tele_bot: Bot, env: Environment, settings: Settings, pool
         .map_err(|e| tracing::error!("{e:#?}"))
         .expect("unable to get listener");
 
-    let sched = init_scheduler(&tele_bot, &settings.stickers, &pool)
-        .await
-        .expect("cannot initialize scheduler");
-
     init_bot_details(&tele_bot).await;
 
     let handler = bot_handler();
+    let storage = settings.application.dialogue_storage;
+    let chat_states = BotStorage::<ChatState>::new(storage, &pool, "chat-state");
+    let wizards = WizardStorage::new(storage, &pool);
+    tokio::spawn(expire_wizards(
+        tele_bot.clone(),
+        wizards.clone(),
+        Duration::minutes(settings.application.wizard_ttl_minutes.into()),
+    ));
 
     Box::pin(
         Dispatcher::builder(tele_bot, handler)
             .dependencies(dptree::deps![
                 settings.stickers,
-                chatgpt,
+                llm,
+                settings.llm,
                 pool,
-                InMemStorage::<ChatState>::new(),
-                InMemStorage::<CallbackPage>::new(),
+                chat_states,
+                wizards,
                 sched
             ])
             .enable_ctrlc_handler()
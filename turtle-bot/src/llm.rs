@@ -0,0 +1,242 @@
+//! the language models which the bot chats through, as picked in the settings
+
+use std::sync::Arc;
+
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequestArgs,
+    },
+    Client,
+};
+use async_trait::async_trait;
+use gaia::llm::{LlmProvider, LlmSettings};
+use secrecy::ExposeSecret;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LlmError {
+    #[error(transparent)]
+    OpenAIError(#[from] OpenAIError),
+
+    #[error("no chat completion choices available.")]
+    NoChatCompletion,
+
+    #[error("bot did not respond")]
+    NoContent,
+
+    #[error("the compatible llm provider needs a base_url.")]
+    NoBaseUrl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+}
+
+impl TryFrom<&str> for ChatRole {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "system" => Ok(Self::System),
+            "user" => Ok(Self::User),
+            "assistant" => Ok(Self::Assistant),
+            unknown => Err(format!("{unknown} is not a supported chat role")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    /// username of the user, if any
+    pub name: Option<String>,
+    pub content: String,
+}
+
+/// How the model is asked for a reply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatOptions {
+    pub model: String,
+    pub temperature: f32,
+    /// number of tokens from the model's response
+    pub max_tokens: u16,
+}
+
+/// A language model which the bot chats through.
+#[async_trait]
+pub trait ChatProvider: Send + Sync {
+    /// the reply of the model to the conversation so far.
+    async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        options: &ChatOptions,
+    ) -> Result<String, LlmError>;
+}
+
+pub type Llm = Arc<dyn ChatProvider>;
+
+/// the provider picked in `settings`.
+pub fn chat_provider(settings: &LlmSettings) -> Result<Llm, LlmError> {
+    let provider: Llm = match settings.provider {
+        LlmProvider::OpenAI => Arc::new(OpenAIProvider::new(Client::new())),
+        LlmProvider::Compatible => {
+            let base_url = settings.base_url.as_ref().ok_or(LlmError::NoBaseUrl)?;
+            let mut config = OpenAIConfig::new().with_api_base(base_url);
+            if let Some(api_key) = &settings.api_key {
+                config = config.with_api_key(api_key.expose_secret());
+            }
+            Arc::new(OpenAIProvider::new(Client::with_config(config)))
+        }
+        LlmProvider::Mock => Arc::new(MockProvider),
+    };
+    Ok(provider)
+}
+
+/// openai, or any server speaking its api.
+pub struct OpenAIProvider {
+    client: Client<OpenAIConfig>,
+}
+
+impl OpenAIProvider {
+    #[must_use]
+    pub fn new(client: Client<OpenAIConfig>) -> Self {
+        Self { client }
+    }
+}
+
+fn to_openai(msg: ChatMessage) -> Result<ChatCompletionRequestMessage, OpenAIError> {
+    let request = match msg.role {
+        ChatRole::System => ChatCompletionRequestSystemMessageArgs::default()
+            .content(msg.content)
+            .build()?
+            .into(),
+        ChatRole::User => {
+            let mut args = ChatCompletionRequestUserMessageArgs::default();
+            args.content(msg.content);
+            if let Some(name) = msg.name {
+                args.name(name);
+            }
+            args.build()?.into()
+        }
+        ChatRole::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+            .content(msg.content)
+            .build()?
+            .into(),
+    };
+    Ok(request)
+}
+
+#[async_trait]
+impl ChatProvider for OpenAIProvider {
+    #[tracing::instrument(skip_all)]
+    #[allow(deprecated)]
+    async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        options: &ChatOptions,
+    ) -> Result<String, LlmError> {
+        let messages = messages
+            .into_iter()
+            .map(to_openai)
+            .collect::<Result<Vec<_>, _>>()?;
+        tracing::debug!("chat_cmp_msg is {messages:#?}");
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .max_tokens(options.max_tokens)
+            .temperature(options.temperature)
+            .model(&options.model)
+            .messages(messages)
+            .build()?;
+
+        let response = self.client.chat().create(request).await?;
+
+        let reply = response
+            .choices
+            .first()
+            .ok_or(LlmError::NoChatCompletion)?
+            .message
+            .content
+            .as_ref()
+            .ok_or(LlmError::NoContent)?
+            .to_owned();
+        Ok(reply)
+    }
+}
+
+/// Echoes the last message of the user, so that the chat can be tried out
+/// without a model.
+pub struct MockProvider;
+
+#[async_trait]
+impl ChatProvider for MockProvider {
+    async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        _options: &ChatOptions,
+    ) -> Result<String, LlmError> {
+        let last = messages
+            .into_iter()
+            .rev()
+            .find(|x| x.role == ChatRole::User)
+            .ok_or(LlmError::NoContent)?;
+        Ok(format!("🐢 {}", last.content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChatMessage, ChatOptions, ChatProvider, ChatRole, MockProvider};
+
+    fn message(role: ChatRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            name: None,
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn chat_role_str() {
+        for role in [ChatRole::System, ChatRole::User, ChatRole::Assistant] {
+            assert_eq!(ChatRole::try_from(role.as_str()), Ok(role));
+        }
+        assert_eq!(ChatRole::try_from(" User "), Ok(ChatRole::User));
+        assert!(ChatRole::try_from("tool").is_err());
+    }
+
+    #[tokio::test]
+    async fn mock_echoes_last_user_message() {
+        let options = ChatOptions {
+            model: "mock".to_string(),
+            temperature: 1.0,
+            max_tokens: 512,
+        };
+        let messages = vec![
+            message(ChatRole::System, "you are a turtle"),
+            message(ChatRole::User, "hello"),
+            message(ChatRole::Assistant, "🐢 hello"),
+            message(ChatRole::User, "how old are you?"),
+        ];
+        let reply = MockProvider.complete(messages, &options).await.unwrap();
+        assert_eq!(reply, "🐢 how old are you?");
+
+        let messages = vec![message(ChatRole::System, "you are a turtle")];
+        assert!(MockProvider.complete(messages, &options).await.is_err());
+    }
+}
@@ -0,0 +1,426 @@
+//! for changing how the bot chats in each chat, managed with `/llmconfig`
+//!
+//! chats start with the `llm` settings, and may override any of them.
+
+use gaia::llm::LlmSettings;
+use sqlx::PgPool;
+use teloxide::{requests::Requester, types::Message, Bot};
+use time::Duration;
+
+use crate::{chatroom::is_chat_admin, llm::ChatOptions};
+
+const MAX_TEMPERATURE: f32 = 2.0;
+
+const USAGE: &str = r"Change how I chat in this chat 🐢
+
+/llmconfig model gpt-4o-mini
+/llmconfig temperature 0.3
+/llmconfig max_tokens 256
+/llmconfig history_count 10
+/llmconfig history_minutes 30
+/llmconfig temperature default
+/llmconfig reset
+
+Only admins can change these.";
+
+/// The llm settings which a chat can override.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LlmSetting {
+    Model,
+    Temperature,
+    MaxTokens,
+    HistoryCount,
+    HistoryMinutes,
+}
+
+impl LlmSetting {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Model => "model",
+            Self::Temperature => "temperature",
+            Self::MaxTokens => "max_tokens",
+            Self::HistoryCount => "history_count",
+            Self::HistoryMinutes => "history_minutes",
+        }
+    }
+}
+
+impl TryFrom<&str> for LlmSetting {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "model" => Ok(Self::Model),
+            "temperature" => Ok(Self::Temperature),
+            "max_tokens" => Ok(Self::MaxTokens),
+            "history_count" => Ok(Self::HistoryCount),
+            "history_minutes" => Ok(Self::HistoryMinutes),
+            unknown => Err(format!("{unknown} is not an llm setting")),
+        }
+    }
+}
+
+/// The llm settings of a chat, each `None` when left at the default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LlmOverrides {
+    model: Option<String>,
+    temperature: Option<f32>,
+    max_tokens: Option<i32>,
+    history_count: Option<i32>,
+    history_minutes: Option<i32>,
+}
+
+/// How the bot chats in a chat, after its overrides.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmConfig {
+    pub options: ChatOptions,
+    /// number of past chat messages sent along with each message.
+    pub history_count: i64,
+    /// past chat messages older than this are left out.
+    pub history_age: Duration,
+}
+
+impl LlmOverrides {
+    /// the settings of the chat, falling back to `defaults`.
+    ///
+    /// overrides which no longer fit, eg. after a change of the limits, are ignored.
+    #[must_use]
+    pub fn apply(&self, defaults: &LlmSettings) -> LlmConfig {
+        let model = self.model.as_ref().filter(|x| defaults.is_allowed_model(x));
+        let max_tokens = self
+            .max_tokens
+            .and_then(|x| u16::try_from(x).ok())
+            .filter(|x| *x <= defaults.max_tokens_limit);
+        let history_count = self
+            .history_count
+            .and_then(|x| u16::try_from(x).ok())
+            .filter(|x| *x <= defaults.history_count_limit);
+        let history_minutes = self
+            .history_minutes
+            .and_then(|x| u32::try_from(x).ok())
+            .filter(|x| *x <= defaults.history_minutes_limit);
+        LlmConfig {
+            options: ChatOptions {
+                model: model.unwrap_or(&defaults.model).clone(),
+                temperature: self.temperature.unwrap_or(defaults.temperature),
+                max_tokens: max_tokens.unwrap_or(defaults.max_tokens),
+            },
+            history_count: history_count.unwrap_or(defaults.history_count).into(),
+            history_age: Duration::minutes(
+                history_minutes.unwrap_or(defaults.history_minutes).into(),
+            ),
+        }
+    }
+
+    fn unset(&mut self, setting: LlmSetting) {
+        match setting {
+            LlmSetting::Model => self.model = None,
+            LlmSetting::Temperature => self.temperature = None,
+            LlmSetting::MaxTokens => self.max_tokens = None,
+            LlmSetting::HistoryCount => self.history_count = None,
+            LlmSetting::HistoryMinutes => self.history_minutes = None,
+        }
+    }
+
+    /// sets the setting to `value`, returning `false` when it doesn't fit
+    /// the models and limits of `defaults`.
+    fn set(&mut self, setting: LlmSetting, value: &str, defaults: &LlmSettings) -> bool {
+        match setting {
+            LlmSetting::Model => {
+                if !defaults.is_allowed_model(value) {
+                    return false;
+                }
+                self.model = Some(value.to_string());
+            }
+            LlmSetting::Temperature => {
+                let Some(x) = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|x| (0.0..=MAX_TEMPERATURE).contains(x))
+                else {
+                    return false;
+                };
+                self.temperature = Some(x);
+            }
+            LlmSetting::MaxTokens => {
+                let Some(x) = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|x| (1..=defaults.max_tokens_limit).contains(x))
+                else {
+                    return false;
+                };
+                self.max_tokens = Some(x.into());
+            }
+            LlmSetting::HistoryCount => {
+                let Some(x) = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|x| *x <= defaults.history_count_limit)
+                else {
+                    return false;
+                };
+                self.history_count = Some(x.into());
+            }
+            LlmSetting::HistoryMinutes => {
+                let Some(x) = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|x| (1..=defaults.history_minutes_limit).contains(x))
+                    .and_then(|x| i32::try_from(x).ok())
+                else {
+                    return false;
+                };
+                self.history_minutes = Some(x);
+            }
+        }
+        true
+    }
+
+    fn is_set(&self, setting: LlmSetting) -> bool {
+        match setting {
+            LlmSetting::Model => self.model.is_some(),
+            LlmSetting::Temperature => self.temperature.is_some(),
+            LlmSetting::MaxTokens => self.max_tokens.is_some(),
+            LlmSetting::HistoryCount => self.history_count.is_some(),
+            LlmSetting::HistoryMinutes => self.history_minutes.is_some(),
+        }
+    }
+}
+
+/// the llm settings which the chat has overridden.
+pub async fn chat_llm_overrides(pool: &PgPool, chat_id: i64) -> Result<LlmOverrides, sqlx::Error> {
+    let overrides = sqlx::query_as!(
+        LlmOverrides,
+        r#"SELECT llm_model as model, llm_temperature as temperature,
+        llm_max_tokens as max_tokens, llm_history_count as history_count,
+        llm_history_minutes as history_minutes
+        FROM chatrooms WHERE id = $1"#,
+        chat_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(overrides.unwrap_or_default())
+}
+
+/// how the bot chats in the chat.
+pub async fn chat_llm_config(
+    pool: &PgPool,
+    defaults: &LlmSettings,
+    chat_id: i64,
+) -> Result<LlmConfig, sqlx::Error> {
+    Ok(chat_llm_overrides(pool, chat_id).await?.apply(defaults))
+}
+
+/// saves the llm settings of the chat.
+///
+/// returns `false` when the chatroom is not in database.
+async fn set_chat_llm_overrides(
+    pool: &PgPool,
+    chat_id: i64,
+    overrides: &LlmOverrides,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE chatrooms SET llm_model = $1, llm_temperature = $2, llm_max_tokens = $3,
+        llm_history_count = $4, llm_history_minutes = $5 WHERE id = $6",
+        overrides.model,
+        overrides.temperature,
+        overrides.max_tokens,
+        overrides.history_count,
+        overrides.history_minutes,
+        chat_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, PartialEq)]
+enum LlmConfigAction<'a> {
+    Set(LlmSetting, &'a str),
+    Default(LlmSetting),
+    Reset,
+}
+
+/// parses the arguments of `/llmconfig`, eg. `temperature 0.3`, `model default`
+/// or `reset`.
+fn parse_llm_config(args: &str) -> Option<LlmConfigAction<'_>> {
+    let tokens: Vec<&str> = args.split_whitespace().collect();
+    match tokens.as_slice() {
+        [reset] if reset.eq_ignore_ascii_case("reset") => Some(LlmConfigAction::Reset),
+        [setting, value] => {
+            let setting = LlmSetting::try_from(*setting).ok()?;
+            if value.eq_ignore_ascii_case("default") {
+                Some(LlmConfigAction::Default(setting))
+            } else {
+                Some(LlmConfigAction::Set(setting, value))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn llm_config_text(overrides: &LlmOverrides, defaults: &LlmSettings) -> String {
+    let config = overrides.apply(defaults);
+    let label = |setting: LlmSetting| {
+        if overrides.is_set(setting) {
+            ""
+        } else {
+            " (default)"
+        }
+    };
+    format!(
+        r"model: {}{}
+temperature: {}{}
+max_tokens: {}{}
+history_count: {} messages{}
+history_minutes: {} minutes{}
+
+{USAGE}",
+        config.options.model,
+        label(LlmSetting::Model),
+        config.options.temperature,
+        label(LlmSetting::Temperature),
+        config.options.max_tokens,
+        label(LlmSetting::MaxTokens),
+        config.history_count,
+        label(LlmSetting::HistoryCount),
+        config.history_age.whole_minutes(),
+        label(LlmSetting::HistoryMinutes),
+    )
+}
+
+/// Changes the llm settings of the chat, for admins only.
+///
+/// shows the llm settings of the chat when the arguments can't be used.
+pub async fn llm_config_command(
+    bot: Bot,
+    msg: &Message,
+    args: &str,
+    pool: &PgPool,
+    defaults: &LlmSettings,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    if !is_chat_admin(&bot, msg).await? {
+        bot.send_message(chat_id, "Only admins can see or change how I chat 🐢")
+            .await?;
+        return Ok(());
+    }
+
+    let mut overrides = chat_llm_overrides(pool, chat_id.0).await?;
+    let Some(action) = parse_llm_config(args) else {
+        bot.send_message(chat_id, llm_config_text(&overrides, defaults))
+            .await?;
+        return Ok(());
+    };
+
+    match action {
+        LlmConfigAction::Set(setting, value) => {
+            if !overrides.set(setting, value, defaults) {
+                let hint = if setting == LlmSetting::Model {
+                    let models: Vec<&str> = std::iter::once(&defaults.model)
+                        .chain(&defaults.allowed_models)
+                        .map(String::as_str)
+                        .collect();
+                    format!(" try one of {}.", models.join(", "))
+                } else {
+                    String::new()
+                };
+                let text = format!("{value} doesn't work for {} 😅{hint}", setting.as_str());
+                bot.send_message(chat_id, text).await?;
+                return Ok(());
+            }
+        }
+        LlmConfigAction::Default(setting) => overrides.unset(setting),
+        LlmConfigAction::Reset => overrides = LlmOverrides::default(),
+    }
+
+    let text = if set_chat_llm_overrides(pool, chat_id.0, &overrides).await? {
+        format!("Updated 🐢\n\n{}", llm_config_text(&overrides, defaults))
+    } else {
+        "I don't know this chat yet 😅 say /start first.".to_string()
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use gaia::llm::{LlmProvider, LlmSettings};
+    use time::Duration;
+
+    use super::{parse_llm_config, LlmConfigAction, LlmOverrides, LlmSetting};
+
+    fn defaults() -> LlmSettings {
+        LlmSettings {
+            provider: LlmProvider::Mock,
+            model: "gpt-3.5-turbo".to_string(),
+            base_url: None,
+            api_key: None,
+            temperature: 1.0,
+            max_tokens: 512,
+            history_count: 20,
+            history_minutes: 60,
+            allowed_models: vec!["llama3".to_string()],
+            max_tokens_limit: 1024,
+            history_count_limit: 50,
+            history_minutes_limit: 120,
+        }
+    }
+
+    #[test]
+    fn llm_config_arguments() {
+        assert_eq!(
+            parse_llm_config("Temperature 0.3"),
+            Some(LlmConfigAction::Set(LlmSetting::Temperature, "0.3"))
+        );
+        assert_eq!(
+            parse_llm_config("model default"),
+            Some(LlmConfigAction::Default(LlmSetting::Model))
+        );
+        assert_eq!(parse_llm_config(" reset "), Some(LlmConfigAction::Reset));
+        assert_eq!(parse_llm_config(""), None);
+        assert_eq!(parse_llm_config("temperature"), None);
+        assert_eq!(parse_llm_config("top_p 0.3"), None);
+        assert_eq!(parse_llm_config("model gpt 4"), None);
+    }
+
+    #[test]
+    fn llm_overrides() {
+        let defaults = defaults();
+        let mut overrides = LlmOverrides::default();
+        assert!(overrides.set(LlmSetting::Model, "llama3", &defaults));
+        assert!(overrides.set(LlmSetting::Temperature, "0.3", &defaults));
+        assert!(overrides.set(LlmSetting::HistoryCount, "0", &defaults));
+        assert!(overrides.set(LlmSetting::HistoryMinutes, "30", &defaults));
+        assert!(!overrides.set(LlmSetting::Model, "gpt-4o", &defaults));
+        assert!(!overrides.set(LlmSetting::Temperature, "2.5", &defaults));
+        assert!(!overrides.set(LlmSetting::Temperature, "NaN", &defaults));
+        assert!(!overrides.set(LlmSetting::MaxTokens, "0", &defaults));
+        assert!(!overrides.set(LlmSetting::MaxTokens, "-5", &defaults));
+        assert!(!overrides.set(LlmSetting::MaxTokens, "2048", &defaults));
+        assert!(!overrides.set(LlmSetting::HistoryCount, "51", &defaults));
+        assert!(!overrides.set(LlmSetting::HistoryMinutes, "121", &defaults));
+
+        let config = overrides.apply(&defaults);
+        assert_eq!(config.options.model, "llama3");
+        assert!((config.options.temperature - 0.3).abs() < f32::EPSILON);
+        assert_eq!(config.options.max_tokens, 512);
+        assert_eq!(config.history_count, 0);
+        assert_eq!(config.history_age, Duration::minutes(30));
+
+        overrides.unset(LlmSetting::Model);
+        assert_eq!(overrides.apply(&defaults).options.model, "gpt-3.5-turbo");
+
+        // the model and limits may change after the overrides were saved
+        let stricter = LlmSettings {
+            allowed_models: Vec::new(),
+            history_minutes_limit: 10,
+            ..defaults.clone()
+        };
+        assert!(overrides.set(LlmSetting::Model, "llama3", &defaults));
+        let config = overrides.apply(&stricter);
+        assert_eq!(config.options.model, "gpt-3.5-turbo");
+        assert_eq!(config.history_age, Duration::minutes(60));
+    }
+}
@@ -1,6 +1,6 @@
 use gaia::{environment::get_environment, get_connection_pool, get_settings, init_tracing};
 use teloxide::Bot;
-use turtle_bot::start_bot;
+use turtle_bot::{init_scheduler, start_bot};
 
 #[tokio::main]
 async fn main() {
@@ -11,5 +11,9 @@ async fn main() {
 
     init_tracing(&env, vec![("turtle_bot")]);
 
-    Box::pin(start_bot(bot, env, settings, pool)).await;
+    let sched = init_scheduler(&bot, &settings.stickers, &pool, &settings.reminder)
+        .await
+        .expect("cannot initialize scheduler");
+
+    Box::pin(start_bot(bot, env, settings, pool, sched)).await;
 }
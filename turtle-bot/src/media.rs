@@ -0,0 +1,287 @@
+//! for reminders which carry a photo, document, voice note or sticker
+
+use serde::{Deserialize, Serialize};
+use teloxide::{
+    payloads::{
+        SendAnimationSetters, SendAudioSetters, SendDocumentSetters, SendMessageSetters,
+        SendPhotoSetters, SendStickerSetters, SendVideoNoteSetters, SendVideoSetters,
+        SendVoiceSetters,
+    },
+    requests::Requester,
+    types::{
+        ChatId, InlineKeyboardMarkup, InputFile, Message, MessageId, ParseMode, ReplyParameters,
+    },
+    Bot, RequestError,
+};
+
+const PHOTO: &str = "photo";
+const VIDEO: &str = "video";
+const ANIMATION: &str = "animation";
+const AUDIO: &str = "audio";
+const DOCUMENT: &str = "document";
+const VOICE: &str = "voice";
+const VIDEO_NOTE: &str = "video_note";
+const STICKER: &str = "sticker";
+
+/// longest caption telegram accepts, longer texts are sent after the media instead.
+const MAX_CAPTION_CHARS: usize = 1024;
+
+/// The kind of media attached to a reminder, stored in the `media_type` column of `jobs_one_off`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MediaKind {
+    Photo,
+    Video,
+    Animation,
+    Audio,
+    Document,
+    Voice,
+    VideoNote,
+    Sticker,
+}
+
+impl MediaKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Photo => PHOTO,
+            Self::Video => VIDEO,
+            Self::Animation => ANIMATION,
+            Self::Audio => AUDIO,
+            Self::Document => DOCUMENT,
+            Self::Voice => VOICE,
+            Self::VideoNote => VIDEO_NOTE,
+            Self::Sticker => STICKER,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Photo => "📷 photo",
+            Self::Video => "🎬 video",
+            Self::Animation => "🎞 gif",
+            Self::Audio => "🎵 audio",
+            Self::Document => "📄 document",
+            Self::Voice => "🎤 voice note",
+            Self::VideoNote => "📹 video message",
+            Self::Sticker => "🐢 sticker",
+        }
+    }
+}
+
+impl TryFrom<&str> for MediaKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            PHOTO => Ok(Self::Photo),
+            VIDEO => Ok(Self::Video),
+            ANIMATION => Ok(Self::Animation),
+            AUDIO => Ok(Self::Audio),
+            DOCUMENT => Ok(Self::Document),
+            VOICE => Ok(Self::Voice),
+            VIDEO_NOTE => Ok(Self::VideoNote),
+            STICKER => Ok(Self::Sticker),
+            unknown => Err(format!("{unknown} is not a supported media type.")),
+        }
+    }
+}
+
+/// A telegram file sent along with the reminder.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReminderMedia {
+    pub kind: MediaKind,
+    pub file_id: String,
+}
+
+impl ReminderMedia {
+    /// the media of the message, if it has any.
+    #[must_use]
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        let (kind, file_id) = if let Some(x) = msg.photo() {
+            // the last size is the largest
+            (MediaKind::Photo, &x.last()?.file.id)
+        } else if let Some(x) = msg.video() {
+            (MediaKind::Video, &x.file.id)
+        } else if let Some(x) = msg.animation() {
+            (MediaKind::Animation, &x.file.id)
+        } else if let Some(x) = msg.audio() {
+            (MediaKind::Audio, &x.file.id)
+        } else if let Some(x) = msg.document() {
+            (MediaKind::Document, &x.file.id)
+        } else if let Some(x) = msg.voice() {
+            (MediaKind::Voice, &x.file.id)
+        } else if let Some(x) = msg.video_note() {
+            (MediaKind::VideoNote, &x.file.id)
+        } else if let Some(x) = msg.sticker() {
+            (MediaKind::Sticker, &x.file.id)
+        } else {
+            return None;
+        };
+        Some(Self {
+            kind,
+            file_id: file_id.clone(),
+        })
+    }
+
+    /// the media stored in the `media_type` and `file_id` columns.
+    #[must_use]
+    pub fn from_columns(media_type: Option<&str>, file_id: Option<String>) -> Option<Self> {
+        let kind = MediaKind::try_from(media_type?)
+            .map_err(|e| tracing::error!("invalid media type in database: {e}"))
+            .ok()?;
+        Some(Self {
+            kind,
+            file_id: file_id?,
+        })
+    }
+}
+
+/// how the reminder text is shown before it is sent, eg. in the wizard.
+pub fn preview_text(media: Option<&ReminderMedia>, text: &str) -> String {
+    match media {
+        Some(media) if text.is_empty() => format!("[{}]", media.kind.label()),
+        Some(media) => format!("[{}] {text}", media.kind.label()),
+        None => text.to_string(),
+    }
+}
+
+/// Sends the `html` text, with the media as its caption when there is one.
+///
+/// video messages, stickers and texts too long for a caption are sent as
+/// a reply to the media instead. `media_sent` keeps the media sent that way,
+/// so that retrying only resends the text. `silent` sends them without a notification.
+pub async fn send_with_media(
+    bot: &Bot,
+    chat_id: ChatId,
+    html: String,
+    media: Option<&ReminderMedia>,
+    keyboard: Option<InlineKeyboardMarkup>,
+    silent: bool,
+    media_sent: &mut Option<MessageId>,
+) -> Result<Message, RequestError> {
+    macro_rules! send {
+        ($request:expr) => {{
+            let request = $request
+                .parse_mode(ParseMode::Html)
+                .disable_notification(silent);
+            match keyboard {
+                Some(keyboard) => request.reply_markup(keyboard).await,
+                None => request.await,
+            }
+        }};
+    }
+
+    let Some(media) = media else {
+        return send!(bot.send_message(chat_id, html));
+    };
+    let file = InputFile::file_id(media.file_id.clone());
+    if html.chars().count() <= MAX_CAPTION_CHARS {
+        match media.kind {
+            MediaKind::Photo => return send!(bot.send_photo(chat_id, file).caption(html)),
+            MediaKind::Video => return send!(bot.send_video(chat_id, file).caption(html)),
+            MediaKind::Animation => return send!(bot.send_animation(chat_id, file).caption(html)),
+            MediaKind::Audio => return send!(bot.send_audio(chat_id, file).caption(html)),
+            MediaKind::Document => return send!(bot.send_document(chat_id, file).caption(html)),
+            MediaKind::Voice => return send!(bot.send_voice(chat_id, file).caption(html)),
+            MediaKind::VideoNote | MediaKind::Sticker => {}
+        }
+    }
+
+    let media_id = if let Some(x) = *media_sent {
+        x
+    } else {
+        let sent = send_media(bot, chat_id, media.kind, file, silent).await?;
+        *media_sent = Some(sent.id);
+        sent.id
+    };
+    send!(bot
+        .send_message(chat_id, html)
+        .reply_parameters(ReplyParameters::new(media_id)))
+}
+
+/// sends the media on its own, without a caption.
+async fn send_media(
+    bot: &Bot,
+    chat_id: ChatId,
+    kind: MediaKind,
+    file: InputFile,
+    silent: bool,
+) -> Result<Message, RequestError> {
+    match kind {
+        MediaKind::Photo => {
+            bot.send_photo(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Video => {
+            bot.send_video(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Animation => {
+            bot.send_animation(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Audio => {
+            bot.send_audio(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Document => {
+            bot.send_document(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Voice => {
+            bot.send_voice(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::VideoNote => {
+            bot.send_video_note(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+        MediaKind::Sticker => {
+            bot.send_sticker(chat_id, file)
+                .disable_notification(silent)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preview_text, MediaKind, ReminderMedia};
+
+    #[test]
+    fn media_columns() {
+        let media = ReminderMedia::from_columns(Some("voice"), Some("AwACAgUAAx".to_string()));
+        assert_eq!(
+            media,
+            Some(ReminderMedia {
+                kind: MediaKind::Voice,
+                file_id: "AwACAgUAAx".to_string()
+            })
+        );
+        assert_eq!(
+            MediaKind::try_from(MediaKind::VideoNote.as_str()),
+            Ok(MediaKind::VideoNote)
+        );
+        assert_eq!(ReminderMedia::from_columns(None, None), None);
+    }
+
+    #[test]
+    fn preview() {
+        let media = ReminderMedia {
+            kind: MediaKind::Document,
+            file_id: String::new(),
+        };
+        assert_eq!(
+            preview_text(Some(&media), "boarding pass"),
+            "[📄 document] boarding pass"
+        );
+        assert_eq!(preview_text(Some(&media), ""), "[📄 document]");
+        assert_eq!(preview_text(None, "boarding pass"), "boarding pass");
+    }
+}
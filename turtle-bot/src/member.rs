@@ -8,13 +8,57 @@ use teloxide::{
     types::{Message, ReplyParameters, User},
     Bot,
 };
+use time::OffsetDateTime;
 
 use crate::{
     bot::BOT_ME,
     chatroom::{self, ChatRoom},
+    handlers::is_group_chat,
     sticker::send_sticker,
 };
 
+/// Saves the user as a member of the group chat, so that reminders can be
+/// assigned to them.
+///
+/// nothing is saved when the chatroom is not in database.
+pub async fn save_member(pool: &PgPool, chat_id: i64, user: &User) -> Result<(), sqlx::Error> {
+    let user_id = i64::from_le_bytes(user.id.0.to_le_bytes());
+    sqlx::query!(
+        "
+        INSERT INTO chat_members (chat_id, user_id, username, first_name, updated_at)
+        SELECT $1, $2, $3, $4, $5
+        WHERE EXISTS (SELECT 1 FROM chatrooms WHERE id = $1)
+        ON CONFLICT (chat_id, user_id) DO UPDATE
+        SET username = EXCLUDED.username,
+        first_name = EXCLUDED.first_name,
+        updated_at = EXCLUDED.updated_at",
+        chat_id,
+        user_id,
+        user.username,
+        user.first_name,
+        OffsetDateTime::now_utc()
+    )
+    .execute(pool)
+    .await
+    .map(|_| ())
+}
+
+/// Keeps track of the members who talk in group chats.
+///
+/// telegram does not list the members of a group to bots, so members are
+/// only known once they joined or said something.
+pub async fn track_member(msg: Message, pool: PgPool) {
+    if !is_group_chat(msg.clone()) {
+        return;
+    }
+    let Some(user) = msg.from.as_ref().filter(|x| !x.is_bot) else {
+        return;
+    };
+    if let Err(e) = save_member(&pool, msg.chat.id.0, user).await {
+        tracing::error!("error saving chat member: {e:#?}");
+    }
+}
+
 #[tracing::instrument(name = "bot got added", skip_all)]
 pub fn i_got_added(msg: Message) -> bool {
     let new_user = msg.new_chat_members();
@@ -71,7 +115,12 @@ pub async fn handle_me_leave(msg: Message, pool: PgPool) -> Result<()> {
 }
 
 #[tracing::instrument(name = "new member", skip_all)]
-pub async fn handle_member_join(bot: Bot, msg: Message, stickers: Stickers) -> Result<()> {
+pub async fn handle_member_join(
+    bot: Bot,
+    msg: Message,
+    pool: PgPool,
+    stickers: Stickers,
+) -> Result<()> {
     let new_users: Option<Vec<User>> = msg
         .new_chat_members()
         .map(std::borrow::ToOwned::to_owned)
@@ -88,6 +137,9 @@ pub async fn handle_member_join(bot: Bot, msg: Message, stickers: Stickers) -> R
     };
 
     for user in users {
+        if let Err(e) = save_member(&pool, msg.chat.id.0, &user).await {
+            tracing::error!("error saving chat member: {e:#?}");
+        }
         tokio::spawn({
             let bot = bot.clone();
             async move {
@@ -130,6 +182,13 @@ pub async fn handle_member_leave(
     )
     .execute(&pool)
     .await?;
+    sqlx::query!(
+        "DELETE FROM chat_members WHERE chat_id = $1 AND user_id = $2",
+        chat_id,
+        user_id_i64
+    )
+    .execute(&pool)
+    .await?;
     let text = format!("Sayanora {} ~~ 😭😭😭", member.full_name());
     send_sticker(&bot, &msg.chat.id, stickers.sad).await?;
     bot.send_message(msg.chat.id, text)
@@ -0,0 +1,262 @@
+//! for giving each chat its own system prompt, managed with `/persona`
+//!
+//! chats without one talk to the turtle.
+
+use sqlx::PgPool;
+use teloxide::{requests::Requester, types::Message, Bot};
+
+use crate::{bot::BOT_NAME, chatroom::is_chat_admin};
+
+/// longest system prompt a chat may set, to keep the tokens of each chat in check.
+const MAX_PERSONA_CHARS: usize = 1000;
+
+const USAGE: &str = r"Change who I am in this chat 🐢
+
+/persona tutor
+/persona translator
+/persona terse
+/persona set You are a pirate who answers in rhymes.
+/persona reset
+
+Only admins can change the persona.";
+
+/// The built-in personas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Tutor,
+    Translator,
+    Terse,
+}
+
+impl Preset {
+    const ALL: [Self; 3] = [Self::Tutor, Self::Translator, Self::Terse];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Tutor => "tutor",
+            Self::Translator => "translator",
+            Self::Terse => "terse",
+        }
+    }
+
+    pub fn prompt(self) -> &'static str {
+        match self {
+            Self::Tutor => {
+                "You are a patient Socratic tutor. Do not give answers away. \
+                Guide the learner to the answer with one question at a time, \
+                and point out mistakes in their reasoning kindly."
+            }
+            Self::Translator => {
+                "You are a translator. Reply with only the translation of each message: \
+                into English, or into the language asked for earlier in the chat \
+                when the message is already in English."
+            }
+            Self::Terse => {
+                "You are a terse assistant. Answer in as few words as possible, \
+                without greetings, filler or emoji."
+            }
+        }
+    }
+
+    /// the preset whose prompt is `prompt`, if any.
+    fn from_prompt(prompt: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.prompt() == prompt)
+    }
+}
+
+impl TryFrom<&str> for Preset {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "tutor" => Ok(Self::Tutor),
+            "translator" => Ok(Self::Translator),
+            "terse" => Ok(Self::Terse),
+            unknown => Err(format!("{unknown} is not a persona preset")),
+        }
+    }
+}
+
+/// the system prompt of chats without a persona.
+fn default_prompt() -> String {
+    format!(
+        "You are a cute and bubbly yet wise and ancient male turtle and your name is {}.",
+        BOT_NAME.get().unwrap()
+    )
+}
+
+/// the persona of the chat, if it has one.
+pub async fn chat_persona(pool: &PgPool, chat_id: i64) -> Result<Option<String>, sqlx::Error> {
+    let persona = sqlx::query_scalar!("SELECT persona FROM chatrooms WHERE id = $1", chat_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(persona.flatten())
+}
+
+/// the system prompt of the chat, which is the turtle's unless it has a persona.
+pub async fn system_prompt(pool: &PgPool, chat_id: i64) -> Result<String, sqlx::Error> {
+    Ok(chat_persona(pool, chat_id)
+        .await?
+        .unwrap_or_else(default_prompt))
+}
+
+/// saves the persona of the chat, which is reset with `None`.
+///
+/// returns `false` when the chatroom is not in database.
+pub async fn set_chat_persona(
+    pool: &PgPool,
+    chat_id: i64,
+    persona: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE chatrooms SET persona = $1 WHERE id = $2",
+        persona,
+        chat_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, PartialEq)]
+enum PersonaAction {
+    Preset(Preset),
+    Set(String),
+    Reset,
+}
+
+#[derive(Debug, PartialEq)]
+enum PersonaParseError {
+    /// no arguments, or ones which aren't in any of the usages.
+    Usage,
+    UnknownPreset(String),
+}
+
+/// parses the arguments of `/persona`, eg. `tutor`, `set <prompt>` or `reset`.
+fn parse_persona(args: &str) -> Result<PersonaAction, PersonaParseError> {
+    let args = args.trim();
+    let (first, rest) = args
+        .split_once(char::is_whitespace)
+        .map_or((args, ""), |(first, rest)| (first, rest.trim()));
+
+    if first.eq_ignore_ascii_case("set") {
+        return match rest {
+            "" => Err(PersonaParseError::Usage),
+            rest => Ok(PersonaAction::Set(rest.to_string())),
+        };
+    }
+    if first.is_empty() || !rest.is_empty() {
+        return Err(PersonaParseError::Usage);
+    }
+    if first.eq_ignore_ascii_case("reset") {
+        return Ok(PersonaAction::Reset);
+    }
+    Preset::try_from(first)
+        .map(PersonaAction::Preset)
+        .map_err(|_| PersonaParseError::UnknownPreset(first.to_string()))
+}
+
+fn persona_text(persona: Option<&str>) -> String {
+    match persona {
+        Some(x) => match Preset::from_prompt(x) {
+            Some(preset) => format!("Persona: {}\n\n{x}\n\n{USAGE}", preset.as_str()),
+            None => format!("Persona: custom\n\n{x}\n\n{USAGE}"),
+        },
+        None => format!("Persona: turtle 🐢\n\n{USAGE}"),
+    }
+}
+
+/// Sets or resets the persona of the chat, for admins only.
+///
+/// shows the persona of the chat when there are no arguments, or ones which can't be used.
+pub async fn persona_command(
+    bot: Bot,
+    msg: &Message,
+    args: &str,
+    pool: &PgPool,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    if !is_chat_admin(&bot, msg).await? {
+        bot.send_message(chat_id, "Only admins can see or change my persona 🐢")
+            .await?;
+        return Ok(());
+    }
+
+    let action = match parse_persona(args) {
+        Ok(x) => x,
+        Err(PersonaParseError::Usage) => {
+            let persona = chat_persona(pool, chat_id.0).await?;
+            bot.send_message(chat_id, persona_text(persona.as_deref()))
+                .await?;
+            return Ok(());
+        }
+        Err(PersonaParseError::UnknownPreset(x)) => {
+            let presets: Vec<&str> = Preset::ALL.into_iter().map(Preset::as_str).collect();
+            let text = format!(
+                "I don't know the {x} persona 😅 try one of {}, or /persona set your own.",
+                presets.join(", ")
+            );
+            bot.send_message(chat_id, text).await?;
+            return Ok(());
+        }
+    };
+
+    let persona = match &action {
+        PersonaAction::Preset(x) => Some(x.prompt()),
+        PersonaAction::Set(x) => Some(x.as_str()),
+        PersonaAction::Reset => None,
+    };
+    if persona.is_some_and(|x| x.chars().count() > MAX_PERSONA_CHARS) {
+        let text = format!("That's too long 😅 keep it under {MAX_PERSONA_CHARS} characters.");
+        bot.send_message(chat_id, text).await?;
+        return Ok(());
+    }
+
+    let text = if set_chat_persona(pool, chat_id.0, persona).await? {
+        match action {
+            PersonaAction::Preset(x) => format!("I'm your {} now. 🐢", x.as_str()),
+            PersonaAction::Set(_) => "Got it, I'll be just that. 🐢".to_string(),
+            PersonaAction::Reset => "I'm back to being a turtle. 🐢".to_string(),
+        }
+    } else {
+        "I don't know this chat yet 😅 say /start first.".to_string()
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_persona, PersonaAction, PersonaParseError, Preset};
+
+    #[test]
+    fn persona_arguments() {
+        assert_eq!(
+            parse_persona(" Tutor "),
+            Ok(PersonaAction::Preset(Preset::Tutor))
+        );
+        assert_eq!(
+            parse_persona("set You are a pirate.\nSay arr."),
+            Ok(PersonaAction::Set(
+                "You are a pirate.\nSay arr.".to_string()
+            ))
+        );
+        assert_eq!(parse_persona("reset"), Ok(PersonaAction::Reset));
+        assert_eq!(parse_persona(""), Err(PersonaParseError::Usage));
+        assert_eq!(parse_persona("set"), Err(PersonaParseError::Usage));
+        assert_eq!(parse_persona("tutor please"), Err(PersonaParseError::Usage));
+        assert_eq!(
+            parse_persona("pirate"),
+            Err(PersonaParseError::UnknownPreset("pirate".to_string()))
+        );
+    }
+
+    #[test]
+    fn preset_prompts() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::try_from(preset.as_str()), Ok(preset));
+            assert_eq!(Preset::from_prompt(preset.prompt()), Some(preset));
+        }
+        assert_eq!(Preset::from_prompt("You are a pirate."), None);
+    }
+}
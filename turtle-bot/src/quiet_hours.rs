@@ -0,0 +1,256 @@
+//! for keeping scheduled messages quiet at night, managed with `/quiet`
+//!
+//! greetings are skipped during the quiet hours of a chat. one-off reminders are
+//! deferred to the end of the quiet hours, unless they are urgent, in which case
+//! they are sent without a notification, as are recurring reminders and heads-ups.
+
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use teloxide::{requests::Requester, types::ChatId, Bot};
+use time::{OffsetDateTime, PrimitiveDateTime, Time};
+
+use crate::{
+    callbacks::parse_time,
+    timezone::{from_local, parse_timezone, to_local},
+};
+
+const USAGE: &str = r"Set the quiet hours of this chat 🐢
+
+/quiet 22:00 07:00
+/quiet 11pm 6:30am
+/quiet off
+
+Greetings are skipped during quiet hours. Reminders wait until they are over, \
+unless they are marked as urgent, which are sent without a sound.";
+
+/// The daily window in the local time of a chat, which may go past midnight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuietHours {
+    pub start: Time,
+    pub end: Time,
+}
+
+impl QuietHours {
+    fn contains(self, time: Time) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// the instant the quiet hours end, when `instant` falls within them.
+    fn ends_at(self, tz: Tz, instant: OffsetDateTime) -> Option<OffsetDateTime> {
+        let local = to_local(tz, instant);
+        if !self.contains(local.time()) {
+            return None;
+        }
+        let date = if local.time() < self.end {
+            local.date()
+        } else {
+            local.date().next_day()?
+        };
+        Some(from_local(tz, PrimitiveDateTime::new(date, self.end)))
+    }
+}
+
+/// the quiet hours of the chat, if it has any.
+pub async fn chat_quiet_hours(
+    pool: &PgPool,
+    chat_id: i64,
+) -> Result<Option<QuietHours>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT quiet_start, quiet_end FROM chatrooms WHERE id = $1",
+        chat_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.and_then(|x| match (x.quiet_start, x.quiet_end) {
+        (Some(start), Some(end)) => Some(QuietHours { start, end }),
+        _ => None,
+    }))
+}
+
+/// saves the quiet hours of the chat, which are turned off with `None`.
+///
+/// returns `false` when the chatroom is not in database.
+pub async fn set_chat_quiet_hours(
+    pool: &PgPool,
+    chat_id: i64,
+    quiet_hours: Option<QuietHours>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE chatrooms SET quiet_start = $1, quiet_end = $2 WHERE id = $3",
+        quiet_hours.map(|x| x.start),
+        quiet_hours.map(|x| x.end),
+        chat_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// the instant the quiet hours of the chat end, when `instant` falls within them.
+pub async fn quiet_until(
+    pool: &PgPool,
+    chat_id: i64,
+    instant: OffsetDateTime,
+) -> Result<Option<OffsetDateTime>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT timezone, quiet_start, quiet_end FROM chatrooms WHERE id = $1",
+        chat_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.and_then(|x| match (x.quiet_start, x.quiet_end) {
+        (Some(start), Some(end)) => {
+            QuietHours { start, end }.ends_at(parse_timezone(&x.timezone), instant)
+        }
+        _ => None,
+    }))
+}
+
+/// whether the chat is in its quiet hours right now.
+///
+/// errors are logged, and the chat is taken as not quiet.
+pub async fn is_quiet(pool: &PgPool, chat_id: i64) -> bool {
+    match quiet_until(pool, chat_id, OffsetDateTime::now_utc()).await {
+        Ok(until) => until.is_some(),
+        Err(e) => {
+            tracing::error!("error getting quiet hours of chat: {e:#?}");
+            false
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum QuietAction {
+    Set(QuietHours),
+    Off,
+}
+
+/// parses the arguments of `/quiet`, eg. `22:00 07:00` or `off`.
+fn parse_quiet_hours(args: &str) -> Option<QuietAction> {
+    let tokens: Vec<&str> = args.split_whitespace().collect();
+    if let [off] = tokens.as_slice() {
+        return off.eq_ignore_ascii_case("off").then_some(QuietAction::Off);
+    }
+
+    let (start, used) = parse_time(&tokens)?;
+    let rest = &tokens[used..];
+    let (end, used) = parse_time(rest)?;
+    if used != rest.len() || start == end {
+        return None;
+    }
+    Some(QuietAction::Set(QuietHours { start, end }))
+}
+
+fn quiet_hours_text(quiet_hours: Option<QuietHours>) -> String {
+    match quiet_hours {
+        Some(x) => format!(
+            "Quiet hours: {:02}:{:02} to {:02}:{:02}\n\n{USAGE}",
+            x.start.hour(),
+            x.start.minute(),
+            x.end.hour(),
+            x.end.minute()
+        ),
+        None => format!("Quiet hours: off\n\n{USAGE}"),
+    }
+}
+
+/// Sets or turns off the quiet hours of the chat.
+///
+/// shows the quiet hours of the chat when the arguments can't be used.
+pub async fn quiet_command(
+    bot: Bot,
+    chat_id: ChatId,
+    args: &str,
+    pool: &PgPool,
+) -> anyhow::Result<()> {
+    let Some(action) = parse_quiet_hours(args) else {
+        let quiet_hours = chat_quiet_hours(pool, chat_id.0).await?;
+        bot.send_message(chat_id, quiet_hours_text(quiet_hours))
+            .await?;
+        return Ok(());
+    };
+
+    let quiet_hours = match action {
+        QuietAction::Set(x) => Some(x),
+        QuietAction::Off => None,
+    };
+    let text = if !set_chat_quiet_hours(pool, chat_id.0, quiet_hours).await? {
+        "I don't know this chat yet 😅 say /start first.".to_string()
+    } else if let Some(x) = quiet_hours {
+        format!(
+            "Shh 🤫 quiet hours are now {:02}:{:02} to {:02}:{:02} every day.",
+            x.start.hour(),
+            x.start.minute(),
+            x.end.hour(),
+            x.end.minute()
+        )
+    } else {
+        "Quiet hours are off. 🐢".to_string()
+    };
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::Tz;
+    use time::macros::{datetime, time};
+
+    use super::{parse_quiet_hours, QuietAction, QuietHours};
+
+    #[test]
+    fn quiet_hours_arguments() {
+        let night = QuietHours {
+            start: time!(22:00),
+            end: time!(7:00),
+        };
+        assert_eq!(
+            parse_quiet_hours("22:00 07:00"),
+            Some(QuietAction::Set(night))
+        );
+        assert_eq!(
+            parse_quiet_hours("10pm 7 am"),
+            Some(QuietAction::Set(night))
+        );
+        assert_eq!(parse_quiet_hours(" OFF "), Some(QuietAction::Off));
+        assert_eq!(parse_quiet_hours(""), None);
+        assert_eq!(parse_quiet_hours("22:00"), None);
+        assert_eq!(parse_quiet_hours("22:00 22:00"), None);
+        assert_eq!(parse_quiet_hours("22:00 07:00 daily"), None);
+    }
+
+    #[test]
+    fn quiet_hours_end() {
+        let tz = Tz::Asia__Singapore;
+        let night = QuietHours {
+            start: time!(22:00),
+            end: time!(7:00),
+        };
+        assert_eq!(
+            night.ends_at(tz, datetime!(2024-07-06 23:30 +8)),
+            Some(datetime!(2024-07-07 07:00 +8))
+        );
+        assert_eq!(
+            night.ends_at(tz, datetime!(2024-07-07 03:00 +8)),
+            Some(datetime!(2024-07-07 07:00 +8))
+        );
+        assert_eq!(night.ends_at(tz, datetime!(2024-07-07 07:00 +8)), None);
+        assert_eq!(night.ends_at(tz, datetime!(2024-07-07 12:00 +8)), None);
+
+        let lunch = QuietHours {
+            start: time!(12:00),
+            end: time!(13:30),
+        };
+        assert_eq!(
+            lunch.ends_at(tz, datetime!(2024-07-07 04:15 UTC)),
+            Some(datetime!(2024-07-07 13:30 +8))
+        );
+        assert_eq!(lunch.ends_at(tz, datetime!(2024-07-07 13:30 +8)), None);
+    }
+}
@@ -0,0 +1,239 @@
+//! for keeping dialogues in the `dialogues` table, so that the `/chat` mode and
+//! half-finished wizards of each chat survive restarts
+
+use std::{future::Future, marker::PhantomData, pin::Pin, sync::Arc};
+
+use gaia::app::DialogueStorage;
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::PgPool;
+use teloxide::{
+    dispatching::dialogue::{InMemStorage, InMemStorageError, Storage},
+    types::ChatId,
+};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error(transparent)]
+    SqlxError(#[from] sqlx::Error),
+
+    #[error(transparent)]
+    SerdeError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    InMemError(#[from] InMemStorageError),
+
+    #[error("dialogue not found.")]
+    DialogueNotFound,
+}
+
+/// A [`Storage`] of the dialogues of type `D`, saved as json.
+///
+/// `kind` tells the dialogues of different types in the same chat apart.
+pub struct PgStorage<D> {
+    pool: PgPool,
+    kind: &'static str,
+    dialogue: PhantomData<fn() -> D>,
+}
+
+impl<D> PgStorage<D> {
+    #[must_use]
+    pub fn new(pool: PgPool, kind: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            pool,
+            kind,
+            dialogue: PhantomData,
+        })
+    }
+}
+
+impl<D> Storage<D> for PgStorage<D>
+where
+    D: Serialize + DeserializeOwned + Send + 'static,
+{
+    type Error = StorageError;
+
+    fn remove_dialogue(self: Arc<Self>, chat_id: ChatId) -> BoxFuture<Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            let result = sqlx::query!(
+                "DELETE FROM dialogues WHERE chat_id = $1 AND kind = $2",
+                chat_id.0,
+                self.kind
+            )
+            .execute(&self.pool)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(StorageError::DialogueNotFound);
+            }
+            Ok(())
+        })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        dialogue: D,
+    ) -> BoxFuture<Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            let dialogue = serde_json::to_string(&dialogue)?;
+            sqlx::query!(
+                "INSERT INTO dialogues (chat_id, kind, dialogue, updated_at)
+                VALUES ($1, $2, $3::TEXT::JSONB, now())
+                ON CONFLICT (chat_id, kind)
+                DO UPDATE SET dialogue = EXCLUDED.dialogue, updated_at = EXCLUDED.updated_at",
+                chat_id.0,
+                self.kind,
+                dialogue
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn get_dialogue(self: Arc<Self>, chat_id: ChatId) -> BoxFuture<Result<Option<D>, Self::Error>> {
+        Box::pin(async move {
+            let dialogue = sqlx::query_scalar!(
+                r#"SELECT dialogue::TEXT as "dialogue!" FROM dialogues
+                WHERE chat_id = $1 AND kind = $2"#,
+                chat_id.0,
+                self.kind
+            )
+            .fetch_optional(&self.pool)
+            .await?;
+
+            let Some(dialogue) = dialogue else {
+                return Ok(None);
+            };
+            // dialogues saved by an older version of the bot may no longer fit,
+            // and are started over instead.
+            match serde_json::from_str(&dialogue) {
+                Ok(x) => Ok(Some(x)),
+                Err(e) => {
+                    tracing::warn!("dropping unreadable {} dialogue: {e}", self.kind);
+                    Ok(None)
+                }
+            }
+        })
+    }
+}
+
+/// The storage of the dialogues of type `D`, as picked in the settings.
+pub enum BotStorage<D> {
+    Postgres(Arc<PgStorage<D>>),
+    Memory(Arc<InMemStorage<D>>),
+}
+
+impl<D> BotStorage<D> {
+    /// `kind` names the dialogues of `D` in postgres.
+    #[must_use]
+    pub fn new(storage: DialogueStorage, pool: &PgPool, kind: &'static str) -> Arc<Self> {
+        Arc::new(match storage {
+            DialogueStorage::Postgres => Self::Postgres(PgStorage::new(pool.clone(), kind)),
+            DialogueStorage::Memory => Self::Memory(InMemStorage::new()),
+        })
+    }
+}
+
+impl<D> Storage<D> for BotStorage<D>
+where
+    D: Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    type Error = StorageError;
+
+    fn remove_dialogue(self: Arc<Self>, chat_id: ChatId) -> BoxFuture<Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            match self.as_ref() {
+                Self::Postgres(x) => x.clone().remove_dialogue(chat_id).await,
+                Self::Memory(x) => Ok(x.clone().remove_dialogue(chat_id).await?),
+            }
+        })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        dialogue: D,
+    ) -> BoxFuture<Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            match self.as_ref() {
+                Self::Postgres(x) => x.clone().update_dialogue(chat_id, dialogue).await,
+                Self::Memory(x) => Ok(x.clone().update_dialogue(chat_id, dialogue).await?),
+            }
+        })
+    }
+
+    fn get_dialogue(self: Arc<Self>, chat_id: ChatId) -> BoxFuture<Result<Option<D>, Self::Error>> {
+        Box::pin(async move {
+            match self.as_ref() {
+                Self::Postgres(x) => x.clone().get_dialogue(chat_id).await,
+                Self::Memory(x) => Ok(x.clone().get_dialogue(chat_id).await?),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use crate::{
+        callbacks::{Assignee, Assignment, CallbackPage},
+        media::{MediaKind, ReminderMedia},
+    };
+
+    #[test]
+    fn callback_page_json() {
+        let page = CallbackPage::AssignReminder {
+            date_time: datetime!(2024-07-10 09:30 +8),
+            msg_text: "water the plants".to_string(),
+            media: Some(ReminderMedia {
+                kind: MediaKind::Photo,
+                file_id: "abc".to_string(),
+            }),
+            assignment: Assignment {
+                assignees: vec![Assignee {
+                    user_id: 42,
+                    name: "turtle".to_string(),
+                }],
+                dm: true,
+            },
+            lead_times: vec![15, 60],
+            urgent: true,
+        };
+
+        let json = serde_json::to_string(&page).unwrap();
+        let CallbackPage::AssignReminder {
+            date_time,
+            media,
+            assignment,
+            lead_times,
+            urgent,
+            ..
+        } = serde_json::from_str(&json).unwrap()
+        else {
+            panic!("page changed after a round trip: {json}");
+        };
+        assert_eq!(date_time, datetime!(2024-07-10 09:30 +8));
+        assert_eq!(date_time.offset(), datetime!(2024-07-10 09:30 +8).offset());
+        assert_eq!(media.unwrap().kind, MediaKind::Photo);
+        assert_eq!(assignment.assignees[0].user_id, 42);
+        assert!(assignment.dm);
+        assert_eq!(lead_times, vec![15, 60]);
+        assert!(urgent);
+    }
+}
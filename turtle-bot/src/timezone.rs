@@ -0,0 +1,113 @@
+//! for converting between UTC and a chatroom's local time
+
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{OffsetComponents, Tz};
+use sqlx::PgPool;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+/// the time zone of chatrooms which never picked one.
+pub const DEFAULT_TIMEZONE: Tz = Tz::Asia__Singapore;
+
+/// gets the time zone picked by the chatroom.
+pub async fn chat_timezone(pool: &PgPool, chat_id: i64) -> Result<Tz, sqlx::Error> {
+    let timezone = sqlx::query_scalar!("SELECT timezone FROM chatrooms WHERE id = $1", chat_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(timezone.map_or(DEFAULT_TIMEZONE, |x| parse_timezone(&x)))
+}
+
+/// saves the time zone picked by the chatroom.
+///
+/// returns `false` when the chatroom is not in database.
+pub async fn set_chat_timezone(pool: &PgPool, chat_id: i64, tz: Tz) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE chatrooms SET timezone = $1 WHERE id = $2",
+        tz.name(),
+        chat_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// parses the time zone stored in database, falling back to [`DEFAULT_TIMEZONE`].
+pub fn parse_timezone(name: &str) -> Tz {
+    name.parse().unwrap_or_else(|e| {
+        tracing::error!("invalid timezone in database: {e}");
+        DEFAULT_TIMEZONE
+    })
+}
+
+/// the current local time of `tz`.
+pub fn now_in(tz: Tz) -> OffsetDateTime {
+    to_local(tz, OffsetDateTime::now_utc())
+}
+
+/// the same instant, shown in the local time of `tz`.
+pub fn to_local(tz: Tz, instant: OffsetDateTime) -> OffsetDateTime {
+    let utc = naive(instant.unix_timestamp());
+    let offset = tz.offset_from_utc_datetime(&utc);
+    instant.to_offset(utc_offset(offset.fix()))
+}
+
+/// whether `instant` falls in the daylight saving time of `tz`.
+pub fn is_daylight_saving(tz: Tz, instant: OffsetDateTime) -> bool {
+    let offset = tz.offset_from_utc_datetime(&naive(instant.unix_timestamp()));
+    offset.dst_offset() != chrono::Duration::zero()
+}
+
+/// the instant at which the clocks of `tz` show `local`.
+///
+/// if the clocks skip over `local` (eg. start of daylight saving time),
+/// the offset before the skip is used, which lands after the skip.
+/// if the clocks show `local` twice, the earlier instant is used.
+pub fn from_local(tz: Tz, local: PrimitiveDateTime) -> OffsetDateTime {
+    let naive = naive(local.assume_utc().unix_timestamp());
+    let offset = match tz.offset_from_local_datetime(&naive) {
+        LocalResult::Single(x) | LocalResult::Ambiguous(x, _) => x,
+        LocalResult::None => tz.offset_from_utc_datetime(&(naive - chrono::Duration::days(1))),
+    };
+    local.assume_offset(utc_offset(offset.fix()))
+}
+
+fn naive(timestamp: i64) -> NaiveDateTime {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+fn utc_offset(offset: chrono::FixedOffset) -> UtcOffset {
+    UtcOffset::from_whole_seconds(offset.local_minus_utc()).unwrap_or(UtcOffset::UTC)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn daylight_saving() {
+        let tz = Tz::Europe__London;
+        assert_eq!(
+            from_local(tz, datetime!(2024-01-15 09:00)),
+            datetime!(2024-01-15 09:00 UTC)
+        );
+        assert_eq!(
+            from_local(tz, datetime!(2024-07-15 09:00)),
+            datetime!(2024-07-15 08:00 UTC)
+        );
+        // 01:30 does not exist on the day the clocks go forward
+        assert_eq!(
+            from_local(tz, datetime!(2024-03-31 01:30)),
+            datetime!(2024-03-31 01:30 UTC)
+        );
+        assert_eq!(
+            to_local(tz, datetime!(2024-07-15 08:00 UTC)),
+            datetime!(2024-07-15 09:00 +1)
+        );
+        assert!(is_daylight_saving(tz, datetime!(2024-07-15 08:00 UTC)));
+        assert!(!is_daylight_saving(tz, datetime!(2024-01-15 08:00 UTC)));
+    }
+}
@@ -0,0 +1,365 @@
+//! for keeping the page of each wizard, so that several people can run their own
+//! wizards in the same chat
+//!
+//! a wizard is keyed by its chat and the message showing it, and belongs to the
+//! user who started it. text replies go to the latest wizard of their sender.
+
+use std::{collections::HashMap, sync::Arc};
+
+use gaia::app::DialogueStorage;
+use sqlx::PgPool;
+use teloxide::{
+    types::{CallbackQuery, ChatId, Message, MessageId, UserId},
+    Bot,
+};
+use time::{Duration, OffsetDateTime};
+use tokio::sync::Mutex;
+
+use crate::{
+    callbacks::{expired_callback_msg, CallbackPage},
+    storage::StorageError,
+};
+
+/// how often abandoned wizards are looked for.
+const EXPIRY_INTERVAL: Duration = Duration::MINUTE;
+
+pub struct Wizard {
+    user_id: UserId,
+    page: CallbackPage,
+    updated_at: OffsetDateTime,
+}
+
+/// The pages of the wizards of every chat, in the storage picked in the settings.
+pub enum WizardStorage {
+    Postgres(PgPool),
+    Memory(Mutex<HashMap<(ChatId, MessageId), Wizard>>),
+}
+
+fn to_i64(user_id: UserId) -> i64 {
+    i64::from_le_bytes(user_id.0.to_le_bytes())
+}
+
+fn to_user_id(user_id: i64) -> UserId {
+    UserId(u64::from_le_bytes(user_id.to_le_bytes()))
+}
+
+impl WizardStorage {
+    #[must_use]
+    pub fn new(storage: DialogueStorage, pool: &PgPool) -> Arc<Self> {
+        Arc::new(match storage {
+            DialogueStorage::Postgres => Self::Postgres(pool.clone()),
+            DialogueStorage::Memory => Self::Memory(Mutex::default()),
+        })
+    }
+
+    /// the user who started the wizard on the message, if there is one.
+    async fn owner(
+        &self,
+        chat_id: ChatId,
+        msg_id: MessageId,
+    ) -> Result<Option<UserId>, StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                let user_id = sqlx::query_scalar!(
+                    "SELECT user_id FROM wizards WHERE chat_id = $1 AND message_id = $2",
+                    chat_id.0,
+                    msg_id.0
+                )
+                .fetch_optional(pool)
+                .await?;
+                Ok(user_id.map(to_user_id))
+            }
+            Self::Memory(wizards) => Ok(wizards
+                .lock()
+                .await
+                .get(&(chat_id, msg_id))
+                .map(|x| x.user_id)),
+        }
+    }
+
+    /// the page of the wizard on the message, when it belongs to the user.
+    async fn page(
+        &self,
+        chat_id: ChatId,
+        msg_id: MessageId,
+        user_id: UserId,
+    ) -> Result<Option<CallbackPage>, StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                let page = sqlx::query_scalar!(
+                    r#"SELECT page::TEXT as "page!" FROM wizards
+                    WHERE chat_id = $1 AND message_id = $2 AND user_id = $3"#,
+                    chat_id.0,
+                    msg_id.0,
+                    to_i64(user_id)
+                )
+                .fetch_optional(pool)
+                .await?;
+
+                let Some(page) = page else {
+                    return Ok(None);
+                };
+                // pages saved by an older version of the bot may no longer fit,
+                // and are treated as expired instead.
+                match serde_json::from_str(&page) {
+                    Ok(x) => Ok(Some(x)),
+                    Err(e) => {
+                        tracing::warn!("dropping unreadable wizard page: {e}");
+                        Ok(None)
+                    }
+                }
+            }
+            Self::Memory(wizards) => Ok(wizards
+                .lock()
+                .await
+                .get(&(chat_id, msg_id))
+                .filter(|x| x.user_id == user_id)
+                .map(|x| x.page.clone())),
+        }
+    }
+
+    /// the message of the wizard which the user touched last in the chat.
+    async fn latest(
+        &self,
+        chat_id: ChatId,
+        user_id: UserId,
+    ) -> Result<Option<MessageId>, StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                let msg_id = sqlx::query_scalar!(
+                    "SELECT message_id FROM wizards WHERE chat_id = $1 AND user_id = $2
+                    ORDER BY updated_at DESC LIMIT 1",
+                    chat_id.0,
+                    to_i64(user_id)
+                )
+                .fetch_optional(pool)
+                .await?;
+                Ok(msg_id.map(MessageId))
+            }
+            Self::Memory(wizards) => Ok(wizards
+                .lock()
+                .await
+                .iter()
+                .filter(|((chat, _), x)| *chat == chat_id && x.user_id == user_id)
+                .max_by_key(|(_, x)| x.updated_at)
+                .map(|((_, msg_id), _)| *msg_id)),
+        }
+    }
+
+    async fn save(
+        &self,
+        chat_id: ChatId,
+        msg_id: MessageId,
+        user_id: UserId,
+        page: CallbackPage,
+    ) -> Result<(), StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                let page = serde_json::to_string(&page)?;
+                sqlx::query!(
+                    "INSERT INTO wizards (chat_id, message_id, user_id, page, updated_at)
+                    VALUES ($1, $2, $3, $4::TEXT::JSONB, now())
+                    ON CONFLICT (chat_id, message_id)
+                    DO UPDATE SET user_id = EXCLUDED.user_id, page = EXCLUDED.page,
+                    updated_at = EXCLUDED.updated_at",
+                    chat_id.0,
+                    msg_id.0,
+                    to_i64(user_id),
+                    page
+                )
+                .execute(pool)
+                .await?;
+            }
+            Self::Memory(wizards) => {
+                let wizard = Wizard {
+                    user_id,
+                    page,
+                    updated_at: OffsetDateTime::now_utc(),
+                };
+                wizards.lock().await.insert((chat_id, msg_id), wizard);
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, chat_id: ChatId, msg_id: MessageId) -> Result<(), StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                sqlx::query!(
+                    "DELETE FROM wizards WHERE chat_id = $1 AND message_id = $2",
+                    chat_id.0,
+                    msg_id.0
+                )
+                .execute(pool)
+                .await?;
+            }
+            Self::Memory(wizards) => {
+                wizards.lock().await.remove(&(chat_id, msg_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// removes the wizards untouched since `before`, returning their messages.
+    async fn expire(
+        &self,
+        before: OffsetDateTime,
+    ) -> Result<Vec<(ChatId, MessageId)>, StorageError> {
+        match self {
+            Self::Postgres(pool) => {
+                let records = sqlx::query!(
+                    "DELETE FROM wizards WHERE updated_at < $1 RETURNING chat_id, message_id",
+                    before
+                )
+                .fetch_all(pool)
+                .await?;
+                Ok(records
+                    .into_iter()
+                    .map(|x| (ChatId(x.chat_id), MessageId(x.message_id)))
+                    .collect())
+            }
+            Self::Memory(wizards) => {
+                let mut expired = Vec::new();
+                wizards.lock().await.retain(|key, x| {
+                    if x.updated_at < before {
+                        expired.push(*key);
+                    }
+                    x.updated_at >= before
+                });
+                Ok(expired)
+            }
+        }
+    }
+}
+
+/// Expires the wizards left untouched for `ttl`, so that their next message isn't
+/// taken as a reply to them.
+///
+/// the message of each expired wizard is edited to say so.
+#[tracing::instrument(skip_all)]
+pub async fn expire_wizards(bot: Bot, storage: Arc<WizardStorage>, ttl: Duration) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL.unsigned_abs());
+    loop {
+        interval.tick().await;
+        let expired = match storage.expire(OffsetDateTime::now_utc() - ttl).await {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::error!("error expiring wizards: {e:#?}");
+                continue;
+            }
+        };
+        for (chat_id, msg_id) in expired {
+            // the message may have been deleted in the meantime
+            if let Err(e) = expired_callback_msg(bot.clone(), chat_id, msg_id).await {
+                tracing::warn!("error editing expired wizard: {e:#?}");
+            }
+        }
+    }
+}
+
+/// The wizard of a user on one message of a chat.
+///
+/// `msg_id` is `None` when the user has no wizard in the chat yet.
+#[derive(Clone)]
+pub struct CallbackState {
+    storage: Arc<WizardStorage>,
+    chat_id: ChatId,
+    user_id: UserId,
+    msg_id: Option<MessageId>,
+}
+
+impl CallbackState {
+    /// the wizard on the message of the pressed button, as the user who pressed it.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn from_query(storage: Arc<WizardStorage>, q: CallbackQuery) -> Option<Self> {
+        let msg = q.regular_message()?;
+        Some(Self {
+            storage,
+            chat_id: msg.chat.id,
+            user_id: q.from.id,
+            msg_id: Some(msg.id),
+        })
+    }
+
+    /// the latest wizard of the sender of the message, which takes text replies.
+    ///
+    /// messages without a sender, eg. channel posts, or whose wizard can't be read
+    /// are handled as if no wizard is open, so that commands and chat still go through.
+    pub async fn from_message(storage: Arc<WizardStorage>, msg: Message) -> Self {
+        let user_id = msg.from.as_ref().map_or(UserId(0), |x| x.id);
+        let msg_id = match msg.from {
+            Some(_) => storage
+                .latest(msg.chat.id, user_id)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("error reading the wizard of the message: {e:#?}");
+                    None
+                }),
+            None => None,
+        };
+        Self {
+            storage,
+            chat_id: msg.chat.id,
+            user_id,
+            msg_id,
+        }
+    }
+
+    /// whether the wizard was started by someone other than the user.
+    pub async fn is_foreign(p: Self) -> bool {
+        let Some(msg_id) = p.msg_id else {
+            return false;
+        };
+        match p.storage.owner(p.chat_id, msg_id).await {
+            Ok(owner) => owner.is_some_and(|x| x != p.user_id),
+            Err(e) => {
+                tracing::error!("{e:#?}");
+                false
+            }
+        }
+    }
+
+    /// the message showing the wizard.
+    pub fn msg_id(&self) -> Option<MessageId> {
+        self.msg_id
+    }
+
+    /// the page of the wizard, which is [`CallbackPage::Expired`] when there is none.
+    pub async fn get_or_default(&self) -> Result<CallbackPage, StorageError> {
+        let Some(msg_id) = self.msg_id else {
+            return Ok(CallbackPage::default());
+        };
+        let page = self
+            .storage
+            .page(self.chat_id, msg_id, self.user_id)
+            .await?;
+        Ok(page.unwrap_or_default())
+    }
+
+    pub async fn update(&self, page: CallbackPage) -> Result<(), StorageError> {
+        let msg_id = self.msg_id.ok_or(StorageError::DialogueNotFound)?;
+        self.storage
+            .save(self.chat_id, msg_id, self.user_id, page)
+            .await
+    }
+
+    /// starts another wizard of the user on the newly sent message `msg_id`.
+    pub async fn start(&self, msg_id: MessageId, page: CallbackPage) -> Result<(), StorageError> {
+        self.storage
+            .save(self.chat_id, msg_id, self.user_id, page)
+            .await
+    }
+
+    /// carries on the wizard on the newly sent message `msg_id`, eg. after a text reply.
+    pub async fn move_to(&self, msg_id: MessageId, page: CallbackPage) -> Result<(), StorageError> {
+        self.reset().await?;
+        self.start(msg_id, page).await
+    }
+
+    pub async fn reset(&self) -> Result<(), StorageError> {
+        match self.msg_id {
+            Some(msg_id) => self.storage.remove(self.chat_id, msg_id).await,
+            None => Ok(()),
+        }
+    }
+}